@@ -24,7 +24,7 @@ fn server() -> tide::Server<State> {
 /// case, JSON (en/de)coding devices.
 #[derive(Clone)]
 struct State {
-    codec: json::Codec<app::slr::Message, json::ConfigPrettyPrint>,
+    codec: json::Codec<app::slr::Message, json::ConfigSettable>,
 }
 
 impl State {
@@ -36,8 +36,12 @@ impl State {
 impl Default for State {
     fn default() -> Self {
         let dictionary = Dictionary::from_version(Version::Fix42);
+        // Custom fields are relayed by tag number instead of being dropped.
+        let mut config = json::ConfigSettable::new();
+        config.set_pretty_print(true);
+        config.set_allow_unknown_fields(true);
         Self {
-            codec: json::Codec::new(dictionary, json::ConfigPrettyPrint),
+            codec: json::Codec::new(dictionary, config),
         }
     }
 }
@@ -0,0 +1,258 @@
+//! Encoding and decoding benchmarks for all available codecs.
+//!
+//! Run with `cargo bench`. Every codec is measured against the same couple of
+//! representative messages: a Heartbeat <0> and a Market Data - Snapshot/Full
+//! Refresh <W> with a repeating group.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use fefix::app::{slr, Version};
+use fefix::codec::fast::{Fast, Templates};
+use fefix::codec::{json, tagvalue, Decoder, Encoder};
+use fefix::Dictionary;
+
+const TAGVALUE_HEARTBEAT: &str =
+    "8=FIX.4.4|9=58|35=0|49=SENDER|56=TARGET|34=4567|52=20160802-21:14:38.717|10=011|";
+
+const TAGVALUE_MD_SNAPSHOT: &str = "8=FIX.4.4|9=163|35=W|34=4567|49=SENDER|52=20160802-21:14:38.717|56=TARGET|22=8|48=ESU6|262=789|268=2|269=0|270=1.50|271=75|273=21:14:38.688|269=1|270=1.75|271=25|273=21:14:38.688|10=031|";
+
+const JSON_HEARTBEAT: &str = r#"
+{
+    "Header": {
+        "BeginString": "FIX.4.4",
+        "MsgType": "0",
+        "MsgSeqNum": "4567",
+        "SenderCompID": "SENDER",
+        "TargetCompID": "TARGET",
+        "SendingTime": "20160802-21:14:38.717"
+    },
+    "Body": {},
+    "Trailer": {}
+}
+"#;
+
+const JSON_MD_SNAPSHOT: &str = r#"
+{
+    "Header": {
+        "BeginString": "FIX.4.4",
+        "MsgType": "W",
+        "MsgSeqNum": "4567",
+        "SenderCompID": "SENDER",
+        "TargetCompID": "TARGET",
+        "SendingTime": "20160802-21:14:38.717"
+    },
+    "Body": {
+        "SecurityIDSource": "8",
+        "SecurityID": "ESU6",
+        "MDReqID": "789",
+        "NoMDEntries": [
+            { "MDEntryType": "0", "MDEntryPx": "1.50", "MDEntrySize": "75", "MDEntryTime": "21:14:38.688" },
+            { "MDEntryType": "1", "MDEntryPx": "1.75", "MDEntrySize": "25", "MDEntryTime": "21:14:38.688" }
+        ]
+    },
+    "Trailer": {}
+}
+"#;
+
+/// FAST has no repeating groups of its own yet, so the snapshot template only
+/// carries the first entry of `NoMDEntries <268>`.
+const FAST_TEMPLATES: &str = r#"
+<templates>
+  <template name="Heartbeat" id="1">
+    <string name="MsgType" id="35"/>
+    <uInt32 name="MsgSeqNum" id="34"/>
+    <string name="SenderCompID" id="49"/>
+    <string name="TargetCompID" id="56"/>
+    <string name="SendingTime" id="52"/>
+  </template>
+  <template name="MarketDataSnapshotFullRefresh" id="2">
+    <string name="MsgType" id="35"/>
+    <uInt32 name="MsgSeqNum" id="34"/>
+    <string name="SenderCompID" id="49"/>
+    <string name="TargetCompID" id="56"/>
+    <string name="SendingTime" id="52"/>
+    <string name="SecurityIDSource" id="22"/>
+    <string name="SecurityID" id="48"/>
+    <string name="MDReqID" id="262"/>
+    <sequence name="MDEntries">
+      <string name="MDEntryType" id="269"/>
+      <decimal name="MDEntryPx" id="270"/>
+      <decimal name="MDEntrySize" id="271"/>
+      <string name="MDEntryTime" id="273"/>
+    </sequence>
+  </template>
+</templates>"#;
+
+fn dict_fix44() -> Dictionary {
+    Dictionary::from_version(Version::Fix44)
+}
+
+fn heartbeat() -> slr::Message {
+    let mut message = slr::Message::new();
+    message.add_str(8, "FIX.4.4");
+    message.add_str(35, "0");
+    message.add_str(49, "SENDER");
+    message.add_str(56, "TARGET");
+    message.add_int(34, 4567);
+    message.add_str(52, "20160802-21:14:38.717");
+    message
+}
+
+fn md_snapshot() -> slr::Message {
+    let mut message = slr::Message::new();
+    message.add_str(8, "FIX.4.4");
+    message.add_str(35, "W");
+    message.add_str(49, "SENDER");
+    message.add_str(56, "TARGET");
+    message.add_int(34, 4567);
+    message.add_str(52, "20160802-21:14:38.717");
+    message.add_str(22, "8");
+    message.add_str(48, "ESU6");
+    message.add_str(262, "789");
+    message.add_str(269, "0");
+    message.add_str(270, "1.50");
+    message.add_str(271, "75");
+    message.add_str(273, "21:14:38.688");
+    message
+}
+
+fn tagvalue_codec() -> tagvalue::Codec<slr::Message, tagvalue::ConfigVerticalSlash> {
+    tagvalue::Codec::with_dict(dict_fix44(), tagvalue::ConfigVerticalSlash)
+}
+
+/// Looks up every field in the dictionary, which hits the field cache of the
+/// codec after the first decode.
+#[derive(Debug, Clone)]
+struct ConfigValidating;
+
+impl tagvalue::Config for ConfigValidating {
+    type ChecksumAlgo = tagvalue::ChecksumAlgoDefault;
+    type TagLookup = tagvalue::TagLookupPredetermined;
+
+    const SOH_SEPARATOR: u8 = b'|';
+    const WARN_ON_DEPRECATED_FIELDS: bool = true;
+    const VALIDATE_TYPES: bool = true;
+}
+
+fn json_codec() -> json::Codec<slr::Message, json::ConfigSettable> {
+    json::Codec::new(dict_fix44(), json::ConfigSettable::default())
+}
+
+fn tagvalue_benches(c: &mut Criterion) {
+    let mut codec = tagvalue_codec();
+    c.bench_function("tagvalue decode Heartbeat", |b| {
+        b.iter(|| {
+            codec
+                .decode(black_box(TAGVALUE_HEARTBEAT.as_bytes()))
+                .unwrap();
+        })
+    });
+    c.bench_function("tagvalue decode MarketDataSnapshotFullRefresh", |b| {
+        b.iter(|| {
+            codec
+                .decode(black_box(TAGVALUE_MD_SNAPSHOT.as_bytes()))
+                .unwrap();
+        })
+    });
+    // A fresh codec for every message, i.e. without any cached lookups, as a
+    // baseline for the one below.
+    let dict = dict_fix44();
+    c.bench_function(
+        "tagvalue decode MarketDataSnapshotFullRefresh with validation (cold cache)",
+        |b| {
+            b.iter_batched(
+                || tagvalue::Codec::<slr::Message, _>::with_dict(dict.clone(), ConfigValidating),
+                |mut codec| {
+                    codec
+                        .decode(black_box(TAGVALUE_MD_SNAPSHOT.as_bytes()))
+                        .unwrap();
+                    codec
+                },
+                BatchSize::SmallInput,
+            )
+        },
+    );
+    let mut validating_codec =
+        tagvalue::Codec::<slr::Message, _>::with_dict(dict_fix44(), ConfigValidating);
+    c.bench_function(
+        "tagvalue decode MarketDataSnapshotFullRefresh with validation",
+        |b| {
+            b.iter(|| {
+                validating_codec
+                    .decode(black_box(TAGVALUE_MD_SNAPSHOT.as_bytes()))
+                    .unwrap();
+            })
+        },
+    );
+    c.bench_function("tagvalue scan MarketDataSnapshotFullRefresh", |b| {
+        b.iter(|| {
+            for field in tagvalue::scan(black_box(TAGVALUE_MD_SNAPSHOT.as_bytes()), b'|') {
+                black_box(field);
+            }
+        })
+    });
+    let message = heartbeat();
+    let mut buffer = Vec::new();
+    c.bench_function("tagvalue encode Heartbeat", |b| {
+        b.iter(|| {
+            buffer.clear();
+            codec.encode(&mut buffer, black_box(&message)).unwrap();
+        })
+    });
+}
+
+fn json_benches(c: &mut Criterion) {
+    let mut codec = json_codec();
+    c.bench_function("json decode Heartbeat", |b| {
+        b.iter(|| {
+            codec.decode(black_box(JSON_HEARTBEAT.as_bytes())).unwrap();
+        })
+    });
+    c.bench_function("json decode MarketDataSnapshotFullRefresh", |b| {
+        b.iter(|| {
+            codec.decode(black_box(JSON_MD_SNAPSHOT.as_bytes())).unwrap();
+        })
+    });
+    for (name, json_message) in &[
+        ("json encode Heartbeat", JSON_HEARTBEAT),
+        ("json encode MarketDataSnapshotFullRefresh", JSON_MD_SNAPSHOT),
+    ] {
+        let message = json_codec()
+            .decode(json_message.as_bytes())
+            .unwrap()
+            .clone();
+        let mut buffer = Vec::new();
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                buffer.clear();
+                codec.encode(&mut buffer, black_box(&message)).unwrap();
+            })
+        });
+    }
+}
+
+fn fast_benches(c: &mut Criterion) {
+    let templates = Templates::from_xml(FAST_TEMPLATES).unwrap();
+    for (name, template_id, message) in &[
+        ("Heartbeat", 1, heartbeat()),
+        ("MarketDataSnapshotFullRefresh", 2, md_snapshot()),
+    ] {
+        let template = templates.get_by_id(*template_id).unwrap();
+        c.bench_function(&format!("fast encode {}", name), |b| {
+            b.iter(|| {
+                template.encode_message(black_box(message)).unwrap();
+            })
+        });
+        // Each message carries its template identifier and no operator uses
+        // previous values, so every iteration decodes the same way.
+        let bytes = template.encode_message(message).unwrap();
+        let mut decoder = Fast::new().with_templates(templates.clone());
+        c.bench_function(&format!("fast decode {}", name), |b| {
+            b.iter(|| {
+                decoder.decode(black_box(&bytes[..])).unwrap();
+            })
+        });
+    }
+}
+
+criterion_group!(benches, tagvalue_benches, json_benches, fast_benches);
+criterion_main!(benches);
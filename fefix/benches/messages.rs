@@ -0,0 +1,49 @@
+//! Field lookup benchmarks for all available message representations.
+//!
+//! Run with `cargo bench`. Every representation is queried for the same
+//! handful of tags of a Market Data - Snapshot/Full Refresh <W>.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fefix::app::slr;
+
+/// A mix of header and body fields, plus a missing one.
+const TAGS: &[i64] = &[8, 35, 34, 49, 52, 55, 262, 1];
+
+fn md_snapshot() -> slr::Message {
+    let mut message = slr::Message::new();
+    message.add_str(8, "FIX.4.4");
+    message.add_str(35, "W");
+    message.add_int(34, 4567);
+    message.add_str(49, "SENDER");
+    message.add_str(52, "20160802-21:14:38.717");
+    message.add_str(56, "TARGET");
+    message.add_str(22, "8");
+    message.add_str(48, "ESU6");
+    message.add_str(55, "ES");
+    message.add_str(262, "789");
+    message.add_str(1128, "9");
+    message.add_str(1300, "XCME");
+    message
+}
+
+fn lookup_benches(c: &mut Criterion) {
+    let message = md_snapshot();
+    c.bench_function("slr::Message lookup", |b| {
+        b.iter(|| {
+            for tag in black_box(TAGS) {
+                black_box(message.get_field(*tag));
+            }
+        })
+    });
+    let indexed = slr::IndexedMessage::from(&message);
+    c.bench_function("slr::IndexedMessage lookup", |b| {
+        b.iter(|| {
+            for tag in black_box(TAGS) {
+                black_box(indexed.get(*tag as u32));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, lookup_benches);
+criterion_main!(benches);
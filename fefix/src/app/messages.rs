@@ -0,0 +1,167 @@
+//! Hand-written, strongly-typed FIX messages. They show what code generation
+//! should eventually produce for all messages of a
+//! [`Dictionary`](crate::Dictionary).
+
+use crate::app::slr;
+use crate::dt;
+
+/// A `Heartbeat <0>` message and its standard header fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heartbeat {
+    /// `BeginString <8>`.
+    pub begin_string: String,
+    /// `SenderCompID <49>`.
+    pub sender_comp_id: String,
+    /// `TargetCompID <56>`.
+    pub target_comp_id: String,
+    /// `MsgSeqNum <34>`.
+    pub msg_seq_num: u64,
+    /// `SendingTime <52>`, as found in the message.
+    pub sending_time: String,
+    /// `TestReqID <112>`, only present in responses to `TestRequest <1>`.
+    pub test_req_id: Option<String>,
+}
+
+impl Heartbeat {
+    /// The `MsgType <35>` of all [`Heartbeat`]s.
+    pub const MSG_TYPE: &'static str = "0";
+
+    /// Reads a [`Heartbeat`] from `message`. Fields that are not part of
+    /// [`Heartbeat`] are ignored.
+    pub fn from_message(message: &slr::Message) -> Result<Self, FromMessageError> {
+        if message.msg_type() != Some(Self::MSG_TYPE) {
+            return Err(FromMessageError::InvalidMsgType);
+        }
+        Ok(Self {
+            begin_string: required(message, 8, string)?,
+            sender_comp_id: required(message, 49, string)?,
+            target_comp_id: required(message, 56, string)?,
+            msg_seq_num: required(message, 34, seq_num)?,
+            sending_time: required(message, 52, string)?,
+            test_req_id: optional(message, 112, string)?,
+        })
+    }
+
+    /// Converts `self` to a schema-less [`slr::Message`].
+    pub fn to_message(&self) -> slr::Message {
+        let mut message = slr::Message::new();
+        message.add_str(8, self.begin_string.as_str());
+        message.add_str(35, Self::MSG_TYPE);
+        message.add_str(49, self.sender_comp_id.as_str());
+        message.add_str(56, self.target_comp_id.as_str());
+        message.add_int(34, self.msg_seq_num as i64);
+        message.add_str(52, self.sending_time.as_str());
+        if let Some(test_req_id) = &self.test_req_id {
+            message.add_str(112, test_req_id.as_str());
+        }
+        message
+    }
+}
+
+/// The error type returned by [`Heartbeat::from_message`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FromMessageError {
+    /// `MsgType <35>` is missing or doesn't match the typed message.
+    InvalidMsgType,
+    /// The required field with this tag is missing.
+    MissingField(u32),
+    /// The field with this tag has a value of the wrong type.
+    InvalidValue(u32),
+}
+
+fn optional<T>(
+    message: &slr::Message,
+    tag: u32,
+    convert: fn(&slr::FixFieldValue) -> Option<T>,
+) -> Result<Option<T>, FromMessageError> {
+    match message.get_field(tag) {
+        Some(value) => convert(value)
+            .map(Some)
+            .ok_or(FromMessageError::InvalidValue(tag)),
+        None => Ok(None),
+    }
+}
+
+fn required<T>(
+    message: &slr::Message,
+    tag: u32,
+    convert: fn(&slr::FixFieldValue) -> Option<T>,
+) -> Result<T, FromMessageError> {
+    optional(message, tag, convert)?.ok_or(FromMessageError::MissingField(tag))
+}
+
+fn string(value: &slr::FixFieldValue) -> Option<String> {
+    match value {
+        slr::FixFieldValue::String(s) => Some(s.clone()),
+        slr::FixFieldValue::Value(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn seq_num(value: &slr::FixFieldValue) -> Option<u64> {
+    match value {
+        slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(n))) if *n >= 0 => Some(*n as u64),
+        slr::FixFieldValue::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codec::tagvalue::{Codec, ConfigVerticalSlash};
+    use crate::codec::Decoder;
+
+    fn heartbeat() -> Heartbeat {
+        Heartbeat {
+            begin_string: "FIX.4.2".to_string(),
+            sender_comp_id: "A".to_string(),
+            target_comp_id: "B".to_string(),
+            msg_seq_num: 12,
+            sending_time: "20100304-07:59:30".to_string(),
+            test_req_id: Some("TEST-1".to_string()),
+        }
+    }
+
+    #[test]
+    fn heartbeat_round_trip() {
+        let heartbeat = heartbeat();
+        let message = heartbeat.to_message();
+        assert_eq!(message.msg_type(), Some("0"));
+        assert_eq!(Heartbeat::from_message(&message), Ok(heartbeat));
+    }
+
+    #[test]
+    fn decoded_heartbeat() {
+        let mut codec = Codec::<slr::Message, _>::new(ConfigVerticalSlash);
+        let message = codec
+            .decode(b"8=FIX.4.2|9=42|35=0|49=A|56=B|34=12|52=20100304-07:59:30|10=185|")
+            .unwrap();
+        let expected = Heartbeat {
+            test_req_id: None,
+            ..heartbeat()
+        };
+        assert_eq!(Heartbeat::from_message(message), Ok(expected));
+    }
+
+    #[test]
+    fn heartbeat_from_other_messages_is_an_error() {
+        let mut message = heartbeat().to_message();
+        message.add_str(35, "1");
+        assert_eq!(
+            Heartbeat::from_message(&message),
+            Err(FromMessageError::InvalidMsgType)
+        );
+        message.add_str(35, "0");
+        message.add_field(112, slr::FixFieldValue::Group(vec![]));
+        assert_eq!(
+            Heartbeat::from_message(&message),
+            Err(FromMessageError::InvalidValue(112))
+        );
+        message.fields.remove(&49);
+        assert_eq!(
+            Heartbeat::from_message(&message),
+            Err(FromMessageError::MissingField(49))
+        );
+    }
+}
@@ -4,6 +4,7 @@ use rust_embed::RustEmbed;
 use std::fmt;
 
 pub mod fix42;
+pub mod messages;
 pub mod slr;
 
 pub trait FieldsIterator {
@@ -24,7 +25,7 @@ pub trait TsrMessageRef: Default
 }
 
 /// Which [`Dictionary`](fefix::Dictionary) version to use.
-#[derive(Copy, Debug, Clone)]
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum Version {
     Fix40,
@@ -44,7 +45,12 @@ impl Version {
     /// content. The QuickFix definition files are extracted and decompressed
     /// from the binary without filesystem access.
     pub fn get_quickfix_spec(&self) -> String {
-        let filename = match self {
+        let filename = self.quickfix_spec_filename();
+        Self::get_embedded_spec(filename).expect(filename)
+    }
+
+    fn quickfix_spec_filename(&self) -> &'static str {
+        match self {
             Version::Fix40 => "FIX-4.0.xml",
             Version::Fix41 => "FIX-4.1.xml",
             Version::Fix42 => "FIX-4.2.xml",
@@ -54,12 +60,48 @@ impl Version {
             Version::Fix50SP1 => "FIX-5.0-SP1.xml",
             Version::Fix50SP2 => "FIX-5.0-SP2.xml",
             Version::Fixt11 => "FIXT-1.1.xml",
-        };
-        let xml_spec = QuickFixDicts::get(filename).expect(filename);
-        std::str::from_utf8(&*xml_spec).unwrap().to_string()
+        }
+    }
+
+    /// Returns the names of all QuickFIX definition files that are embedded in
+    /// the binary, in alphabetical order. They can be read with
+    /// [`Version::get_embedded_spec`].
+    ///
+    /// ```
+    /// use fefix::app::Version;
+    ///
+    /// assert!(Version::list_embedded_specs().contains(&"FIX-4.4.xml"));
+    /// ```
+    pub fn list_embedded_specs() -> Vec<&'static str> {
+        // There's one embedded file per version.
+        let mut names: Vec<&'static str> = Version::all()
+            .map(|version| version.quickfix_spec_filename())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Returns the contents of the embedded QuickFIX definition file called
+    /// `name`, if any. See [`Version::list_embedded_specs`].
+    pub fn get_embedded_spec(name: &str) -> Option<String> {
+        let xml_spec = QuickFixDicts::get(name)?;
+        Some(String::from_utf8_lossy(&*xml_spec).into_owned())
+    }
+
+    /// Returns the value of `BeginString <8>` in messages of `self`. FIX 5.0+
+    /// messages are framed by the FIXT.1.1 session layer and thus all share
+    /// the same `BeginString <8>`.
+    pub fn begin_string(&self) -> &'static str {
+        match self {
+            Version::Fix40 => "FIX.4.0",
+            Version::Fix41 => "FIX.4.1",
+            Version::Fix42 => "FIX.4.2",
+            Version::Fix43 => "FIX.4.3",
+            Version::Fix44 => "FIX.4.4",
+            Version::Fix50 | Version::Fix50SP1 | Version::Fix50SP2 | Version::Fixt11 => "FIXT.1.1",
+        }
     }
 
-    #[cfg(test)]
     pub(crate) fn all() -> impl Iterator<Item = Self> {
         vec![
             Version::Fix40,
@@ -118,6 +160,21 @@ mod test {
         assert_eq!(set.len(), Version::all().count());
     }
 
+    #[test]
+    fn embedded_specs_match_versions() {
+        let mut names: Vec<String> = Version::all()
+            .map(|version| format!("{}.xml", version))
+            .collect();
+        names.sort();
+        assert_eq!(Version::list_embedded_specs(), names);
+        let mut embedded: Vec<String> = QuickFixDicts::iter()
+            .map(|name| name.into_owned())
+            .collect();
+        embedded.sort();
+        assert_eq!(embedded, names);
+        assert!(Version::get_embedded_spec("FIX-9.9.xml").is_none());
+    }
+
     #[test]
     fn all_versions_have_xml_valid_quickfix_spec() {
         assert!(Version::all()
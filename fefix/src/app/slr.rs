@@ -2,17 +2,27 @@
 
 use crate::app::slr;
 use crate::app::TsrMessageRef;
-use crate::dt::{self, DataTypeValue};
-use std::collections::BTreeMap;
+use crate::codec::fast::Decimal;
+use crate::dictionary::{Dictionary, LayoutItem, LayoutItemKind, ValidationError};
+use crate::dt::{self, DataType, DataTypeValue};
+use crate::session::SessionRejectReason;
+use chrono::NaiveDateTime;
+use std::collections::{BTreeMap, BTreeSet};
 use std::time::SystemTime;
 
+/// A single entry of a repeating group, i.e. its fields by tag.
+pub type GroupEntry = BTreeMap<i64, FixFieldValue>;
+
 /// An owned value of a FIX field.
 #[derive(Clone, Debug, PartialEq)]
 pub enum FixFieldValue {
     String(String),
     Data(Vec<u8>),
     Value(DataTypeValue),
-    Group(Vec<BTreeMap<i64, FixFieldValue>>),
+    Group(Vec<GroupEntry>),
+    /// Space-separated values of a `MultipleStringValue` or `MultipleCharValue`
+    /// field, e.g. `ExecInst <18>`.
+    MultiValue(Vec<String>),
 }
 
 impl From<i64> for FixFieldValue {
@@ -75,6 +85,19 @@ impl From<SystemTime> for FixFieldValue {
     }
 }
 
+impl FixFieldValue {
+    /// Returns the unit and amount of `self` if it's a well-formed `Tenor`
+    /// value (e.g. `M3`), `None` otherwise.
+    pub fn as_tenor(&self) -> Option<(dt::TenorUnit, u32)> {
+        let tenor = match self {
+            FixFieldValue::Value(DataTypeValue::Tenor(tenor)) => tenor.clone(),
+            FixFieldValue::String(s) => dt::Tenor::parse(s.as_bytes())?,
+            _ => return None,
+        };
+        Some((tenor.unit(), tenor.amount()))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Field {
     tag: i64,
@@ -176,6 +199,77 @@ impl PushyMessage {
     }
 }
 
+/// FIX message, backed by a [`Vec`] of fields sorted by tag. Lookups are
+/// binary searches over contiguous memory, which makes [`IndexedMessage`]
+/// faster than [`Message`] for read-heavy workloads. Insertions are slower
+/// though, as they require shifting fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexedMessage {
+    fields: Vec<(u32, FixFieldValue)>,
+}
+
+impl IndexedMessage {
+    /// Creates a new [`IndexedMessage`] without any fields.
+    pub fn new() -> Self {
+        Self { fields: Vec::new() }
+    }
+
+    /// Returns the value of the field with `tag`, if any.
+    pub fn get(&self, tag: u32) -> Option<&FixFieldValue> {
+        self.fields
+            .binary_search_by_key(&tag, |(t, _)| *t)
+            .ok()
+            .map(|i| &self.fields[i].1)
+    }
+
+    /// Sets the field with `tag` to `value`, replacing its previous value.
+    pub fn insert(&mut self, tag: u32, value: FixFieldValue) {
+        match self.fields.binary_search_by_key(&tag, |(t, _)| *t) {
+            Ok(i) => self.fields[i].1 = value,
+            Err(i) => self.fields.insert(i, (tag, value)),
+        }
+    }
+
+    /// Returns an [`Iterator`] over all fields in `self`, in ascending tag
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &FixFieldValue)> {
+        self.fields.iter().map(|(tag, value)| (*tag, value))
+    }
+
+    /// Returns the number of fields in `self`.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns `true` if `self` has no fields, `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl From<&Message> for IndexedMessage {
+    fn from(message: &Message) -> Self {
+        // `BTreeMap` iteration is already sorted by tag.
+        Self {
+            fields: message
+                .fields
+                .iter()
+                .map(|(tag, value)| (*tag as u32, value.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl TsrMessageRef for IndexedMessage {
+    fn get_field(&self, msg_type: u32) -> Option<&slr::FixFieldValue> {
+        self.get(msg_type)
+    }
+
+    fn set_field(&mut self, msg_type: u32, val: slr::FixFieldValue) {
+        self.insert(msg_type, val)
+    }
+}
+
 impl TsrMessageRef for Message {
     fn get_field(&self, msg_type: u32) -> Option<&slr::FixFieldValue> {
         self.fields.get(&(msg_type as i64))
@@ -202,6 +296,11 @@ impl Message {
         }
     }
 
+    /// Removes all fields from `self`.
+    pub fn clear(&mut self) {
+        self.fields.clear();
+    }
+
     /// Adds a field to `self`.
     pub fn add_field<K: Into<i64>>(&mut self, tag: K, value: slr::FixFieldValue) {
         self.fields.insert(tag.into(), value);
@@ -221,6 +320,23 @@ impl Message {
         self.fields.get(&tag.into())
     }
 
+    /// Returns the value of the field named `name` in `dict` (e.g.
+    /// `"MsgType"`), if both the field definition and the field itself exist.
+    pub fn get_by_name(&self, name: &str, dict: &Dictionary) -> Option<&slr::FixFieldValue> {
+        let field = dict.field_by_name(name)?;
+        self.get_field(field.tag())
+    }
+
+    /// Returns an [`Iterator`] over all repeating groups in `self`, as pairs of
+    /// "NumInGroup" tag and group entries. Nested groups are not included, but
+    /// they can be found inside the entries of their parent group.
+    pub fn groups(&self) -> impl Iterator<Item = (u32, &[GroupEntry])> {
+        self.fields.iter().filter_map(|(tag, value)| match value {
+            FixFieldValue::Group(entries) => Some((*tag as u32, &entries[..])),
+            _ => None,
+        })
+    }
+
     pub fn msg_type(&self) -> Option<&str> {
         match self.fields.get(&35) {
             Some(FixFieldValue::String(s)) => Some(s.as_str()),
@@ -244,4 +360,797 @@ impl Message {
             _ => Some(false),
         }
     }
+
+    /// Returns the value of the field with `tag` as a fixed-point [`Decimal`],
+    /// if it's present and holds a valid decimal number. `Price` and `Qty`
+    /// fields should be read this way, as `f64` would lose precision.
+    pub fn get_decimal<K: Into<i64>>(&self, tag: K) -> Option<Decimal> {
+        match self.get_field(tag)? {
+            FixFieldValue::String(s) => s.parse().ok(),
+            FixFieldValue::Value(DataTypeValue::Price(dt::Price(price))) => Some(*price),
+            FixFieldValue::Value(DataTypeValue::Qty(dt::Qty(qty))) => Some(*qty),
+            FixFieldValue::Value(DataTypeValue::Int(dt::Int(n))) => {
+                Some(Decimal::new(i64::from(*n), 0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the value of `Price <44>`, if any.
+    pub fn price(&self) -> Option<Decimal> {
+        self.get_decimal(44)
+    }
+
+    /// Returns the value of `OrderQty <38>`, if any.
+    pub fn order_qty(&self) -> Option<Decimal> {
+        self.get_decimal(38)
+    }
+
+    /// Marks `self` as a possible duplicate of a message that was originally
+    /// sent at `orig_sending_time`, i.e. sets `PossDupFlag <43>` to "Y" and
+    /// `OrigSendingTime <122>`. This is necessary before resending a message.
+    pub fn set_poss_dup<S: Into<String>>(&mut self, orig_sending_time: S) {
+        self.add_str(43, "Y");
+        self.add_str(122, orig_sending_time);
+    }
+
+    /// Returns `true` if `PossDupFlag <43>` is set to "Y", `false` otherwise.
+    pub fn poss_dup(&self) -> bool {
+        match self.fields.get(&43) {
+            Some(FixFieldValue::String(s)) => s == "Y",
+            Some(f) => *f == FixFieldValue::from('Y'),
+            None => false,
+        }
+    }
+
+    /// Sets `PossResend <97>` to "Y" if `poss_resend` is `true`, "N" otherwise.
+    /// Unlike possible duplicates, possible resends carry a new `MsgSeqNum
+    /// <34>` and must be deduplicated by the application, e.g. by `ClOrdID
+    /// <11>`.
+    pub fn set_poss_resend(&mut self, poss_resend: bool) {
+        self.add_str(97, if poss_resend { "Y" } else { "N" });
+    }
+
+    /// Returns `true` if `PossResend <97>` is set to "Y", `false` otherwise.
+    pub fn poss_resend(&self) -> bool {
+        match self.fields.get(&97) {
+            Some(FixFieldValue::String(s)) => s == "Y",
+            Some(f) => *f == FixFieldValue::from('Y'),
+            None => false,
+        }
+    }
+
+    /// Checks that `PossResend <97>`, if present, is either "Y" or "N".
+    pub fn validate_poss_resend(&self) -> Result<(), SessionRejectReason> {
+        match self.fields.get(&97) {
+            None => Ok(()),
+            Some(FixFieldValue::String(s)) if s == "Y" || s == "N" => Ok(()),
+            Some(f) if *f == FixFieldValue::from('Y') || *f == FixFieldValue::from('N') => Ok(()),
+            Some(_) => Err(SessionRejectReason::ValueIsIncorrect),
+        }
+    }
+
+    /// Checks that `OrigSendingTime <122>` is not later than `SendingTime
+    /// <52>`. `OrigSendingTime <122>` is only required for possible duplicates
+    /// (see [`Message::set_poss_dup`]).
+    pub fn validate_orig_sending_time(&self) -> Result<(), SendingTimeError> {
+        let orig_sending_time = match self.fields.get(&122) {
+            Some(value) => utc_timestamp(122, value)?,
+            None if self.poss_dup() => return Err(SendingTimeError::Missing(122)),
+            None => return Ok(()),
+        };
+        let sending_time = match self.fields.get(&52) {
+            Some(value) => utc_timestamp(52, value)?,
+            None => return Err(SendingTimeError::Missing(52)),
+        };
+        if orig_sending_time <= sending_time {
+            Ok(())
+        } else {
+            Err(SendingTimeError::OrigSendingTimeAfterSendingTime)
+        }
+    }
+
+    /// Creates a session-level `Reject <3>` message for an invalid inbound
+    /// message with `MsgSeqNum <34>` equal to `ref_seq_num`. `ref_tag` is the
+    /// tag number of the offending field, if any.
+    ///
+    /// Standard header fields other than `MsgType <35>` are left to the
+    /// session layer.
+    pub fn reject(ref_seq_num: u64, ref_tag: Option<u32>, reason: SessionRejectReason) -> Self {
+        let mut message = Self::new();
+        message.add_str(35, "3");
+        message.add_int(45, ref_seq_num as i64);
+        if let Some(ref_tag) = ref_tag {
+            message.add_int(371, ref_tag as i64);
+        }
+        message.add_int(373, u32::from(reason) as i64);
+        message
+    }
+
+    /// Creates a `BusinessMessageReject <j>` message for an application-level
+    /// message of type `ref_msg_type` and `MsgSeqNum <34>` equal to
+    /// `ref_seq_num`, which can't be rejected with a session-level
+    /// `Reject <3>`. `reason` is the `BusinessRejectReason <380>`, e.g. 3 for
+    /// "Unsupported message type".
+    ///
+    /// Standard header fields other than `MsgType <35>` are left to the
+    /// session layer.
+    pub fn business_reject(
+        ref_msg_type: &str,
+        ref_seq_num: u64,
+        reason: u32,
+        text: Option<&str>,
+    ) -> Self {
+        let mut message = Self::new();
+        message.add_str(35, "j");
+        message.add_int(45, ref_seq_num as i64);
+        message.add_str(372, ref_msg_type);
+        message.add_int(380, reason as i64);
+        if let Some(text) = text {
+            message.add_str(58, text);
+        }
+        message
+    }
+
+    /// Creates a `ResendRequest <2>` message for all messages from
+    /// `begin_seq_no` to `end_seq_no` (inclusive), e.g. after detecting a seq.
+    /// number gap. An `end_seq_no` of 0 means "infinity", i.e. all messages
+    /// after `begin_seq_no`.
+    ///
+    /// Standard header fields other than `MsgType <35>` are left to the
+    /// session layer.
+    pub fn resend_request(begin_seq_no: u64, end_seq_no: u64) -> Self {
+        let mut message = Self::new();
+        message.add_str(35, "2");
+        message.add_int(7, begin_seq_no as i64);
+        message.add_int(16, end_seq_no as i64);
+        message
+    }
+
+    /// Creates a `TestRequest <1>` message, which forces the counterparty to
+    /// send a `Heartbeat <0>` carrying the same `TestReqID <112>`.
+    ///
+    /// Standard header fields other than `MsgType <35>` are left to the
+    /// session layer.
+    pub fn test_request<S: Into<String>>(test_req_id: S) -> Self {
+        let mut message = Self::new();
+        message.add_str(35, "1");
+        message.add_str(112, test_req_id);
+        message
+    }
+
+    /// Creates a `Heartbeat <0>` message. `test_req_id` must be the
+    /// `TestReqID <112>` of the `TestRequest <1>` that this heartbeat responds
+    /// to, if any.
+    ///
+    /// Standard header fields other than `MsgType <35>` are left to the
+    /// session layer.
+    pub fn heartbeat(test_req_id: Option<&str>) -> Self {
+        let mut message = Self::new();
+        message.add_str(35, "0");
+        if let Some(test_req_id) = test_req_id {
+            message.add_str(112, test_req_id);
+        }
+        message
+    }
+
+    /// Creates a `Logout <5>` message, which initiates or confirms the
+    /// termination of a session. `text` is the reason for logging out, if any,
+    /// as `Text <58>`.
+    ///
+    /// Standard header fields other than `MsgType <35>` are left to the
+    /// session layer.
+    pub fn logout(text: Option<&str>) -> Self {
+        let mut message = Self::new();
+        message.add_str(35, "5");
+        if let Some(text) = text {
+            message.add_str(58, text);
+        }
+        message
+    }
+
+    /// Returns the value that `BodyLength <9>` would have if `self` were
+    /// encoded with `separator` as field delimiter, i.e. the number of bytes
+    /// after the delimiter of `BodyLength <9>` up to and including the
+    /// delimiter right before `CheckSum <10>`.
+    ///
+    /// `BeginString <8>`, `BodyLength <9>` and `CheckSum <10>` don't count
+    /// towards the body length, even if present in `self`.
+    pub fn body_length(&self, separator: u8) -> usize {
+        let mut body = Vec::new();
+        write_fields(&mut body, &self.fields, separator);
+        body.len()
+    }
+
+    /// Returns a canonical copy of `self`, such that two semantically equal
+    /// messages have equal normalized forms. Field values are canonicalized
+    /// according to their datatype in `dict` (e.g. "0042" and "42" are the same
+    /// int, "1.50" and "1.5" the same float) and repeating group entries are
+    /// sorted in a deterministic order. Fields that are unknown to `dict` or
+    /// that don't have a numeric datatype are left untouched.
+    pub fn normalized(&self, dict: &Dictionary) -> Message {
+        Message {
+            fields: normalized_fields(&self.fields, dict),
+        }
+    }
+
+    /// Returns a hash of the business payload of `self`, such that duplicate
+    /// messages hash equal regardless of wire differences: field order, numeric
+    /// formatting and `StandardHeader`/`StandardTrailer` fields (e.g.
+    /// `MsgSeqNum <34>`, `SendingTime <52>`) are all ignored. See
+    /// [`Message::normalized`] and [`Message::application_fields`].
+    ///
+    /// The hash is the 64-bit FNV-1a of the SOH-delimited tag-value encoding of
+    /// the normalized payload, so it doesn't depend on the platform nor on
+    /// the process. It may change across versions of this crate though, if
+    /// normalization rules do.
+    pub fn semantic_hash(&self, dict: &Dictionary) -> u64 {
+        let payload = self.application_fields(dict).normalized(dict);
+        let mut bytes = Vec::new();
+        write_fields(&mut bytes, &payload.fields, b'\x01');
+        bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    /// Returns a copy of `self` without the fields of `StandardHeader` and
+    /// `StandardTrailer` (e.g. `MsgSeqNum <34>`, `SendingTime <52>`,
+    /// `CheckSum <10>`) as defined by `dict`, i.e. only the business payload.
+    /// Fields that are unknown to `dict` are kept.
+    ///
+    /// FIX 5.0+ messages take their header and trailer from FIXT.1.1, so `dict`
+    /// should be the FIXT.1.1 dictionary.
+    pub fn application_fields(&self, dict: &Dictionary) -> Message {
+        let mut session_tags = BTreeSet::new();
+        for name in &["StandardHeader", "StandardTrailer"] {
+            if let Some(component) = dict.component_by_name(name) {
+                collect_layout_tags(component.items(), &mut session_tags);
+            }
+        }
+        Message {
+            fields: self
+                .fields
+                .iter()
+                .filter(|(tag, _)| !session_tags.contains(*tag))
+                .map(|(tag, value)| (*tag, value.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// Incrementally builds a [`Message`] with a given `MsgType <35>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageBuilder {
+    message: Message,
+}
+
+impl MessageBuilder {
+    /// Creates a new [`MessageBuilder`] for messages with `MsgType <35>` equal
+    /// to `msg_type`.
+    pub fn new<S: Into<String>>(msg_type: S) -> Self {
+        let mut message = Message::new();
+        message.add_str(35, msg_type);
+        Self { message }
+    }
+
+    /// Adds a field to the message.
+    pub fn add_field<K: Into<i64>>(&mut self, tag: K, value: FixFieldValue) -> &mut Self {
+        self.message.add_field(tag, value);
+        self
+    }
+
+    /// Adds a string field to the message.
+    pub fn add_str<K: Into<i64>, S: Into<String>>(&mut self, tag: K, value: S) -> &mut Self {
+        self.message.add_str(tag, value);
+        self
+    }
+
+    /// Adds an integer field to the message.
+    pub fn add_int<K: Into<i64>>(&mut self, tag: K, value: i64) -> &mut Self {
+        self.message.add_int(tag, value);
+        self
+    }
+
+    /// Returns the message built so far, without any validation.
+    pub fn build(&self) -> Message {
+        self.message.clone()
+    }
+
+    /// Returns the message built so far if it passes
+    /// [`Dictionary::validate_message`], i.e. if it has all required fields
+    /// for its `MsgType <35>`. Otherwise, returns all validation errors.
+    pub fn build_validated(&self, dict: &Dictionary) -> Result<Message, Vec<ValidationError>> {
+        let errors = dict.validation_errors(&self.message);
+        if errors.is_empty() {
+            Ok(self.build())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Collects the tags of all top-level fields in `items`, including those of
+/// components and the counters of repeating groups.
+pub(crate) fn collect_layout_tags<'a>(
+    items: impl Iterator<Item = LayoutItem<'a>>,
+    tags: &mut BTreeSet<i64>,
+) {
+    for item in items {
+        match item.kind() {
+            LayoutItemKind::Field(field) | LayoutItemKind::Group(field, _) => {
+                tags.insert(field.tag() as i64);
+            }
+            LayoutItemKind::Component(component) => {
+                collect_layout_tags(component.items(), tags);
+            }
+        }
+    }
+}
+
+/// The error type returned by [`Message::validate_orig_sending_time`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SendingTimeError {
+    /// The field with this tag is required but missing.
+    Missing(u32),
+    /// The field with this tag is not a valid `UTCTimestamp`.
+    Malformed(u32),
+    /// `OrigSendingTime <122>` is later than `SendingTime <52>`.
+    OrigSendingTimeAfterSendingTime,
+}
+
+pub(crate) fn utc_timestamp(
+    tag: u32,
+    value: &FixFieldValue,
+) -> Result<NaiveDateTime, SendingTimeError> {
+    match value {
+        // Milliseconds are optional.
+        FixFieldValue::String(s) => NaiveDateTime::parse_from_str(s, "%Y%m%d-%H:%M:%S%.f")
+            .map_err(|_| SendingTimeError::Malformed(tag)),
+        _ => Err(SendingTimeError::Malformed(tag)),
+    }
+}
+
+fn write_fields(buffer: &mut Vec<u8>, fields: &BTreeMap<i64, FixFieldValue>, separator: u8) {
+    for (tag, value) in fields {
+        if let 8..=10 = *tag {
+            continue;
+        }
+        buffer.extend_from_slice(tag.to_string().as_bytes());
+        buffer.push(b'=');
+        match value {
+            FixFieldValue::String(s) => buffer.extend_from_slice(s.as_bytes()),
+            FixFieldValue::Data(data) => buffer.extend_from_slice(&data[..]),
+            FixFieldValue::Value(value) => buffer.extend_from_slice(value.to_string().as_bytes()),
+            FixFieldValue::MultiValue(values) => {
+                buffer.extend_from_slice(values.join(" ").as_bytes())
+            }
+            FixFieldValue::Group(entries) => {
+                buffer.extend_from_slice(entries.len().to_string().as_bytes());
+                buffer.push(separator);
+                for entry in entries {
+                    write_fields(buffer, entry, separator);
+                }
+                continue;
+            }
+        }
+        buffer.push(separator);
+    }
+}
+
+fn normalized_fields(
+    fields: &BTreeMap<i64, FixFieldValue>,
+    dict: &Dictionary,
+) -> BTreeMap<i64, FixFieldValue> {
+    fields
+        .iter()
+        .map(|(tag, value)| (*tag, normalized_value(*tag, value, dict)))
+        .collect()
+}
+
+fn normalized_value(tag: i64, value: &FixFieldValue, dict: &Dictionary) -> FixFieldValue {
+    if let FixFieldValue::Group(entries) = value {
+        let mut entries: Vec<BTreeMap<i64, FixFieldValue>> = entries
+            .iter()
+            .map(|entry| normalized_fields(entry, dict))
+            .collect();
+        // Entries are sorted by their tag-value encoding, which is
+        // well-defined (unlike the `Debug` representation of `FixFieldValue`).
+        entries.sort_by_cached_key(|entry| {
+            let mut bytes = Vec::new();
+            write_fields(&mut bytes, entry, b'\x01');
+            bytes
+        });
+        return FixFieldValue::Group(entries);
+    }
+    let datatype = match dict.field_by_tag(tag as u32) {
+        Some(field) => field.exact_data_type(),
+        None => return value.clone(),
+    };
+    let text = match value {
+        FixFieldValue::String(s) => s.clone(),
+        FixFieldValue::Value(value) => value.to_string(),
+        _ => return value.clone(),
+    };
+    let normalized = match datatype {
+        DataType::Int
+        | DataType::Length
+        | DataType::NumInGroup
+        | DataType::SeqNum
+        | DataType::TagNum
+        | DataType::DayOfMonth => normalized_int(&text),
+        // Decimals are compared as fixed-point numbers, which (unlike `f32`
+        // and `f64`) don't hide differences past the 7th or 15th digit.
+        DataType::Float
+        | DataType::Amt
+        | DataType::Price
+        | DataType::PriceOffset
+        | DataType::Qty
+        | DataType::Percentage => text.parse::<Decimal>().ok().map(|d| d.to_string()),
+        _ => None,
+    };
+    match normalized {
+        Some(normalized) => FixFieldValue::String(normalized),
+        None => value.clone(),
+    }
+}
+
+/// Strips the sign and leading zeros of an integer of any size, e.g. "+0042"
+/// becomes "42". Returns `None` if `s` is not an integer.
+fn normalized_int(s: &str) -> Option<String> {
+    let (is_negative, digits) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = digits.trim_start_matches('0');
+    Some(match (is_negative, digits) {
+        (_, "") => "0".to_string(),
+        (true, digits) => format!("-{}", digits),
+        (false, digits) => digits.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::Version;
+
+    fn md_entry(entry_type: char, position: &str, orders: &str) -> BTreeMap<i64, FixFieldValue> {
+        let mut entry = BTreeMap::new();
+        entry.insert(269, FixFieldValue::from(entry_type));
+        entry.insert(290, FixFieldValue::String(position.to_string()));
+        entry.insert(346, FixFieldValue::String(orders.to_string()));
+        entry
+    }
+
+    #[test]
+    fn body_length_excludes_header_and_trailer_fields() {
+        let mut message = Message::new();
+        message.add_str(8, "FIX.4.2");
+        message.add_int(9, 42);
+        message.add_str(35, "0");
+        message.add_str(49, "A");
+        message.add_str(56, "B");
+        message.add_int(34, 12);
+        message.add_str(52, "20100304-07:59:30");
+        message.add_str(10, "185");
+        assert_eq!(message.body_length(b'|'), 42);
+        assert_eq!(message.body_length(0x1), 42);
+    }
+
+    #[test]
+    fn normalized_messages_with_different_formatting_are_equal() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut a = Message::new();
+        a.add_str(35, "W");
+        a.add_str(34, "0042");
+        a.add_str(231, "1.50");
+        a.add_field(
+            268,
+            FixFieldValue::Group(vec![md_entry('0', "1", "75"), md_entry('1', "2", "25")]),
+        );
+        let mut b = Message::new();
+        b.add_field(
+            268,
+            FixFieldValue::Group(vec![md_entry('1', "+02", "25"), md_entry('0', "1", "075")]),
+        );
+        b.add_str(231, "001.5");
+        b.add_int(34, 42);
+        b.add_str(35, "W");
+        assert_ne!(a, b);
+        assert_eq!(a.normalized(&dict), b.normalized(&dict));
+    }
+
+    #[test]
+    fn normalized_messages_with_different_values_are_not_equal() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut a = Message::new();
+        a.add_str(35, "W");
+        a.add_str(34, "42");
+        let mut b = a.clone();
+        b.add_str(34, "43");
+        assert_ne!(a.normalized(&dict), b.normalized(&dict));
+    }
+
+    #[test]
+    fn normalized_values_are_exact() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut a = Message::new();
+        a.add_str(35, "W");
+        a.add_str(34, "42");
+        // `Commission <12>` is an `Amt`, which an `f32` can't hold exactly.
+        a.add_str(12, "0.123456789");
+        let mut b = a.clone();
+        b.add_str(34, "4294967338");
+        assert_ne!(a.normalized(&dict), b.normalized(&dict));
+        let mut b = a.clone();
+        b.add_str(12, "0.123456788");
+        assert_ne!(a.normalized(&dict), b.normalized(&dict));
+        b.add_str(12, "0.1234567890");
+        assert_eq!(a.normalized(&dict), b.normalized(&dict));
+    }
+
+    #[test]
+    fn semantic_hash_ignores_formatting_and_session_fields() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut a = Message::new();
+        a.add_str(8, "FIX.4.4");
+        a.add_str(35, "D");
+        a.add_int(34, 1);
+        a.add_str(52, "20210101-10:00:00");
+        a.add_str(11, "ORD1");
+        a.add_str(38, "100");
+        a.add_str(44, "1.50");
+        let mut b = Message::new();
+        b.add_str(44, "01.5");
+        b.add_str(38, "100.00");
+        b.add_str(11, "ORD1");
+        b.add_str(52, "20210101-10:00:05");
+        b.add_int(34, 2);
+        b.add_str(35, "D");
+        b.add_str(8, "FIX.4.4");
+        assert_ne!(a, b);
+        assert_eq!(a.semantic_hash(&dict), b.semantic_hash(&dict));
+        b.add_str(38, "200");
+        assert_ne!(a.semantic_hash(&dict), b.semantic_hash(&dict));
+    }
+
+    #[test]
+    fn semantic_hash_of_large_integers_does_not_collide() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut a = Message::new();
+        a.add_str(35, "D");
+        a.add_str(38, "100");
+        // `NumDaysInterest <157>` is an `Int`.
+        a.add_str(157, "42");
+        let mut b = a.clone();
+        b.add_str(157, "4294967338");
+        assert_ne!(a.semantic_hash(&dict), b.semantic_hash(&dict));
+    }
+
+    #[test]
+    fn semantic_hash_is_fnv1a_of_tagvalue_payload() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = Message::new();
+        message.add_str(35, "D");
+        message.add_str(11, "ORD1");
+        // FNV-1a of "11=ORD1<SOH>".
+        assert_eq!(message.semantic_hash(&dict), 0x512814fd99e484e7);
+    }
+
+    #[test]
+    fn set_poss_dup_sets_flag_and_orig_sending_time() {
+        let mut message = Message::new();
+        message.add_str(35, "D");
+        message.add_str(52, "20210304-10:00:05.123");
+        assert!(!message.poss_dup());
+        assert_eq!(message.validate_orig_sending_time(), Ok(()));
+        message.set_poss_dup("20210304-10:00:00");
+        assert!(message.poss_dup());
+        assert_eq!(
+            message.get_field(122),
+            Some(&FixFieldValue::String("20210304-10:00:00".to_string()))
+        );
+        assert_eq!(message.validate_orig_sending_time(), Ok(()));
+    }
+
+    #[test]
+    fn build_validated_new_order_single_without_side() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut builder = MessageBuilder::new("D");
+        builder
+            .add_str(11, "ORDER-1")
+            .add_str(55, "EUR/USD")
+            .add_str(60, "20210304-10:00:05.123")
+            .add_str(38, "100")
+            .add_str(40, "1");
+        assert_eq!(
+            builder.build_validated(&dict),
+            Err(vec![ValidationError::MissingField(54)])
+        );
+        builder.add_str(54, "1");
+        assert_eq!(builder.build_validated(&dict), Ok(builder.build()));
+    }
+
+    #[test]
+    fn set_and_validate_poss_resend() {
+        let mut message = Message::new();
+        message.add_str(35, "D");
+        assert!(!message.poss_resend());
+        assert!(message.validate_poss_resend().is_ok());
+        message.set_poss_resend(true);
+        assert!(message.poss_resend());
+        assert!(message.validate_poss_resend().is_ok());
+        message.set_poss_resend(false);
+        assert!(!message.poss_resend());
+        assert!(message.validate_poss_resend().is_ok());
+        message.add_str(97, "X");
+        assert!(!message.poss_resend());
+        assert!(matches!(
+            message.validate_poss_resend(),
+            Err(SessionRejectReason::ValueIsIncorrect)
+        ));
+    }
+
+    #[test]
+    fn orig_sending_time_after_sending_time_is_invalid() {
+        let mut message = Message::new();
+        message.add_str(35, "D");
+        message.add_str(52, "20210304-10:00:05.123");
+        message.set_poss_dup("20210304-10:00:05.124");
+        assert_eq!(
+            message.validate_orig_sending_time(),
+            Err(SendingTimeError::OrigSendingTimeAfterSendingTime)
+        );
+        message.set_poss_dup("yesterday");
+        assert_eq!(
+            message.validate_orig_sending_time(),
+            Err(SendingTimeError::Malformed(122))
+        );
+        message.fields.remove(&122);
+        assert_eq!(
+            message.validate_orig_sending_time(),
+            Err(SendingTimeError::Missing(122))
+        );
+    }
+
+    #[test]
+    fn indexed_message_from_message() {
+        let mut message = Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(11, "ORD1");
+        message.add_int(34, 42);
+        let indexed = IndexedMessage::from(&message);
+        assert_eq!(indexed.len(), 4);
+        for (tag, value) in message.fields.iter() {
+            assert_eq!(indexed.get(*tag as u32), Some(value));
+        }
+        assert_eq!(indexed.get(1), None);
+        let tags: Vec<u32> = indexed.iter().map(|(tag, _)| tag).collect();
+        assert_eq!(tags, vec![8, 11, 34, 35]);
+    }
+
+    #[test]
+    fn indexed_message_insert_keeps_fields_sorted() {
+        let mut indexed = IndexedMessage::new();
+        indexed.insert(35, FixFieldValue::String("D".to_string()));
+        indexed.insert(8, FixFieldValue::String("FIX.4.4".to_string()));
+        indexed.insert(11, FixFieldValue::String("ORD1".to_string()));
+        indexed.insert(35, FixFieldValue::String("G".to_string()));
+        let tags: Vec<u32> = indexed.iter().map(|(tag, _)| tag).collect();
+        assert_eq!(tags, vec![8, 11, 35]);
+        assert_eq!(
+            indexed.get(35),
+            Some(&FixFieldValue::String("G".to_string()))
+        );
+    }
+
+    #[test]
+    fn reject_references_offending_message() {
+        let message = Message::reject(42, Some(55), SessionRejectReason::ValueIsIncorrect);
+        assert_eq!(message.msg_type(), Some("3"));
+        assert_eq!(message.get_field(45), Some(&FixFieldValue::from(42i64)));
+        assert_eq!(message.get_field(371), Some(&FixFieldValue::from(55i64)));
+        assert_eq!(message.get_field(373), Some(&FixFieldValue::from(5i64)));
+        let message = Message::reject(42, None, SessionRejectReason::InvalidMsgType);
+        assert_eq!(message.get_field(371), None);
+        assert_eq!(message.get_field(373), Some(&FixFieldValue::from(11i64)));
+    }
+
+    #[test]
+    fn business_reject_references_offending_message() {
+        let message = Message::business_reject("D", 42, 3, Some("Unsupported"));
+        assert_eq!(message.msg_type(), Some("j"));
+        assert_eq!(
+            message.get_field(372),
+            Some(&FixFieldValue::from("D".to_string()))
+        );
+        assert_eq!(message.get_field(45), Some(&FixFieldValue::from(42i64)));
+        assert_eq!(message.get_field(380), Some(&FixFieldValue::from(3i64)));
+        assert_eq!(
+            message.get_field(58),
+            Some(&FixFieldValue::from("Unsupported".to_string()))
+        );
+        let message = Message::business_reject("D", 42, 3, None);
+        assert_eq!(message.get_field(58), None);
+    }
+
+    #[test]
+    fn application_fields_of_new_order_single() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_int(34, 42);
+        message.add_str(49, "SENDER");
+        message.add_str(52, "20100225-19:41:57.316");
+        message.add_str(11, "ORD1");
+        message.add_str(55, "EUR/USD");
+        message.add_str(10, "000");
+        let payload = message.application_fields(&dict);
+        assert_eq!(payload.get_field(34), None);
+        assert_eq!(payload.get_field(10), None);
+        assert_eq!(
+            payload.get_field(11),
+            Some(&FixFieldValue::String("ORD1".to_string()))
+        );
+        let tags: Vec<i64> = payload.fields.keys().copied().collect();
+        assert_eq!(tags, vec![11, 55]);
+    }
+
+    #[test]
+    fn resend_request_until_infinity() {
+        let message = Message::resend_request(5, 0);
+        assert_eq!(message.msg_type(), Some("2"));
+        assert_eq!(message.get_field(7), Some(&FixFieldValue::from(5i64)));
+        assert_eq!(message.get_field(16), Some(&FixFieldValue::from(0i64)));
+        let tags: Vec<i64> = message.fields.keys().copied().collect();
+        assert_eq!(tags, vec![7, 16, 35]);
+    }
+
+    #[test]
+    fn heartbeat_echoes_test_req_id() {
+        let test_request = Message::test_request("PING-1");
+        assert_eq!(test_request.msg_type(), Some("1"));
+        let test_req_id = match test_request.get_field(112) {
+            Some(FixFieldValue::String(test_req_id)) => test_req_id.as_str(),
+            _ => panic!(),
+        };
+        let heartbeat = Message::heartbeat(Some(test_req_id));
+        assert_eq!(heartbeat.msg_type(), Some("0"));
+        assert_eq!(heartbeat.get_field(112), test_request.get_field(112));
+        assert_eq!(Message::heartbeat(None).get_field(112), None);
+    }
+
+    #[test]
+    fn logout_with_and_without_text() {
+        let logout = Message::logout(Some("Session closed"));
+        assert_eq!(logout.msg_type(), Some("5"));
+        assert_eq!(
+            logout.get_field(58),
+            Some(&FixFieldValue::from("Session closed".to_string()))
+        );
+        let logout = Message::logout(None);
+        assert_eq!(logout.msg_type(), Some("5"));
+        assert_eq!(logout.get_field(58), None);
+    }
+
+    #[test]
+    fn get_field_by_name() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "0");
+        message.add_str(49, "SENDER");
+        assert_eq!(
+            message.get_by_name("MsgType", &dict),
+            Some(&FixFieldValue::String("0".to_string()))
+        );
+        assert_eq!(message.get_by_name("TestReqID", &dict), None);
+        assert_eq!(message.get_by_name("NotAField", &dict), None);
+    }
 }
@@ -102,25 +102,97 @@ impl Codec for i32 {
 }
 
 impl Codec for u64 {
-    fn deserialize(&mut self, _input: &mut impl io::Read) -> io::Result<usize> {
-        todo!();
+    fn deserialize(&mut self, input: &mut impl io::Read) -> io::Result<usize> {
+        *self = 0;
+        let bytes = decode_stop_bit_entity(input)?;
+        for byte in &bytes {
+            *self = (*self << 7) | u64::from(*byte);
+        }
+        Ok(bytes.len())
     }
 
-    fn serialize(&self, _output: &mut impl io::Write) -> io::Result<usize> {
-        todo!();
+    fn serialize(&self, output: &mut impl io::Write) -> io::Result<usize> {
+        // The number of 7-bit groups needed to hold `self`.
+        let mut i = 1;
+        while i < 10 && (*self >> (7 * i)) != 0 {
+            i += 1;
+        }
+        let mut bytes = [0u8; 10];
+        for (j, byte) in bytes[..i].iter_mut().enumerate() {
+            *byte = (self >> (7 * (i - j - 1))) as u8 & SIGNIFICANT_BYTE;
+        }
+        bytes[i - 1] |= STOP_BYTE;
+        output.write_all(&bytes[..i])?;
+        Ok(i)
     }
 }
 
 impl Codec for i64 {
-    fn deserialize(&mut self, _input: &mut impl io::Read) -> io::Result<usize> {
-        todo!();
+    fn deserialize(&mut self, input: &mut impl io::Read) -> io::Result<usize> {
+        let bytes = decode_stop_bit_entity(input)?;
+        let is_negative = (bytes[0] & NEGATIVE_SIGN_MASK) != 0;
+        *self = -(is_negative as i64);
+        for byte in &bytes {
+            *self = (*self << 7) | i64::from(*byte);
+        }
+        Ok(bytes.len())
     }
 
-    fn serialize(&self, _output: &mut impl io::Write) -> io::Result<usize> {
-        todo!();
+    fn serialize(&self, output: &mut impl io::Write) -> io::Result<usize> {
+        // The number of 7-bit groups needed to hold `self` in two's
+        // complement, sign bit included.
+        let mut i = 1;
+        while i < 10 && (*self >> (7 * i - 1)) != 0 && (*self >> (7 * i - 1)) != -1 {
+            i += 1;
+        }
+        let mut bytes = [0u8; 10];
+        for (j, byte) in bytes[..i].iter_mut().enumerate() {
+            *byte = (self >> (7 * (i - j - 1))) as u8 & SIGNIFICANT_BYTE;
+        }
+        bytes[i - 1] |= STOP_BYTE;
+        output.write_all(&bytes[..i])?;
+        Ok(i)
     }
 }
 
+/// Decodes a signed integer field with optional presence, where NULL is
+/// encoded as zero and all non-negative values are incremented by one.
+/// Returns `None` if the field is NULL.
+pub fn decode_nullable_i64(input: &mut impl io::Read) -> io::Result<Option<i64>> {
+    let mut value = 0i64;
+    value.deserialize(input)?;
+    Ok(match value {
+        0 => None,
+        n if n > 0 => Some(n - 1),
+        n => Some(n),
+    })
+}
+
+/// Encodes a signed integer field with optional presence. See
+/// [`decode_nullable_i64`].
+pub fn encode_nullable_i64(value: Option<i64>, output: &mut impl io::Write) -> io::Result<usize> {
+    match value {
+        None => 0i64.serialize(output),
+        Some(n) if n >= 0 => (n + 1).serialize(output),
+        Some(n) => n.serialize(output),
+    }
+}
+
+/// Decodes an unsigned integer field with optional presence, where NULL is
+/// encoded as zero and all other values are incremented by one. Returns `None`
+/// if the field is NULL.
+pub fn decode_nullable_u64(input: &mut impl io::Read) -> io::Result<Option<u64>> {
+    let mut value = 0u64;
+    value.deserialize(input)?;
+    Ok(value.checked_sub(1))
+}
+
+/// Encodes an unsigned integer field with optional presence. See
+/// [`decode_nullable_u64`].
+pub fn encode_nullable_u64(value: Option<u64>, output: &mut impl io::Write) -> io::Result<usize> {
+    value.map_or(0, |n| n + 1).serialize(output)
+}
+
 impl Codec for Vec<u8> {
     fn serialize(&self, output: &mut impl io::Write) -> io::Result<usize> {
         let len = self.len() as u32;
@@ -237,6 +309,27 @@ pub fn decode_stop_bit_entity(input: &mut impl io::Read) -> io::Result<Vec<u8>>
     Ok(bytes)
 }
 
+/// Encodes `bits` as a presence map, i.e. seven bits per byte with a stop bit
+/// on the last byte. Trailing unset bits are omitted, as they are implicit.
+pub fn encode_stop_bit_bitvec(bits: &[bool], output: &mut impl io::Write) -> io::Result<usize> {
+    let len = bits.iter().rposition(|bit| *bit).map_or(0, |i| i + 1);
+    let mut bytes: Vec<u8> = bits[..len]
+        .chunks(7)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, bit)| byte | ((*bit as u8) << (6 - i)))
+        })
+        .collect();
+    if bytes.is_empty() {
+        bytes.push(0);
+    }
+    *bytes.last_mut().unwrap() |= STOP_BYTE;
+    output.write_all(&bytes[..])?;
+    Ok(bytes.len())
+}
+
 pub fn decode_stop_bit_bitvec(input: &mut impl io::Read) -> io::Result<BitVec> {
     let mut bits = BitVec::new();
     let mut stop_bit = false;
@@ -244,14 +337,11 @@ pub fn decode_stop_bit_bitvec(input: &mut impl io::Read) -> io::Result<BitVec> {
         let mut buffer = [0u8; 1];
         input.read_exact(&mut buffer[..])?;
         let byte = buffer[0];
+        // The most significant bit is the stop bit, not data.
         stop_bit = byte >= STOP_BYTE;
-        if !stop_bit {
-            bits.push(byte >> 7 == 1);
-        }
         bits.push((byte >> 6) & 1 == 1);
         bits.push((byte >> 5) & 1 == 1);
         bits.push((byte >> 4) & 1 == 1);
-        bits.push((byte >> 4) & 1 == 1);
         bits.push((byte >> 3) & 1 == 1);
         bits.push((byte >> 2) & 1 == 1);
         bits.push((byte >> 1) & 1 == 1);
@@ -315,6 +405,63 @@ mod test {
         assert_eq!(*value, expected_value);
     }
 
+    #[quickcheck]
+    fn encode_then_decode_i64(expected_value: i64) -> bool {
+        let mut bytes: Vec<u8> = Vec::new();
+        expected_value.serialize(&mut bytes).unwrap();
+        let value = &mut 0i64;
+        value.deserialize(&mut &bytes[..]).unwrap();
+        *value == expected_value
+    }
+
+    #[quickcheck]
+    fn encode_then_decode_u64(expected_value: u64) -> bool {
+        let mut bytes: Vec<u8> = Vec::new();
+        expected_value.serialize(&mut bytes).unwrap();
+        let value = &mut 0u64;
+        value.deserialize(&mut &bytes[..]).unwrap();
+        *value == expected_value
+    }
+
+    #[quickcheck]
+    fn encode_then_decode_nullable_i64(expected_value: Option<i64>) -> bool {
+        // Incrementing the greatest value would overflow.
+        let expected_value = expected_value.filter(|n| *n != i64::MAX);
+        let mut bytes: Vec<u8> = Vec::new();
+        encode_nullable_i64(expected_value, &mut bytes).unwrap();
+        decode_nullable_i64(&mut &bytes[..]).unwrap() == expected_value
+    }
+
+    #[test]
+    fn encode_then_decode_presence_map() {
+        let mut bits = vec![false; 9];
+        bits[0] = true;
+        bits[8] = true;
+        let mut bytes: Vec<u8> = Vec::new();
+        encode_stop_bit_bitvec(&bits[..], &mut bytes).unwrap();
+        assert_eq!(bytes, vec![0x40, 0xa0]);
+        let decoded = decode_stop_bit_bitvec(&mut &bytes[..]).unwrap();
+        let decoded: Vec<bool> = decoded.iter().copied().take(9).collect();
+        assert_eq!(decoded, bits);
+    }
+
+    #[test]
+    fn decode_nullable_i64_examples() {
+        assert_eq!(decode_nullable_i64(&mut &[0x80u8][..]).unwrap(), None);
+        assert_eq!(decode_nullable_i64(&mut &[0x81u8][..]).unwrap(), Some(0));
+        assert_eq!(decode_nullable_i64(&mut &[0xfeu8][..]).unwrap(), Some(-2));
+    }
+
+    #[test]
+    fn decode_presence_map() {
+        let bits = decode_stop_bit_bitvec(&mut &[0x40u8, 0x81][..]).unwrap();
+        let bits: Vec<bool> = bits.iter().copied().collect();
+        let mut expected = vec![false; 14];
+        expected[0] = true;
+        expected[13] = true;
+        assert_eq!(bits, expected);
+    }
+
     #[quickcheck]
     fn encode_then_decode_string(expected_value: String) -> bool {
         let mut bytes: Vec<u8> = Vec::new();
@@ -3,6 +3,7 @@
 use std::cmp;
 use std::fmt;
 use std::ops;
+use std::str::FromStr;
 
 /// [`Decimal`] is a fixed 96-bit representation of a decimal number. It can be
 /// used to represent all values of the form `m * 10**e`, with `-2**63 <= m <=
@@ -34,6 +35,10 @@ pub struct Decimal {
 #[derive(Debug)]
 pub enum Error {
     InvalidScale,
+    /// The string is not a valid decimal number.
+    InvalidFormat,
+    /// The string has too many significant digits for a 64-bit mantissa.
+    Overflow,
 }
 
 impl Decimal {
@@ -426,42 +431,69 @@ impl fmt::Display for Decimal {
         if self.is_negative() {
             write!(f, "-")?;
         }
-        if self.mantissa().abs() < self.pow_of_ten() {
-            write!(f, "0")?;
+        let digits = self.mantissa().unsigned_abs().to_string();
+        if self.mantissa() == 0 {
+            write!(f, "0")
+        } else if self.exp() >= 0 {
+            // Trailing zeros are implied by the exponent.
+            write!(f, "{}{}", digits, "0".repeat(self.exp() as usize))
         } else {
-            // First, the transform the mantissa into a string.
-            let digits = self.mantissa().abs().to_string();
-            // We then calculate the total number of digits that we're supposed
-            // to print. Note that this can never become <= 0, due to the if
-            // guard.
-            let len = digits.chars().count() as i32 + self.exp();
-            // We then concatenate the string with 0's in case we don't have
-            // enough digits.
-            for digit in digits
-                .chars()
-                .chain(std::iter::repeat('0'))
-                .take(len as usize)
-            {
-                write!(f, "{}", digit)?;
-            }
+            // We pad the digits with leading zeros, so that there's always at
+            // least one digit before the decimal point. `self` is normalized,
+            // so the last digit is never a zero.
+            let scale = -self.exp() as usize;
+            let digits = format!("{:0>width$}", digits, width = scale + 1);
+            let (int_digits, fract_digits) = digits.split_at(digits.len() - scale);
+            write!(f, "{}.{}", int_digits, fract_digits)
         }
-        if self.fract().is_positive() {
-            // We just verified we have some decimal digits, so we're gonna need
-            // the decimal point.
-            write!(f, ".{}", "")?;
-            // Here we take the mantissa of the fractional part.
-            let digits = self.fract().mantissa().to_string();
-            let digits_len = digits.chars().count();
-            let len = -self.exp();
-            debug_assert!(len > 0);
-            for digit in std::iter::repeat('0')
-                .take(len as usize - digits_len)
-                .chain(digits.chars())
-            {
-                write!(f, "{}", digit)?;
-            }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    /// Parses a decimal number with an optional sign and decimal point, e.g.
+    /// `-100.00`.
+    ///
+    /// ```
+    /// use fefix::codec::fast::decimal::Decimal;
+    ///
+    /// let px: Decimal = "1.50".parse().unwrap();
+    /// assert_eq!(px, Decimal::new(15, -1));
+    ///
+    /// let qty: Decimal = "100.00".parse().unwrap();
+    /// assert_eq!(qty.to_string(), "100");
+    /// let px: Decimal = "-0.05".parse().unwrap();
+    /// assert_eq!(px.to_string(), "-0.05");
+    ///
+    /// let qty = "99999999999999999999".parse::<Decimal>();
+    /// assert!(matches!(qty, Err(fefix::codec::fast::decimal::Error::Overflow)));
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (is_negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (int_digits, fract_digits) = match digits.find('.') {
+            Some(i) => (&digits[..i], &digits[i + 1..]),
+            None => (digits, ""),
+        };
+        if int_digits.is_empty() && fract_digits.is_empty() {
+            return Err(Error::InvalidFormat);
+        }
+        let mut mantissa: i64 = 0;
+        for c in int_digits.chars().chain(fract_digits.chars()) {
+            let digit = c.to_digit(10).ok_or(Error::InvalidFormat)?;
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(digit as i64))
+                .ok_or(Error::Overflow)?;
+        }
+        if is_negative {
+            mantissa = -mantissa;
         }
-        Ok(())
+        Ok(Decimal::new(mantissa, -(fract_digits.len() as i32)))
     }
 }
 
@@ -2,13 +2,28 @@ use super::codec::Codec;
 use std::marker::PhantomData;
 use std::ops::Sub;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum FieldOperatorInstruction {
     Constant,
+    Default,
     None,
     Delta,
     Tail,
     Copy,
+    Increment,
+}
+
+/// The state of the previous value of a field operator. See section 6.3.1 of
+/// FAST 1.1 documentation.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PreviousValue<T> {
+    /// No value has been assigned yet.
+    #[default]
+    Undefined,
+    /// The field was absent, i.e. NULL.
+    Empty,
+    /// The last value of the field.
+    Assigned(T),
 }
 
 /// *Field encoding operator* in FAST terminology.
@@ -4,11 +4,11 @@ use crate::app::slr;
 use crate::codec::{Decoder, Encoder};
 use crate::dictionary::Dictionary;
 use crate::utils::Buffer;
-use bitvec::vec::BitVec;
-use codec::decode_stop_bit_bitvec;
-use errors::Error;
+use codec::{decode_nullable_i64, decode_nullable_u64, decode_stop_bit_bitvec};
+use errors::{DynamicError, Error, ReportableError, StaticError};
 use std::collections::HashMap;
-use template::Template;
+use std::convert::TryFrom;
+use std::io::{self, Read};
 
 mod codec;
 pub mod decimal;
@@ -24,8 +24,25 @@ pub use template::*;
 #[derive(Clone, Debug)]
 pub struct Fast {
     dict: Dictionary,
-    templates: HashMap<String, Template>,
+    templates: Templates,
     message: slr::Message,
+    state: OperatorState,
+    encoder_state: OperatorState,
+    encoding_template_id: Option<u32>,
+}
+
+/// Operator state that the decoder carries over from each message to the
+/// next. The encoder keeps its own copy, so that it knows which values the
+/// decoder can infer.
+#[derive(Clone, Debug, Default)]
+struct OperatorState {
+    /// The last template identifier, which has a copy operator.
+    template_id: Option<u32>,
+    /// Previous values of decimal fields, by template ID and field ID. Each
+    /// template has its own operator state.
+    decimals: HashMap<(u32, u32), (PreviousValue<i64>, PreviousValue<i64>)>,
+    /// Previous values of integer fields, by template ID and field ID.
+    integers: HashMap<(u32, u32), PreviousValue<i64>>,
 }
 
 impl Fast {
@@ -33,83 +50,838 @@ impl Fast {
     pub fn new() -> Self {
         Fast {
             dict: Dictionary::empty(),
-            templates: HashMap::new(),
+            templates: Templates::new(),
             message: slr::Message::new(),
+            state: OperatorState::default(),
+            encoder_state: OperatorState::default(),
+            encoding_template_id: None,
         }
     }
 
     pub fn with_template(mut self, template: Template) -> Self {
-        self.templates.insert(template.name().to_string(), template);
+        self.templates.insert(template);
         self
     }
+
+    /// Adds all of `templates` to `self`. Each message is then decoded with
+    /// the template selected by its template identifier.
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        for template in templates.iter() {
+            self.templates.insert(template.clone());
+        }
+        self
+    }
+
+    /// Selects the template that [`Encoder::encode`] uses, by template ID.
+    pub fn with_encoding_template(mut self, template_id: u32) -> Self {
+        self.encoding_template_id = Some(template_id);
+        self
+    }
+
+    /// Encodes `message` with the template with ID `template_id`, mapping its
+    /// fields to instructions by tag.
+    ///
+    /// Just like the decoder, the encoder carries operator state over from
+    /// each message to the next and builds presence maps accordingly: values
+    /// that a decoder can infer (e.g. copied or incremented values, initial
+    /// values of default operators, and repeated template identifiers) are
+    /// left out of the stream and have their presence map bit unset.
+    /// Messages must thus be decoded in the same order they're encoded. A
+    /// message that fails to encode leaves the operator state untouched.
+    pub fn encode_message(
+        &mut self,
+        template_id: u32,
+        message: &slr::Message,
+    ) -> Result<Vec<u8>, Error> {
+        let template = self
+            .templates
+            .get_by_id(template_id)
+            .ok_or(Error::Dynamic(DynamicError::D9))?;
+        let mut state = self.encoder_state.clone();
+        let bytes = template.encode_with_state(message, &mut state)?;
+        self.encoder_state = state;
+        Ok(bytes)
+    }
 }
 
 impl Decoder<slr::Message> for Fast {
     type Error = Error;
 
     fn decode(&mut self, mut source: &[u8]) -> Result<&slr::Message, Error> {
-        let _presence_map = decode_stop_bit_bitvec(&mut source).unwrap();
-        let mut presence_by_field: BitVec = BitVec::new();
-        let message = slr::Message::new();
-        for field in self.templates.get("").unwrap().iter_items() {
-            if let template::FieldType::Primitive(_f) = &field.kind() {
-                presence_by_field.push(field.is_mandatory());
+        self.message = self.decode_message(&mut source)?;
+        Ok(&self.message)
+    }
+}
+
+impl Fast {
+    /// Returns an [`Iterator`] over all messages in `reader`, which are decoded
+    /// one after the other. Operator state (e.g. copied values and the current
+    /// template identifier) is carried over from each message to the next.
+    ///
+    /// Iteration stops at the end of `reader` or right after the first error.
+    pub fn iter<R: io::Read>(
+        mut self,
+        mut reader: R,
+    ) -> impl Iterator<Item = Result<slr::Message, Error>> {
+        let mut is_done = false;
+        std::iter::from_fn(move || {
+            if is_done {
+                return None;
+            }
+            // Every message starts with a presence map, which is at least one
+            // byte long. Running out of data right there means that there are
+            // no more messages.
+            let mut first_byte = [0u8];
+            let result = match reader.read(&mut first_byte) {
+                Ok(0) => {
+                    is_done = true;
+                    return None;
+                }
+                Ok(_) => {
+                    let mut source = io::Read::chain(&first_byte[..], &mut reader);
+                    self.decode_message(&mut source)
+                }
+                Err(err) => Err(Error::from(err)),
+            };
+            is_done = result.is_err();
+            Some(result)
+        })
+    }
+
+    fn decode_message(&mut self, source: &mut impl io::Read) -> Result<slr::Message, Error> {
+        let presence_map = decode_stop_bit_bitvec(source)?;
+        // Bits past the end of the presence map are implicitly unset.
+        let mut presence_bits = presence_map.iter().copied().chain(std::iter::repeat(false));
+        let template_id = decode_template_id(&mut self.state, &mut presence_bits, source)?;
+        let template = self
+            .templates
+            .get_by_id(template_id)
+            .ok_or(Error::Dynamic(DynamicError::D9))?;
+        let mut message = slr::Message::new();
+        decode_instructions(
+            template_id,
+            template.instructions(),
+            &self.templates,
+            &mut self.state,
+            &mut presence_bits,
+            source,
+            &mut message,
+        )?;
+        Ok(message)
+    }
+}
+
+/// Decodes a template identifier, which has a copy operator.
+fn decode_template_id(
+    state: &mut OperatorState,
+    presence_bits: &mut impl Iterator<Item = bool>,
+    source: &mut impl io::Read,
+) -> Result<u32, Error> {
+    if presence_bits.next().unwrap() {
+        let mut template_id = 0u32;
+        template_id.deserialize(source)?;
+        state.template_id = Some(template_id);
+    }
+    state.template_id.ok_or(Error::Dynamic(DynamicError::D5))
+}
+
+/// Decodes the fields described by `instructions` into `message`.
+///
+/// An optional group takes up a single presence map bit, which tells whether
+/// any of its fields are in the stream. A group whose fields consume presence
+/// map bits comes with its own presence map.
+///
+/// Dynamic template references are followed by a presence map and a template
+/// identifier, and then by the fields of the referenced template, which are
+/// added to `message` as well.
+fn decode_instructions(
+    template_id: u32,
+    instructions: &[FieldInstruction],
+    templates: &Templates,
+    state: &mut OperatorState,
+    presence_bits: &mut impl Iterator<Item = bool>,
+    source: &mut impl io::Read,
+    message: &mut slr::Message,
+) -> Result<(), Error> {
+    let mut position = 0;
+    while let Some(field) = instructions.get(position) {
+        position += 1;
+        if let template::FieldType::Primitive(PrimitiveType::Decimal) = field.kind() {
+            let previous = state.decimals.entry((template_id, field.id())).or_default();
+            if let Some(decimal) = decode_decimal(field, previous, presence_bits, source)? {
+                message.add_str(field.id(), decimal.to_string());
+            }
+            continue;
+        }
+        if let template::FieldType::Primitive(f) = field.kind() {
+            let is_optional = !field.is_mandatory();
+            let is_unsigned = matches!(f, PrimitiveType::UInt32 | PrimitiveType::UInt64);
+            // NULL values of optional fields are simply left out of the
+            // message.
+            let value = match f {
+                PrimitiveType::SInt32
+                | PrimitiveType::SInt64
+                | PrimitiveType::UInt32
+                | PrimitiveType::UInt64 => {
+                    let previous = state.integers.entry((template_id, field.id())).or_default();
+                    decode_integer(
+                        &field.integer_operator(),
+                        is_optional,
+                        is_unsigned,
+                        previous,
+                        presence_bits,
+                        source,
+                    )?
+                    .map(|n| integer_field_value(n, is_unsigned))
+                }
+                PrimitiveType::Bytes => {
+                    read_bytes(is_optional, source)?.map(slr::FixFieldValue::Data)
+                }
+                PrimitiveType::Ascii => read_bytes(is_optional, source)?.map(|bytes| {
+                    slr::FixFieldValue::String(String::from_utf8_lossy(&bytes[..]).to_string())
+                }),
+                PrimitiveType::Utf8 => match read_bytes(is_optional, source)? {
+                    Some(bytes) => Some(slr::FixFieldValue::String(
+                        String::from_utf8(bytes).map_err(|_| Error::Dynamic(DynamicError::D10))?,
+                    )),
+                    None => None,
+                },
+                PrimitiveType::Decimal => unreachable!(),
+            };
+            if let Some(value) = value {
+                message.add_field(field.id(), value);
+            }
+        } else if let template::FieldType::Group(len) = field.kind() {
+            let children = &instructions[position..position + *len as usize];
+            position += *len as usize;
+            if !field.is_mandatory() && !presence_bits.next().unwrap() {
+                continue;
+            }
+            if template::pmap_bits(children).is_empty() {
+                decode_instructions(
+                    template_id,
+                    children,
+                    templates,
+                    state,
+                    &mut *presence_bits,
+                    &mut *source,
+                    message,
+                )?;
             } else {
-                presence_by_field.push(false);
+                let presence_map = decode_stop_bit_bitvec(source)?;
+                let mut group_presence_bits =
+                    presence_map.iter().copied().chain(std::iter::repeat(false));
+                decode_instructions(
+                    template_id,
+                    children,
+                    templates,
+                    state,
+                    &mut group_presence_bits,
+                    &mut *source,
+                    message,
+                )?;
             }
+        } else if let template::FieldType::DynamicTemplateRef = field.kind() {
+            let presence_map = decode_stop_bit_bitvec(source)?;
+            let mut nested_presence_bits =
+                presence_map.iter().copied().chain(std::iter::repeat(false));
+            let nested_template_id = decode_template_id(state, &mut nested_presence_bits, source)?;
+            let nested_template = templates
+                .get_by_id(nested_template_id)
+                .ok_or(Error::Dynamic(DynamicError::D9))?;
+            decode_instructions(
+                nested_template_id,
+                nested_template.instructions(),
+                templates,
+                state,
+                &mut nested_presence_bits,
+                &mut *source,
+                message,
+            )?;
         }
-        for field in self.templates.get("").unwrap().iter_items() {
-            if let template::FieldType::Primitive(f) = field.kind() {
-                match f {
-                    PrimitiveType::SInt32 => {
-                        let mut val = 0i32;
-                        val.deserialize(&mut source)?;
-                        PrimitiveValue::SInt32(val)
-                    }
-                    PrimitiveType::UInt32 => {
-                        let mut val = 0u32;
-                        val.deserialize(&mut source)?;
-                        PrimitiveValue::UInt32(val)
-                    }
-                    PrimitiveType::SInt64 => {
-                        let mut val = 0i64;
-                        val.deserialize(&mut source)?;
-                        PrimitiveValue::SInt64(val)
-                    }
-                    PrimitiveType::UInt64 => {
-                        let mut val = 0u64;
-                        val.deserialize(&mut source)?;
-                        PrimitiveValue::UInt64(val)
-                    }
-                    PrimitiveType::Bytes => {
-                        let mut val: Vec<u8> = Vec::new();
-                        val.deserialize(&mut source)?;
-                        PrimitiveValue::Bytes(&val[..])
-                    }
-                    PrimitiveType::Ascii => {
-                        let mut val = String::new();
-                        val.deserialize(&mut source)?;
-                        PrimitiveValue::Ascii(val.as_bytes())
-                    }
-                    _ => {
-                        todo!();
-                    }
-                };
+    }
+    Ok(())
+}
+
+/// Decodes a decimal field, which is made of an exponent and a mantissa.
+/// Without individual operators, both are read straight from the stream.
+///
+/// If the field is optional, the exponent is treated as an optional integer
+/// field and the mantissa as a mandatory one. A NULL exponent thus means that
+/// the whole decimal is absent, in which case the mantissa takes up neither
+/// space in the stream nor bits in the presence map, and its previous value is
+/// left untouched.
+fn decode_decimal(
+    instruction: &FieldInstruction,
+    previous: &mut (PreviousValue<i64>, PreviousValue<i64>),
+    presence_bits: &mut impl Iterator<Item = bool>,
+    source: &mut impl io::Read,
+) -> Result<Option<Decimal>, Error> {
+    let no_operator = ComponentOperator::default();
+    let (exponent_operator, mantissa_operator) = match instruction.decimal_components() {
+        Some(components) => components,
+        None if instruction.operator() == &FieldOperatorInstruction::None => {
+            (&no_operator, &no_operator)
+        }
+        // Operators on the decimal as a whole aren't supported yet.
+        None => return Err(Error::Static(StaticError::S2)),
+    };
+    let is_optional = !instruction.is_mandatory();
+    let exponent = decode_integer(
+        exponent_operator,
+        is_optional,
+        false,
+        &mut previous.0,
+        presence_bits,
+        source,
+    )?;
+    let exponent = match exponent {
+        Some(exponent) if (-63..=63).contains(&exponent) => exponent,
+        Some(_) => return Err(Error::Reportable(ReportableError::R1)),
+        None => return Ok(None),
+    };
+    let mantissa = decode_integer(
+        mantissa_operator,
+        false,
+        false,
+        &mut previous.1,
+        presence_bits,
+        source,
+    )?
+    .ok_or(Error::Dynamic(DynamicError::D6))?;
+    Ok(Some(Decimal::new(mantissa, exponent as i32)))
+}
+
+/// Decodes an integer field according to `operator`, updating its
+/// `previous` value if needed. Returns `None` if an optional field is absent.
+/// Unsigned values are reinterpreted as `i64`; deltas are always signed.
+fn decode_integer(
+    operator: &ComponentOperator,
+    is_optional: bool,
+    is_unsigned: bool,
+    previous: &mut PreviousValue<i64>,
+    presence_bits: &mut impl Iterator<Item = bool>,
+    source: &mut impl io::Read,
+) -> Result<Option<i64>, Error> {
+    let read_value = |source: &mut _| -> Result<Option<i64>, Error> {
+        if is_unsigned {
+            Ok(read_unsigned_integer(is_optional, source)?.map(|n| n as i64))
+        } else {
+            read_integer(is_optional, source)
+        }
+    };
+    let value = match operator.operator() {
+        FieldOperatorInstruction::None => read_value(source)?,
+        // Mandatory constant fields take up no bits in the presence map.
+        FieldOperatorInstruction::Constant if !is_optional => operator.initial_value(),
+        FieldOperatorInstruction::Constant => {
+            if presence_bits.next().unwrap() {
+                operator.initial_value()
             } else {
-                // Sequence or group.
-                todo!();
+                None
             }
         }
-        self.message = message;
-        Ok(&self.message)
+        FieldOperatorInstruction::Default => {
+            if presence_bits.next().unwrap() {
+                read_value(source)?
+            } else {
+                operator.initial_value()
+            }
+        }
+        FieldOperatorInstruction::Copy | FieldOperatorInstruction::Increment => {
+            let value = if presence_bits.next().unwrap() {
+                read_value(source)?
+            } else {
+                match *previous {
+                    PreviousValue::Assigned(value)
+                        if operator.operator() == &FieldOperatorInstruction::Increment =>
+                    {
+                        Some(value.wrapping_add(1))
+                    }
+                    PreviousValue::Assigned(value) => Some(value),
+                    PreviousValue::Empty if is_optional => None,
+                    PreviousValue::Empty => return Err(Error::Dynamic(DynamicError::D6)),
+                    PreviousValue::Undefined => operator.initial_value(),
+                }
+            };
+            *previous = value.map_or(PreviousValue::Empty, PreviousValue::Assigned);
+            value
+        }
+        FieldOperatorInstruction::Delta => match read_integer(is_optional, source)? {
+            Some(delta) => {
+                let base = match *previous {
+                    PreviousValue::Assigned(value) => value,
+                    PreviousValue::Undefined => operator.initial_value().unwrap_or(0),
+                    PreviousValue::Empty => return Err(Error::Dynamic(DynamicError::D6)),
+                };
+                let value = base.wrapping_add(delta);
+                *previous = PreviousValue::Assigned(value);
+                Some(value)
+            }
+            None => None,
+        },
+        FieldOperatorInstruction::Tail => return Err(Error::Static(StaticError::S2)),
+    };
+    match value {
+        None if !is_optional => Err(Error::Dynamic(DynamicError::D5)),
+        value => Ok(value),
     }
 }
 
+/// Integers that don't fit an `Int` are kept as strings, so that `int64` and
+/// `uInt64` values aren't truncated.
+fn integer_field_value(n: i64, is_unsigned: bool) -> slr::FixFieldValue {
+    let fits_int = if is_unsigned {
+        i32::try_from(n as u64).is_ok()
+    } else {
+        i32::try_from(n).is_ok()
+    };
+    match (fits_int, is_unsigned) {
+        (true, _) => slr::FixFieldValue::from(n),
+        (false, true) => slr::FixFieldValue::String((n as u64).to_string()),
+        (false, false) => slr::FixFieldValue::String(n.to_string()),
+    }
+}
+
+fn read_integer(is_optional: bool, source: &mut impl io::Read) -> Result<Option<i64>, Error> {
+    if is_optional {
+        Ok(decode_nullable_i64(source)?)
+    } else {
+        let mut value = 0i64;
+        value.deserialize(source)?;
+        Ok(Some(value))
+    }
+}
+
+fn read_unsigned_integer(
+    is_optional: bool,
+    source: &mut impl io::Read,
+) -> Result<Option<u64>, Error> {
+    if is_optional {
+        Ok(decode_nullable_u64(source)?)
+    } else {
+        let mut value = 0u64;
+        value.deserialize(source)?;
+        Ok(Some(value))
+    }
+}
+
+/// Reads a length-prefixed byte vector. The length of optional fields is
+/// nullable.
+fn read_bytes(is_optional: bool, source: &mut impl io::Read) -> Result<Option<Vec<u8>>, Error> {
+    let len = match read_unsigned_integer(is_optional, source)? {
+        Some(len) => len,
+        None => return Ok(None),
+    };
+    // The length comes from the stream, so the buffer only grows as bytes
+    // actually arrive.
+    let mut bytes = Vec::new();
+    source.by_ref().take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(Some(bytes))
+}
+
 impl Encoder<slr::Message> for Fast {
     type Error = Error;
 
-    fn encode(&mut self, buffer: impl Buffer, _message: &slr::Message) -> Result<usize, Error> {
-        let _presence_by_field: BitVec = BitVec::new();
+    /// Encodes `message` with the template selected by
+    /// [`Fast::with_encoding_template`]. See [`Fast::encode_message`].
+    fn encode(&mut self, mut buffer: impl Buffer, message: &slr::Message) -> Result<usize, Error> {
+        let template_id = self
+            .encoding_template_id
+            .ok_or(Error::Dynamic(DynamicError::D9))?;
+        let bytes = self.encode_message(template_id, message)?;
+        buffer.extend_from_slice(&bytes[..]);
         Ok(buffer.as_slice().len())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEMPLATE_OPTIONAL_DECIMAL: &str = r#"
+<templates>
+  <template name="Quote" id="1">
+    <decimal name="Price" id="44" presence="optional">
+      <exponent/>
+      <mantissa><copy/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+
+    #[test]
+    fn optional_decimal_with_copied_mantissa() {
+        let template = Template::new(TEMPLATE_OPTIONAL_DECIMAL).unwrap();
+        let mut decoder = Fast::new().with_template(template);
+        let messages: &[(&[u8], Option<&str>)] = &[
+            // Present: template ID 1, exponent -2, mantissa 150.
+            (&[0xe0, 0x81, 0xfe, 0x01, 0x96], Some("1.5")),
+            // Copied: exponent -2, the mantissa is absent from the stream.
+            (&[0x80, 0xfe], Some("1.5")),
+            // NULL: the exponent is NULL and the mantissa takes up no bit in
+            // the presence map.
+            (&[0x80, 0x80], None),
+            // The previous value of the mantissa survives NULL decimals.
+            (&[0x80, 0xff], Some("15")),
+        ];
+        for (bytes, expected) in messages {
+            let message = decoder.decode(bytes).unwrap();
+            let expected = expected.map(|s| slr::FixFieldValue::String(s.to_string()));
+            assert_eq!(message.get_field(44u32), expected.as_ref());
+        }
+    }
+
+    #[test]
+    fn mandatory_decimal_with_undefined_mantissa_is_an_error() {
+        let xml = TEMPLATE_OPTIONAL_DECIMAL.replace(r#" presence="optional""#, "");
+        let template = Template::new(&xml).unwrap();
+        let mut decoder = Fast::new().with_template(template);
+        // Template ID 1, exponent -2, but no mantissa.
+        let result = decoder.decode(&[0xc0, 0x81, 0xfe]);
+        assert!(matches!(result, Err(Error::Dynamic(DynamicError::D5))));
+    }
+
+    #[test]
+    fn decimal_with_whole_field_operator_is_an_error() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <decimal name="Price" id="44"><copy/></decimal>
+  </template>
+</templates>"#;
+        let mut decoder = Fast::new().with_template(Template::new(xml).unwrap());
+        assert!(matches!(
+            decoder.decode(&[0xe0, 0x81, 0xfe, 0x01, 0x96]),
+            Err(Error::Static(StaticError::S2))
+        ));
+    }
+
+    #[test]
+    fn constant_exponent_with_mantissa_delta() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <decimal name="Price" id="44">
+      <exponent><constant value="-2"/></exponent>
+      <mantissa><delta/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+        let decoder = Fast::new().with_template(Template::new(xml).unwrap());
+        let stream: Vec<u8> = vec![
+            0xc0, 0x81, 0x01, 0x96, // Mantissa delta +150 from 0.
+            0x80, 0x81, // Mantissa delta +1.
+            0x80, 0xfe, // Mantissa delta -2.
+        ];
+        let prices: Vec<Decimal> = decoder
+            .iter(io::Cursor::new(stream))
+            .map(|message| match message.unwrap().get_field(44u32) {
+                Some(slr::FixFieldValue::String(s)) => s.parse().unwrap(),
+                other => panic!("unexpected price {:?}", other),
+            })
+            .collect();
+        let expected: Vec<Decimal> = ["1.50", "1.51", "1.49"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(prices, expected);
+    }
+
+    #[test]
+    fn iter_over_messages_in_stream() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <uInt32 name="MsgSeqNum" id="34"/>
+    <decimal name="Price" id="44" presence="optional">
+      <exponent/>
+      <mantissa><copy/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+        let decoder = Fast::new().with_template(Template::new(xml).unwrap());
+        let stream: Vec<u8> = vec![
+            0xe0, 0x81, 0x81, 0xfe, 0x01, 0x96, // 34=1, 44=1.5
+            0x80, 0x82, 0xfe, // 34=2, 44=1.5 (copied mantissa)
+            0x80, 0x83, 0x80, // 34=3, no price
+        ];
+        let messages: Vec<slr::Message> = decoder
+            .iter(io::Cursor::new(stream))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(messages.len(), 3);
+        for (i, message) in messages.iter().enumerate() {
+            assert_eq!(message.seq_num(), Some(i as u64 + 1));
+        }
+        let price = slr::FixFieldValue::String("1.5".to_string());
+        assert_eq!(messages[0].get_field(44u32), Some(&price));
+        assert_eq!(messages[1].get_field(44u32), Some(&price));
+        assert_eq!(messages[2].get_field(44u32), None);
+    }
+
+    #[test]
+    fn interleaved_templates_have_separate_operator_state() {
+        let xml = r#"
+<templates>
+  <template name="A" id="1">
+    <decimal name="Price" id="44">
+      <exponent/>
+      <mantissa><copy/></mantissa>
+    </decimal>
+  </template>
+  <template name="B" id="2">
+    <decimal name="Price" id="44">
+      <exponent/>
+      <mantissa><copy/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+        let decoder = Fast::new().with_templates(Templates::from_xml(xml).unwrap());
+        let stream: Vec<u8> = vec![
+            0xe0, 0x81, 0xfe, 0x01, 0x96, // A: 44=1.5
+            0xe0, 0x82, 0x80, 0x89, // B: 44=9
+            0xc0, 0x81, 0xfe, // A: 44=1.5 (mantissa copied from A, not B)
+        ];
+        let prices: Vec<Option<slr::FixFieldValue>> = decoder
+            .iter(io::Cursor::new(stream))
+            .map(|message| message.unwrap().get_field(44u32).cloned())
+            .collect();
+        let price = |s: &str| Some(slr::FixFieldValue::String(s.to_string()));
+        assert_eq!(prices, vec![price("1.5"), price("9"), price("1.5")]);
+    }
+
+    #[test]
+    fn copy_with_negative_initial_value() {
+        let xml = r#"
+<templates>
+  <template name="Position" id="1">
+    <int32 name="PosAmt" id="708"><copy value="-1"/></int32>
+  </template>
+</templates>"#;
+        let templates = Templates::from_xml(xml).unwrap();
+        let template = templates.get("Position").unwrap();
+        let field = template.iter_items().next().unwrap();
+        assert_eq!(
+            field.operator_value(),
+            Some(&OperatorValue::Raw("-1".to_string()))
+        );
+        assert_eq!(field.integer_operator().initial_value(), Some(-1));
+        let decoder = Fast::new().with_templates(templates);
+        let stream: Vec<u8> = vec![
+            0xc0, 0x81, // 708=-1 (initial value)
+            0xa0, 0x85, // 708=5
+            0x80, // 708=5 (copied)
+        ];
+        let amounts: Vec<Option<slr::FixFieldValue>> = decoder
+            .iter(io::Cursor::new(stream))
+            .map(|message| message.unwrap().get_field(708u32).cloned())
+            .collect();
+        let amount = |n: i64| Some(slr::FixFieldValue::from(n));
+        assert_eq!(amounts, vec![amount(-1), amount(5), amount(5)]);
+        // Unsigned fields can't have negative initial values.
+        let xml = xml.replace("int32", "uInt32");
+        assert!(matches!(
+            Templates::from_xml(&xml),
+            Err(Error::Static(StaticError::S3))
+        ));
+    }
+
+    #[test]
+    fn encoder_builds_presence_maps_that_decode() {
+        let xml = r#"
+<templates>
+  <template name="Trade" id="1">
+    <uInt32 name="MsgSeqNum" id="34"><increment value="1"/></uInt32>
+    <string name="Symbol" id="55"/>
+    <int32 name="Side" id="54"><copy/></int32>
+    <uInt32 name="OrderQty" id="38"><default value="100"/></uInt32>
+    <int64 name="TransactTime" id="60"><delta/></int64>
+    <decimal name="Price" id="44" presence="optional">
+      <exponent><copy/></exponent>
+      <mantissa><delta/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+        let templates = Templates::from_xml(xml).unwrap();
+        let mut encoder = Fast::new()
+            .with_templates(templates.clone())
+            .with_encoding_template(1);
+        let decoder = Fast::new().with_templates(templates);
+        let trade = |seq_num: i64, side: i64, qty: i64, time: i64, price: Option<&str>| {
+            let mut message = slr::Message::new();
+            message.add_int(34, seq_num);
+            message.add_str(55, "EUR/USD");
+            message.add_int(54, side);
+            message.add_int(38, qty);
+            message.add_int(60, time);
+            if let Some(price) = price {
+                message.add_str(44, price);
+            }
+            message
+        };
+        let messages = [
+            trade(1, 1, 100, 1000, Some("1.5")),
+            trade(2, 1, 100, 1005, Some("1.6")),
+            trade(5, 2, 250, 1003, None),
+        ];
+        let mut stream = Vec::new();
+        let mut presence_maps = Vec::new();
+        for message in messages.iter() {
+            let start = stream.len();
+            encoder.encode(&mut stream, message).unwrap();
+            presence_maps.push(stream[start]);
+        }
+        // Template ID, Side and exponent; then nothing at all, as every value
+        // can be inferred; then everything but the template ID, including the
+        // NULL exponent, which deviates from the copied one.
+        assert_eq!(presence_maps, vec![0b1101_0100, 0b1000_0000, 0b1011_1100]);
+        let decoded: Vec<slr::Message> = decoder
+            .iter(io::Cursor::new(stream))
+            .map(|message| message.unwrap())
+            .collect();
+        assert_eq!(decoded.len(), messages.len());
+        for (decoded, message) in decoded.iter().zip(messages.iter()) {
+            for tag in &[34u32, 55, 54, 38, 60, 44] {
+                assert_eq!(decoded.get_field(*tag), message.get_field(*tag));
+            }
+        }
+    }
+
+    #[test]
+    fn large_integers_are_not_truncated() {
+        let xml = r#"
+<templates>
+  <template name="Trade" id="1">
+    <uInt64 name="TradeVolume" id="1020"/>
+    <int64 name="Position" id="704"/>
+    <uInt32 name="MsgSeqNum" id="34"/>
+  </template>
+</templates>"#;
+        let templates = Templates::from_xml(xml).unwrap();
+        let mut encoder = Fast::new()
+            .with_templates(templates.clone())
+            .with_encoding_template(1);
+        let decoder = Fast::new().with_templates(templates);
+        let mut message = slr::Message::new();
+        message.add_str(1020, u64::MAX.to_string());
+        message.add_str(704, "-5000000000");
+        message.add_str(34, "4294967295");
+        let mut stream = Vec::new();
+        encoder.encode(&mut stream, &message).unwrap();
+        message.add_str(1020, "5000000000");
+        encoder.encode(&mut stream, &message).unwrap();
+        let decoded: Vec<slr::Message> = decoder
+            .iter(io::Cursor::new(stream))
+            .map(|message| message.unwrap())
+            .collect();
+        let field = |message: &slr::Message, tag: u32| match message.get_field(tag) {
+            Some(slr::FixFieldValue::String(s)) => s.clone(),
+            other => panic!("unexpected value {:?}", other),
+        };
+        assert_eq!(field(&decoded[0], 1020), u64::MAX.to_string());
+        assert_eq!(field(&decoded[1], 1020), "5000000000");
+        assert_eq!(field(&decoded[1], 704), "-5000000000");
+        assert_eq!(field(&decoded[1], 34), "4294967295");
+    }
+
+    #[test]
+    fn failed_encoding_leaves_operator_state_untouched() {
+        let xml = r#"
+<templates>
+  <template name="Order" id="1">
+    <uInt32 name="MsgSeqNum" id="34"><increment/></uInt32>
+    <uInt32 name="OrderQty" id="38"/>
+  </template>
+</templates>"#;
+        let templates = Templates::from_xml(xml).unwrap();
+        let mut encoder = Fast::new()
+            .with_templates(templates.clone())
+            .with_encoding_template(1);
+        let decoder = Fast::new().with_templates(templates);
+        let order = |seq_num: i64, qty: Option<i64>| {
+            let mut message = slr::Message::new();
+            message.add_int(34, seq_num);
+            if let Some(qty) = qty {
+                message.add_int(38, qty);
+            }
+            message
+        };
+        let mut stream = Vec::new();
+        encoder.encode(&mut stream, &order(1, Some(100))).unwrap();
+        assert!(matches!(
+            encoder.encode(&mut stream, &order(2, None)),
+            Err(Error::Dynamic(DynamicError::D5))
+        ));
+        encoder.encode(&mut stream, &order(3, Some(100))).unwrap();
+        let decoded: Vec<slr::Message> = decoder
+            .iter(io::Cursor::new(stream))
+            .map(|message| message.unwrap())
+            .collect();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].seq_num(), Some(1));
+        assert_eq!(decoded[1].seq_num(), Some(3));
+    }
+
+    #[test]
+    fn dynamic_template_ref_switches_template() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <string name="Symbol" id="55"/>
+    <templateRef/>
+  </template>
+  <template name="Details" id="2">
+    <uInt32 name="MsgSeqNum" id="34"/>
+    <decimal name="Price" id="44">
+      <exponent/>
+      <mantissa><copy/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+        let mut decoder = Fast::new().with_templates(Templates::from_xml(xml).unwrap());
+        let bytes = [
+            0xc0, 0x81, 0x82, b'A', b'B', // Quote: 55=AB
+            0xe0, 0x82, 0x87, 0xfe, 0x01, 0x96, // Details: 34=7, 44=1.5
+        ];
+        let message = decoder.decode(&bytes[..]).unwrap();
+        assert_eq!(
+            message.get_field(55u32),
+            Some(&slr::FixFieldValue::String("AB".to_string()))
+        );
+        assert_eq!(message.seq_num(), Some(7));
+        assert_eq!(
+            message.get_field(44u32),
+            Some(&slr::FixFieldValue::String("1.5".to_string()))
+        );
+    }
+
+    #[test]
+    fn byte_vector_longer_than_stream_is_an_error() {
+        let xml = r#"
+<templates>
+  <template name="News" id="1">
+    <byteVector name="RawData" id="96"/>
+  </template>
+</templates>"#;
+        let mut decoder = Fast::new().with_template(Template::new(xml).unwrap());
+        // A length of 2^35 - 1, but only two bytes of data.
+        let bytes = [0xc0, 0x81, 0x7f, 0x7f, 0x7f, 0x7f, 0xff, b'A', b'B'];
+        assert!(decoder.decode(&bytes[..]).is_err());
+        let bytes = [0xc0, 0x81, 0x82, b'A', b'B'];
+        assert_eq!(
+            decoder.decode(&bytes[..]).unwrap().get_field(96u32),
+            Some(&slr::FixFieldValue::Data(b"AB".to_vec()))
+        );
+    }
+
+    #[test]
+    fn iter_stops_after_truncated_message() {
+        let decoder = Fast::new().with_template(Template::new(TEMPLATE_OPTIONAL_DECIMAL).unwrap());
+        let mut messages = decoder.iter(io::Cursor::new(vec![0xe0, 0x81, 0xfe]));
+        assert!(messages.next().unwrap().is_err());
+        assert!(messages.next().is_none());
+    }
+}
@@ -1,7 +1,12 @@
-use super::errors::StaticError;
-use super::field_operators::FieldOperatorInstruction;
-use super::Decimal;
+use super::codec::{encode_nullable_i64, encode_nullable_u64, encode_stop_bit_bitvec, Codec};
+use super::errors::{DynamicError, Error, ReportableError, StaticError};
+use super::field_operators::{FieldOperatorInstruction, PreviousValue};
+use super::{Decimal, OperatorState};
+use crate::app::slr;
 use crate::dictionary::Dictionary;
+use crate::dt;
+use std::collections::HashMap;
+use std::io;
 
 #[derive(Clone, Debug)]
 pub enum PrimitiveValue<'a> {
@@ -15,7 +20,7 @@ pub enum PrimitiveValue<'a> {
     Bytes(&'a [u8]),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum PrimitiveType {
     SInt32,
     UInt32,
@@ -34,6 +39,8 @@ pub struct FieldInstruction {
     id: u32,
     mandatory: bool,
     operator: FieldOperatorInstruction,
+    operator_value: Option<OperatorValue>,
+    decimal_components: Option<(ComponentOperator, ComponentOperator)>,
 }
 
 impl FieldInstruction {
@@ -41,35 +48,497 @@ impl FieldInstruction {
         &self.field_type
     }
 
+    /// Returns the name of `self`, as written in the template. See also
+    /// [`Template::field_name`].
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
     pub fn is_mandatory(&self) -> bool {
         self.mandatory
     }
+
+    pub fn operator(&self) -> &FieldOperatorInstruction {
+        &self.operator
+    }
+
+    /// Returns the initial value of the field operator of `self`, as specified
+    /// by its `value` attribute.
+    pub fn operator_value(&self) -> Option<&OperatorValue> {
+        self.operator_value.as_ref()
+    }
+
+    /// Returns the field operator of `self` as if it were the component of a
+    /// decimal field, so that integer fields can share the same logic.
+    pub(super) fn integer_operator(&self) -> ComponentOperator {
+        ComponentOperator {
+            operator: self.operator.clone(),
+            initial_value: self.integer_initial_value(),
+        }
+    }
+
+    /// Returns the field operators of the exponent and the mantissa of `self`,
+    /// in this order, if `self` is a decimal field with individual operators
+    /// (i.e. `<exponent>` and `<mantissa>` elements). The exponent has the
+    /// presence of `self`, while the mantissa is always mandatory.
+    pub fn decimal_components(&self) -> Option<(&ComponentOperator, &ComponentOperator)> {
+        self.decimal_components
+            .as_ref()
+            .map(|(exponent, mantissa)| (exponent, mantissa))
+    }
 }
 
-#[derive(Clone, Debug)]
+/// The field operator of either the exponent or the mantissa of a decimal field.
+/// Both are encoded as integer fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComponentOperator {
+    operator: FieldOperatorInstruction,
+    initial_value: Option<i64>,
+}
+
+impl Default for ComponentOperator {
+    /// No field operator at all.
+    fn default() -> Self {
+        Self {
+            operator: FieldOperatorInstruction::None,
+            initial_value: None,
+        }
+    }
+}
+
+impl ComponentOperator {
+    fn from_template(node: Option<roxmltree::Node>, mandatory: bool) -> Result<Self, StaticError> {
+        let operator_node = node.and_then(|node| node.children().find(|n| n.is_element()));
+        let operator_node = match operator_node {
+            Some(operator_node) => operator_node,
+            None => return Ok(Self::default()),
+        };
+        let operator = FieldInstruction::xml_tag_to_operator(operator_node.tag_name().name())?;
+        let initial_value = match operator_node.attribute("value") {
+            Some(value) => Some(value.parse().map_err(|_| StaticError::S3)?),
+            None => None,
+        };
+        match (&operator, initial_value) {
+            (FieldOperatorInstruction::Tail, _) => Err(StaticError::S2),
+            (FieldOperatorInstruction::Constant, None) => Err(StaticError::S4),
+            (FieldOperatorInstruction::Default, None) if mandatory => Err(StaticError::S5),
+            _ => Ok(Self {
+                operator,
+                initial_value,
+            }),
+        }
+    }
+
+    pub fn operator(&self) -> &FieldOperatorInstruction {
+        &self.operator
+    }
+
+    /// Encodes an integer `value` according to `self`. `value` is `None` only
+    /// if the field is optional and absent.
+    ///
+    /// Just like the decoder, the encoder keeps track of the `previous` value
+    /// of the field. Values that the decoder can infer on its own (i.e.
+    /// initial, copied and incremented values) are left out of the stream and
+    /// their presence map bit is unset. Deltas are always signed.
+    fn encode(
+        &self,
+        value: Option<i64>,
+        is_optional: bool,
+        is_unsigned: bool,
+        previous: &mut PreviousValue<i64>,
+        presence_bits: &mut Vec<bool>,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        match self.operator {
+            FieldOperatorInstruction::None => {
+                write_integer(value, is_optional, is_unsigned, output)
+            }
+            FieldOperatorInstruction::Constant => {
+                if value.is_some_and(|value| Some(value) != self.initial_value) {
+                    return Err(Error::Dynamic(DynamicError::D3));
+                }
+                if is_optional {
+                    presence_bits.push(value.is_some());
+                }
+                Ok(())
+            }
+            FieldOperatorInstruction::Default => {
+                let is_implicit = value == self.initial_value;
+                presence_bits.push(!is_implicit);
+                if is_implicit {
+                    Ok(())
+                } else {
+                    write_integer(value, is_optional, is_unsigned, output)
+                }
+            }
+            FieldOperatorInstruction::Copy | FieldOperatorInstruction::Increment => {
+                // What the decoder would assume if the bit were unset, if
+                // anything.
+                let implicit = match *previous {
+                    PreviousValue::Assigned(previous)
+                        if self.operator == FieldOperatorInstruction::Increment =>
+                    {
+                        Some(Some(previous.wrapping_add(1)))
+                    }
+                    PreviousValue::Assigned(previous) => Some(Some(previous)),
+                    PreviousValue::Empty if is_optional => Some(None),
+                    PreviousValue::Empty => None,
+                    PreviousValue::Undefined => Some(self.initial_value),
+                };
+                *previous = value.map_or(PreviousValue::Empty, PreviousValue::Assigned);
+                let is_implicit = implicit == Some(value);
+                presence_bits.push(!is_implicit);
+                if is_implicit {
+                    Ok(())
+                } else {
+                    write_integer(value, is_optional, is_unsigned, output)
+                }
+            }
+            FieldOperatorInstruction::Delta => {
+                let value = match value {
+                    Some(value) => value,
+                    None => return write_integer(None, is_optional, false, output),
+                };
+                let base = match *previous {
+                    PreviousValue::Assigned(previous) => previous,
+                    PreviousValue::Undefined => self.initial_value.unwrap_or(0),
+                    PreviousValue::Empty => return Err(Error::Dynamic(DynamicError::D6)),
+                };
+                *previous = PreviousValue::Assigned(value);
+                write_integer(Some(value.wrapping_sub(base)), is_optional, false, output)
+            }
+            FieldOperatorInstruction::Tail => Err(Error::Static(StaticError::S2)),
+        }
+    }
+
+    /// Returns the initial value of the field operator, as specified by its
+    /// `value` attribute.
+    pub fn initial_value(&self) -> Option<i64> {
+        self.initial_value
+    }
+}
+
+/// The initial value of a field operator.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OperatorValue {
+    Decimal(Decimal),
+    /// The value of a non-decimal field, as it appears in the template.
+    Raw(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum FieldType {
     Primitive(PrimitiveType),
+    /// A `<group>`, made of this many instructions right after it.
     Group(u32),
+    /// A `<templateRef>` without a name. The referenced template is only known
+    /// while decoding, as its identifier is in the stream.
+    DynamicTemplateRef,
 }
 
 impl FieldInstruction {
     fn from_template(node: roxmltree::Node) -> Result<Self, StaticError> {
         let name = node.attribute("name").ok_or(StaticError::S1)?;
         let id = node.attribute("id").unwrap().parse().unwrap();
-        let mandatory = {
-            let attr = node.attribute("presence").unwrap_or("true");
-            attr == "true"
-        };
+        let mandatory = node.attribute("presence") != Some("optional");
         let type_name = node.tag_name().name();
+        // Only strings have a character set; other attributes that don't
+        // affect encoding (e.g. `ns`, `dictionary`) are ignored.
+        let field_type = match (type_name, node.attribute("charset")) {
+            ("string", Some("unicode")) => FieldType::Primitive(PrimitiveType::Utf8),
+            ("string", Some("ascii")) | ("string", None) => {
+                FieldType::Primitive(PrimitiveType::Ascii)
+            }
+            ("string", Some(_)) => return Err(StaticError::S1),
+            _ => Template::xml_tag_to_instruction(type_name)?,
+        };
+        let exponent_node = node.children().find(|n| n.has_tag_name("exponent"));
+        let mantissa_node = node.children().find(|n| n.has_tag_name("mantissa"));
+        let decimal_components = match (&field_type, exponent_node, mantissa_node) {
+            (_, None, None) => None,
+            (FieldType::Primitive(PrimitiveType::Decimal), exponent_node, mantissa_node) => Some((
+                ComponentOperator::from_template(exponent_node, mandatory)?,
+                ComponentOperator::from_template(mantissa_node, true)?,
+            )),
+            _ => return Err(StaticError::S1),
+        };
+        let (operator, operator_value) = match node.children().find(|n| n.is_element()) {
+            // Individual operators were already parsed above, and they can't
+            // be mixed with an operator for the whole decimal.
+            Some(_) if decimal_components.is_some() => {
+                let is_whole_field_operator = |n: roxmltree::Node| {
+                    n.is_element() && !n.has_tag_name("exponent") && !n.has_tag_name("mantissa")
+                };
+                if node.children().any(is_whole_field_operator) {
+                    return Err(StaticError::S1);
+                }
+                (FieldOperatorInstruction::None, None)
+            }
+            Some(operator_node) => {
+                let operator = Self::xml_tag_to_operator(operator_node.tag_name().name())?;
+                let value = match operator_node.attribute("value") {
+                    Some(value) => Some(Self::parse_operator_value(&field_type, value)?),
+                    None => None,
+                };
+                (operator, value)
+            }
+            None => (FieldOperatorInstruction::None, None),
+        };
+        if operator == FieldOperatorInstruction::Default && mandatory && operator_value.is_none() {
+            return Err(StaticError::S5);
+        }
         let instruction = FieldInstruction {
-            field_type: Template::xml_tag_to_instruction(type_name)?,
+            field_type,
             name: name.to_string(),
             id,
             mandatory,
-            operator: FieldOperatorInstruction::Constant,
+            operator,
+            operator_value,
+            decimal_components,
         };
         Ok(instruction)
     }
+
+    /// Builds the instruction that opens a `<group>`. The number of its child
+    /// instructions is only known after they have been pushed, so it starts
+    /// at zero. Groups don't need an ID.
+    fn group_from_template(node: roxmltree::Node) -> Result<Self, StaticError> {
+        let name = node.attribute("name").ok_or(StaticError::S1)?;
+        let id = match node.attribute("id") {
+            Some(id) => id.parse().map_err(|_| StaticError::S1)?,
+            None => 0,
+        };
+        Ok(FieldInstruction {
+            field_type: FieldType::Group(0),
+            name: name.to_string(),
+            id,
+            mandatory: node.attribute("presence") != Some("optional"),
+            operator: FieldOperatorInstruction::None,
+            operator_value: None,
+            decimal_components: None,
+        })
+    }
+
+    /// Builds the instruction of a `<templateRef>` without a name.
+    fn dynamic_template_ref() -> Self {
+        FieldInstruction {
+            field_type: FieldType::DynamicTemplateRef,
+            name: "templateRef".to_string(),
+            id: 0,
+            mandatory: true,
+            operator: FieldOperatorInstruction::None,
+            operator_value: None,
+            decimal_components: None,
+        }
+    }
+
+    /// Returns `true` if `self` and `other` are encoded the same way.
+    /// Field names don't matter.
+    fn is_compatible_with(&self, other: &FieldInstruction) -> bool {
+        self.id == other.id
+            && self.field_type == other.field_type
+            && self.mandatory == other.mandatory
+            && self.operator == other.operator
+            && self.operator_value == other.operator_value
+            && self.decimal_components == other.decimal_components
+    }
+
+    /// Encodes `value` as the field described by `self`, appending its
+    /// presence map bits (if any) to `presence_bits`. The previous values of
+    /// integer and decimal fields are kept in `state`, just like the decoder
+    /// does.
+    fn encode_field(
+        &self,
+        value: Option<&slr::FixFieldValue>,
+        template_id: u32,
+        state: &mut OperatorState,
+        presence_bits: &mut Vec<bool>,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let kind = match &self.field_type {
+            FieldType::Primitive(kind) => kind,
+            // Groups and template references are laid out by
+            // `encode_instructions`.
+            FieldType::Group(_) | FieldType::DynamicTemplateRef => unreachable!(),
+        };
+        if value.is_none() && self.mandatory {
+            return Err(Error::Dynamic(DynamicError::D5));
+        }
+        if let PrimitiveType::Decimal = kind {
+            let previous = state.decimals.entry((template_id, self.id)).or_default();
+            return self.encode_decimal(value, previous, presence_bits, output);
+        }
+        let is_unsigned = matches!(kind, PrimitiveType::UInt32 | PrimitiveType::UInt64);
+        if is_unsigned || matches!(kind, PrimitiveType::SInt32 | PrimitiveType::SInt64) {
+            let previous = state.integers.entry((template_id, self.id)).or_default();
+            return self.integer_operator().encode(
+                value
+                    .map(|value| integer_value(value, is_unsigned))
+                    .transpose()?,
+                !self.mandatory,
+                is_unsigned,
+                previous,
+                presence_bits,
+                output,
+            );
+        }
+        // Field operators on strings and byte vectors aren't supported yet.
+        if self.operator != FieldOperatorInstruction::None {
+            return Err(Error::Static(StaticError::S2));
+        }
+        let value = match value {
+            Some(value) => value,
+            // All nullable types encode NULL the same way.
+            None => {
+                encode_nullable_u64(None, output)?;
+                return Ok(());
+            }
+        };
+        let is_optional = !self.mandatory;
+        match kind {
+            PrimitiveType::Ascii | PrimitiveType::Utf8 | PrimitiveType::Bytes => {
+                let bytes = match value {
+                    slr::FixFieldValue::String(s) => s.as_bytes(),
+                    slr::FixFieldValue::Data(data) => &data[..],
+                    _ => return Err(Error::Dynamic(DynamicError::D1)),
+                };
+                let len = bytes.len() as u64;
+                if is_optional {
+                    encode_nullable_u64(Some(len), output)?;
+                } else {
+                    len.serialize(output)?;
+                }
+                io::Write::write_all(output, bytes)?;
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Encodes a decimal field as an exponent followed by a mantissa. See
+    /// `decode_decimal` for how optional decimals are laid out.
+    fn encode_decimal(
+        &self,
+        value: Option<&slr::FixFieldValue>,
+        previous: &mut (PreviousValue<i64>, PreviousValue<i64>),
+        presence_bits: &mut Vec<bool>,
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let decimal: Option<Decimal> = match value {
+            Some(slr::FixFieldValue::String(s)) => {
+                Some(s.parse().map_err(|_| Error::Dynamic(DynamicError::D1))?)
+            }
+            Some(_) => return Err(Error::Dynamic(DynamicError::D1)),
+            None => None,
+        };
+        let no_operator = ComponentOperator::default();
+        let (exponent_operator, mantissa_operator) = match self.decimal_components() {
+            Some(components) => components,
+            None if self.operator == FieldOperatorInstruction::None => (&no_operator, &no_operator),
+            // Operators on the decimal as a whole aren't supported yet.
+            None => return Err(Error::Static(StaticError::S2)),
+        };
+        let mut exponent = decimal.map(|decimal| i64::from(decimal.exp()));
+        let mut mantissa = decimal.map(|decimal| decimal.mantissa());
+        // A constant exponent fixes the scale of the mantissa, e.g. 1.5 is
+        // sent as 150 if the exponent is always -2. Values with more decimal
+        // places can't be encoded.
+        if let (FieldOperatorInstruction::Constant, Some(target), Some(exp), Some(m)) = (
+            exponent_operator.operator(),
+            exponent_operator.initial_value(),
+            exponent,
+            mantissa,
+        ) {
+            if exp > target {
+                let scaled = 10i64
+                    .checked_pow((exp - target) as u32)
+                    .and_then(|scale| m.checked_mul(scale))
+                    .ok_or(Error::Reportable(ReportableError::R1))?;
+                exponent = Some(target);
+                mantissa = Some(scaled);
+            }
+        }
+        if exponent.is_some_and(|exponent| !(-63..=63).contains(&exponent)) {
+            return Err(Error::Reportable(ReportableError::R1));
+        }
+        let is_optional = !self.mandatory;
+        exponent_operator.encode(
+            exponent,
+            is_optional,
+            false,
+            &mut previous.0,
+            presence_bits,
+            output,
+        )?;
+        if mantissa.is_some() {
+            mantissa_operator.encode(
+                mantissa,
+                false,
+                false,
+                &mut previous.1,
+                presence_bits,
+                output,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn xml_tag_to_operator(tag: &str) -> Result<FieldOperatorInstruction, StaticError> {
+        Ok(match tag {
+            "constant" => FieldOperatorInstruction::Constant,
+            "default" => FieldOperatorInstruction::Default,
+            "copy" => FieldOperatorInstruction::Copy,
+            "increment" => FieldOperatorInstruction::Increment,
+            "delta" => FieldOperatorInstruction::Delta,
+            "tail" => FieldOperatorInstruction::Tail,
+            _ => return Err(StaticError::S1),
+        })
+    }
+
+    fn parse_operator_value(
+        field_type: &FieldType,
+        value: &str,
+    ) -> Result<OperatorValue, StaticError> {
+        Ok(match field_type {
+            FieldType::Primitive(PrimitiveType::Decimal) => {
+                OperatorValue::Decimal(value.parse().map_err(|_| StaticError::S3)?)
+            }
+            FieldType::Primitive(PrimitiveType::SInt32)
+            | FieldType::Primitive(PrimitiveType::SInt64) => {
+                value.parse::<i64>().map_err(|_| StaticError::S3)?;
+                OperatorValue::Raw(value.to_string())
+            }
+            FieldType::Primitive(PrimitiveType::UInt32)
+            | FieldType::Primitive(PrimitiveType::UInt64) => {
+                value.parse::<u64>().map_err(|_| StaticError::S3)?;
+                OperatorValue::Raw(value.to_string())
+            }
+            _ => OperatorValue::Raw(value.to_string()),
+        })
+    }
+
+    /// Returns the initial value of the field operator of `self` if `self` is
+    /// an integer field. Unsigned values are reinterpreted as `i64`, just like
+    /// decoded ones.
+    pub(super) fn integer_initial_value(&self) -> Option<i64> {
+        let value = match &self.operator_value {
+            Some(OperatorValue::Raw(value)) => value,
+            _ => return None,
+        };
+        match self.field_type {
+            FieldType::Primitive(PrimitiveType::SInt32)
+            | FieldType::Primitive(PrimitiveType::SInt64) => value.parse().ok(),
+            FieldType::Primitive(PrimitiveType::UInt32)
+            | FieldType::Primitive(PrimitiveType::UInt64) => {
+                value.parse::<u64>().ok().map(|n| n as i64)
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Templates are used to represent the structure of the data that is to be
@@ -92,53 +561,99 @@ pub struct Template {
     name: String,
     instructions: Vec<FieldInstruction>,
     dictionary: Dictionary,
+    prefer_dictionary_names: bool,
 }
 
 impl Template {
+    /// Parses the first template inside `xml_document`.
+    ///
+    /// A `<templateRef>` with a name can't be resolved without the template it
+    /// refers to, so it results in [`StaticError::S1`]. Use
+    /// [`Templates::from_xml`] for such templates.
     pub fn new(xml_document: &str) -> Result<Template, StaticError> {
-        let document = roxmltree::Document::parse(xml_document).unwrap();
-        let container = document.root().first_element_child().unwrap();
-        let root = container.first_element_child().unwrap();
-        Template::from_xml(Dictionary::empty(), root)
+        let document = roxmltree::Document::parse(xml_document).map_err(|_| StaticError::S1)?;
+        let root = document
+            .root()
+            .first_element_child()
+            .and_then(|container| container.first_element_child())
+            .ok_or(StaticError::S1)?;
+        Template::from_xml(Dictionary::empty(), root, &Templates::new()).map_err(|err| match err {
+            Error::Static(err) => err,
+            _ => StaticError::S1,
+        })
     }
 
-    fn from_xml(dict: Dictionary, root: roxmltree::Node) -> Result<Self, StaticError> {
+    fn from_xml(
+        dict: Dictionary,
+        root: roxmltree::Node,
+        templates: &Templates,
+    ) -> Result<Self, Error> {
         debug_assert_eq!(root.tag_name().name(), "template");
-        let name = root.attribute("name").unwrap();
+        let name = root
+            .attribute("name")
+            .ok_or(Error::Static(StaticError::S1))?;
         let id = {
             let id = root.attribute("id");
             match id {
-                Some(num) => Some(num.parse().map_err(|_| StaticError::S1)?),
+                Some(num) => Some(num.parse().map_err(|_| Error::Static(StaticError::S1))?),
                 None => None,
             }
         };
         let mut instructions = Vec::new();
-        for node in root.children() {
+        Template::push_instructions(root, templates, &mut instructions)?;
+        let template = Template {
+            id,
+            name: name.to_string(),
+            instructions,
+            dictionary: dict,
+            prefer_dictionary_names: false,
+        };
+        Ok(template)
+    }
+
+    fn push_instructions(
+        parent: roxmltree::Node,
+        templates: &Templates,
+        instructions: &mut Vec<FieldInstruction>,
+    ) -> Result<(), Error> {
+        for node in parent.children() {
             if node.is_element() {
                 match node.tag_name().name() {
                     "sequence" => {
-                        for child in node.children() {
-                            if child.is_element() {
-                                let instruction = FieldInstruction::from_template(child)?;
-                                instructions.push(instruction);
-                            }
-                        }
+                        Template::push_instructions(node, templates, instructions)?;
+                    }
+                    // Groups are inlined after an instruction that tells how
+                    // many of the following instructions belong to them.
+                    "group" => {
+                        let position = instructions.len();
+                        let group =
+                            FieldInstruction::group_from_template(node).map_err(Error::Static)?;
+                        instructions.push(group);
+                        Template::push_instructions(node, templates, instructions)?;
+                        let len = (instructions.len() - position - 1) as u32;
+                        instructions[position].field_type = FieldType::Group(len);
                     }
                     "typeRef" => (),
+                    // Dynamic template references (i.e. without a name) can
+                    // only be resolved while decoding the stream.
+                    "templateRef" => match node.attribute("name") {
+                        Some(name) => {
+                            let template = templates
+                                .get(name)
+                                .ok_or(Error::Dynamic(DynamicError::D8))?;
+                            instructions.extend(template.iter_items().cloned());
+                        }
+                        None => instructions.push(FieldInstruction::dynamic_template_ref()),
+                    },
                     _ => {
-                        let instruction = FieldInstruction::from_template(node)?;
+                        let instruction =
+                            FieldInstruction::from_template(node).map_err(Error::Static)?;
                         instructions.push(instruction);
                     }
                 }
             }
         }
-        let template = Template {
-            id,
-            name: name.to_string(),
-            instructions,
-            dictionary: dict,
-        };
-        Ok(template)
+        Ok(())
     }
 
     pub fn id(&self) -> Option<u32> {
@@ -153,6 +668,128 @@ impl Template {
         self.instructions.iter()
     }
 
+    /// Sets the FIX [`Dictionary`] that field IDs of `self` refer to. It's
+    /// empty by default.
+    pub fn set_dictionary(&mut self, dict: Dictionary) {
+        self.dictionary = dict;
+    }
+
+    /// Names of template fields may be stale, i.e. differ from the name that
+    /// the dictionary of `self` gives to the same tag. If `prefer` is true,
+    /// [`Template::field_name`] resolves names via field IDs instead. This is
+    /// turned off by default.
+    pub fn set_prefer_dictionary_names(&mut self, prefer: bool) {
+        self.prefer_dictionary_names = prefer;
+    }
+
+    /// Returns the name of `instruction` for output purposes: either its
+    /// template name or, if [`Template::set_prefer_dictionary_names`] is
+    /// enabled, the dictionary name of its field ID. Instructions with an ID
+    /// unknown to the dictionary always keep their template name.
+    pub fn field_name<'a>(&'a self, instruction: &'a FieldInstruction) -> &'a str {
+        if self.prefer_dictionary_names {
+            if let Some(field) = self.dictionary.field_by_tag(instruction.id()) {
+                return field.name();
+            }
+        }
+        instruction.name()
+    }
+
+    pub(super) fn instructions(&self) -> &[FieldInstruction] {
+        &self.instructions[..]
+    }
+
+    /// Returns `true` if `self` only differs from `old` by optional
+    /// instructions appended at the end, i.e. there are no
+    /// [`Incompatibility`]s between them.
+    ///
+    /// This is a check of the definitions only. FAST streams don't delimit
+    /// fields, so a decoder with `self` still reads the appended fields (and
+    /// their presence map bits) from streams encoded with `old`. Both sides
+    /// must switch to `self` at the same time.
+    pub fn is_compatible_with(&self, old: &Template) -> bool {
+        self.incompatibilities(old).is_empty()
+    }
+
+    /// Returns all differences between the definitions of `self` and `old`,
+    /// as [`Incompatibility`]s. See [`Template::is_compatible_with`]. The only
+    /// allowed changes are new optional fields at the end of `self`;
+    /// all instructions of `old` must appear in `self`, in the same order and
+    /// with the same field ID, type, presence and operators.
+    pub fn incompatibilities(&self, old: &Template) -> Vec<Incompatibility> {
+        let mut incompatibilities = Vec::new();
+        if self.id != old.id {
+            incompatibilities.push(Incompatibility::IdChanged);
+        }
+        for (position, old_instruction) in old.instructions.iter().enumerate() {
+            match self.instructions.get(position) {
+                Some(instruction) if instruction.is_compatible_with(old_instruction) => (),
+                Some(_) => incompatibilities.push(Incompatibility::FieldChanged(position)),
+                None => incompatibilities.push(Incompatibility::FieldRemoved(position)),
+            }
+        }
+        for (position, instruction) in self.instructions.iter().enumerate() {
+            if position >= old.instructions.len() && instruction.is_mandatory() {
+                incompatibilities.push(Incompatibility::MandatoryFieldAdded(position));
+            }
+        }
+        incompatibilities
+    }
+
+    /// Returns the presence map bits that `self` may consume, in order. The
+    /// first one always signals the template identifier. This is meant for
+    /// template authors, to debug misaligned streams.
+    ///
+    /// The mantissa of an optional decimal field only consumes its bit if the
+    /// field is present. Fields inside groups are left out, because groups
+    /// come with their own presence map.
+    pub fn pmap_layout(&self) -> Vec<PmapBit> {
+        let mut layout = vec![PmapBit::TemplateId];
+        layout.extend(pmap_bits(&self.instructions[..]));
+        layout
+    }
+
+    /// Encodes `message` according to `self`, mapping the fields of `message`
+    /// to instructions by tag. The template identifier is always included and
+    /// no previous values are assumed, so the output can be decoded on its own
+    /// by a fresh decoder. See [`Fast::encode_message`](super::Fast::encode_message)
+    /// for streams of messages.
+    ///
+    /// Mandatory fields that are missing from `message` result in an error.
+    /// Fields of `message` which don't appear in `self` are ignored.
+    pub fn encode_message(&self, message: &slr::Message) -> Result<Vec<u8>, Error> {
+        self.encode_with_state(message, &mut OperatorState::default())
+    }
+
+    /// Encodes `message` according to `self`, building the presence map out
+    /// of the operator `state` left by previous messages, which is updated.
+    pub(super) fn encode_with_state(
+        &self,
+        message: &slr::Message,
+        state: &mut OperatorState,
+    ) -> Result<Vec<u8>, Error> {
+        let template_id = self.id.ok_or(Error::Dynamic(DynamicError::D9))?;
+        let mut body = Vec::new();
+        // The template identifier has a copy operator.
+        let mut presence_bits = vec![state.template_id != Some(template_id)];
+        if state.template_id != Some(template_id) {
+            template_id.serialize(&mut body)?;
+            state.template_id = Some(template_id);
+        }
+        encode_instructions(
+            template_id,
+            &self.instructions[..],
+            message,
+            state,
+            &mut presence_bits,
+            &mut body,
+        )?;
+        let mut output = Vec::new();
+        encode_stop_bit_bitvec(&presence_bits[..], &mut output)?;
+        output.extend(body);
+        Ok(output)
+    }
+
     fn xml_tag_to_instruction(tag: &str) -> Result<FieldType, StaticError> {
         Ok(match tag {
             "string" => FieldType::Primitive(PrimitiveType::Ascii),
@@ -161,7 +798,7 @@ impl Template {
             "uInt64" => FieldType::Primitive(PrimitiveType::UInt64),
             "int64" => FieldType::Primitive(PrimitiveType::SInt64),
             "decimal" => FieldType::Primitive(PrimitiveType::Decimal),
-            "byteVector" => FieldType::Primitive(PrimitiveType::Decimal),
+            "byteVector" => FieldType::Primitive(PrimitiveType::Bytes),
             "length" => FieldType::Primitive(PrimitiveType::UInt32),
             _ => return Err(StaticError::S1),
         })
@@ -176,11 +813,258 @@ impl Template {
     }
 }
 
+/// A reason why a [`Template`] is not backward compatible with an older
+/// version of it. See [`Template::incompatibilities`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// The template ID changed.
+    IdChanged,
+    /// The instruction at this position has a different field ID, type,
+    /// presence or operator.
+    FieldChanged(usize),
+    /// The instruction at this position was removed.
+    FieldRemoved(usize),
+    /// A mandatory instruction was added at this position.
+    MandatoryFieldAdded(usize),
+}
+
+/// What a presence map bit signals. See [`Template::pmap_layout`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PmapBit {
+    /// The presence of the template identifier.
+    TemplateId,
+    /// The field with this ID.
+    Field(u32),
+    /// The exponent of the decimal field with this ID.
+    Exponent(u32),
+    /// The mantissa of the decimal field with this ID.
+    Mantissa(u32),
+}
+
+/// Returns the presence map bits that `instructions` may consume, in order,
+/// not counting the fields inside groups.
+pub(super) fn pmap_bits(instructions: &[FieldInstruction]) -> Vec<PmapBit> {
+    let mut bits = Vec::new();
+    let mut instructions = instructions.iter();
+    while let Some(instruction) = instructions.next() {
+        let id = instruction.id();
+        let is_optional = !instruction.is_mandatory();
+        match (instruction.kind(), instruction.decimal_components()) {
+            (_, Some((exponent, mantissa))) => {
+                if uses_pmap_bit(exponent.operator(), is_optional) {
+                    bits.push(PmapBit::Exponent(id));
+                }
+                if uses_pmap_bit(mantissa.operator(), false) {
+                    bits.push(PmapBit::Mantissa(id));
+                }
+            }
+            (FieldType::Group(len), None) => {
+                if is_optional {
+                    bits.push(PmapBit::Field(id));
+                }
+                for _ in 0..*len {
+                    instructions.next();
+                }
+            }
+            (FieldType::Primitive(_), None) => {
+                if uses_pmap_bit(instruction.operator(), is_optional) {
+                    bits.push(PmapBit::Field(id));
+                }
+            }
+            // The referenced template comes with its own presence map.
+            (FieldType::DynamicTemplateRef, None) => (),
+        }
+    }
+    bits
+}
+
+/// Encodes the fields of `message` described by `instructions`.
+///
+/// An optional group is present if any of its fields is. A group whose fields
+/// consume presence map bits is preceded by its own presence map.
+fn encode_instructions(
+    template_id: u32,
+    instructions: &[FieldInstruction],
+    message: &slr::Message,
+    state: &mut OperatorState,
+    presence_bits: &mut Vec<bool>,
+    output: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let mut position = 0;
+    while let Some(instruction) = instructions.get(position) {
+        position += 1;
+        let len = match instruction.kind() {
+            FieldType::Group(len) => *len as usize,
+            FieldType::Primitive(_) => {
+                let value = message.get_field(instruction.id());
+                instruction.encode_field(value, template_id, state, presence_bits, output)?;
+                continue;
+            }
+            // The encoder has no way to tell which template to reference.
+            FieldType::DynamicTemplateRef => return Err(Error::Dynamic(DynamicError::D9)),
+        };
+        let children = &instructions[position..position + len];
+        position += len;
+        if !instruction.is_mandatory() {
+            let is_present = children
+                .iter()
+                .any(|child| message.get_field(child.id()).is_some());
+            presence_bits.push(is_present);
+            if !is_present {
+                continue;
+            }
+        }
+        if pmap_bits(children).is_empty() {
+            encode_instructions(template_id, children, message, state, presence_bits, output)?;
+        } else {
+            let mut group_presence_bits = Vec::new();
+            let mut group_output = Vec::new();
+            encode_instructions(
+                template_id,
+                children,
+                message,
+                state,
+                &mut group_presence_bits,
+                &mut group_output,
+            )?;
+            encode_stop_bit_bitvec(&group_presence_bits[..], output)?;
+            output.extend(group_output);
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if a field with `operator` consumes a presence map bit.
+fn uses_pmap_bit(operator: &FieldOperatorInstruction, is_optional: bool) -> bool {
+    match operator {
+        FieldOperatorInstruction::None | FieldOperatorInstruction::Delta => false,
+        FieldOperatorInstruction::Constant => is_optional,
+        FieldOperatorInstruction::Default
+        | FieldOperatorInstruction::Copy
+        | FieldOperatorInstruction::Increment
+        | FieldOperatorInstruction::Tail => true,
+    }
+}
+
+/// Unsigned values are reinterpreted as `i64`, just like in the decoder, so
+/// that the whole range of `uInt64` fits.
+fn integer_value(value: &slr::FixFieldValue, is_unsigned: bool) -> Result<i64, Error> {
+    match value {
+        slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(n))) if is_unsigned && *n < 0 => {
+            Err(Error::Dynamic(DynamicError::D2))
+        }
+        slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(n))) => Ok(i64::from(*n)),
+        slr::FixFieldValue::String(s) if is_unsigned => s
+            .parse::<u64>()
+            .map(|n| n as i64)
+            .map_err(|_| Error::Dynamic(DynamicError::D1)),
+        slr::FixFieldValue::String(s) => s.parse().map_err(|_| Error::Dynamic(DynamicError::D1)),
+        _ => Err(Error::Dynamic(DynamicError::D1)),
+    }
+}
+
+fn write_integer(
+    value: Option<i64>,
+    is_optional: bool,
+    is_unsigned: bool,
+    output: &mut Vec<u8>,
+) -> Result<(), Error> {
+    if is_unsigned {
+        let value = value.map(|value| value as u64);
+        if is_optional {
+            encode_nullable_u64(value, output)?;
+        } else {
+            value
+                .ok_or(Error::Dynamic(DynamicError::D5))?
+                .serialize(output)?;
+        }
+    } else if is_optional {
+        encode_nullable_i64(value, output)?;
+    } else {
+        value
+            .ok_or(Error::Dynamic(DynamicError::D5))?
+            .serialize(output)?;
+    }
+    Ok(())
+}
+
+/// A registry of [`Template`]s, indexed by name. Static template references
+/// (i.e. `<templateRef name="..."/>`) are resolved against it.
+///
+/// ```
+/// use fefix::codec::fast::{Fast, Templates};
+///
+/// let xml = r#"
+/// <templates>
+///   <template name="Heartbeat" id="1">
+///     <uInt32 name="MsgSeqNum" id="34"><increment/></uInt32>
+///   </template>
+/// </templates>"#;
+/// let templates = Templates::from_xml(xml).unwrap();
+/// assert_eq!(templates.get("Heartbeat").unwrap().id(), Some(1));
+/// let _decoder = Fast::new().with_templates(templates);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Templates {
+    templates: HashMap<String, Template>,
+}
+
+impl Templates {
+    /// Creates an empty [`Templates`] registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses all templates inside `xml_document`, in document order. A
+    /// `<templateRef>` can only refer to templates that were defined before it.
+    pub fn from_xml(xml_document: &str) -> Result<Self, Error> {
+        let document =
+            roxmltree::Document::parse(xml_document).map_err(|_| Error::Static(StaticError::S1))?;
+        let container = document
+            .root()
+            .first_element_child()
+            .ok_or(Error::Static(StaticError::S1))?;
+        let mut templates = Templates::new();
+        for node in container.children() {
+            if node.is_element() && node.tag_name().name() == "template" {
+                let template = Template::from_xml(Dictionary::empty(), node, &templates)?;
+                templates.insert(template);
+            }
+        }
+        Ok(templates)
+    }
+
+    /// Adds `template` to `self`, replacing any other template with the same
+    /// name.
+    pub fn insert(&mut self, template: Template) {
+        self.templates.insert(template.name().to_string(), template);
+    }
+
+    /// Returns the [`Template`] called `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    /// Returns the [`Template`] with the template ID `id`, if any.
+    pub fn get_by_id(&self, id: u32) -> Option<&Template> {
+        self.templates
+            .values()
+            .find(|template| template.id() == Some(id))
+    }
+
+    /// Returns an [`Iterator`] over all templates in `self`, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = &Template> {
+        self.templates.values()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     const SIMPLE_TEMPLATE: &str = std::include_str!("templates/example.xml");
+    const TEMPLATE_REF: &str = std::include_str!("templates/template_ref.xml");
 
     #[test]
     fn first_field_instruction() {
@@ -188,4 +1072,490 @@ mod test {
         let first_field_instruction = template.instructions.get(0).unwrap();
         assert_eq!(first_field_instruction.name, "BeginString");
     }
+
+    #[test]
+    fn stale_field_name_is_resolved_by_id() {
+        let xml = r#"
+<templates>
+  <template name="Heartbeat" id="1">
+    <string name="SenderCompId" id="49"> <copy/> </string>
+    <string name="MyCustomField" id="9999"/>
+  </template>
+</templates>"#;
+        let templates = Templates::from_xml(xml).unwrap();
+        let mut template = templates.get("Heartbeat").unwrap().clone();
+        template.set_dictionary(Dictionary::from_version(crate::app::Version::Fix44));
+        let names = |template: &Template| -> Vec<String> {
+            template
+                .iter_items()
+                .map(|instruction| template.field_name(instruction).to_string())
+                .collect()
+        };
+        assert_eq!(names(&template), vec!["SenderCompId", "MyCustomField"]);
+        template.set_prefer_dictionary_names(true);
+        assert_eq!(names(&template), vec!["SenderCompID", "MyCustomField"]);
+    }
+
+    #[test]
+    fn template_ref_inlines_referenced_instructions() {
+        let templates = Templates::from_xml(TEMPLATE_REF).unwrap();
+        let names = |name: &str| -> Vec<String> {
+            templates
+                .get(name)
+                .unwrap()
+                .iter_items()
+                .map(|instruction| instruction.name.clone())
+                .collect()
+        };
+        assert_eq!(
+            names("Header"),
+            vec!["MessageType", "SenderCompID", "MsgSeqNum"]
+        );
+        assert_eq!(
+            names("Heartbeat"),
+            vec!["MessageType", "SenderCompID", "MsgSeqNum", "TestReqID"]
+        );
+        assert_eq!(
+            names("MDRefresh"),
+            vec![
+                "MessageType",
+                "SenderCompID",
+                "MsgSeqNum",
+                "NoMDEntries",
+                "MDEntryPx"
+            ]
+        );
+    }
+
+    #[test]
+    fn template_ref_to_unknown_template_is_an_error() {
+        let xml = r#"<templates><template name="A"><templateRef name="B"/></template></templates>"#;
+        assert!(matches!(
+            Templates::from_xml(xml),
+            Err(Error::Dynamic(DynamicError::D8))
+        ));
+    }
+
+    #[test]
+    fn decimal_operator_values() {
+        let xml = r#"
+<templates>
+  <template name="Quote">
+    <decimal name="Price" id="44"> <constant value="100.00"/> </decimal>
+    <decimal name="Spread" id="188"> <default value="1.50"/> </decimal>
+    <string name="Symbol" id="55"> <constant value="ESU6"/> </string>
+  </template>
+</templates>"#;
+        let template = Template::new(xml).unwrap();
+        let instructions: Vec<&FieldInstruction> = template.iter_items().collect();
+        assert_eq!(
+            instructions[0].operator(),
+            &FieldOperatorInstruction::Constant
+        );
+        assert_eq!(
+            instructions[0].operator_value(),
+            Some(&OperatorValue::Decimal(Decimal::new(100, 0)))
+        );
+        assert_eq!(
+            instructions[1].operator(),
+            &FieldOperatorInstruction::Default
+        );
+        assert_eq!(
+            instructions[1].operator_value(),
+            Some(&OperatorValue::Decimal(Decimal::new(15, -1)))
+        );
+        assert_eq!(
+            instructions[2].operator_value(),
+            Some(&OperatorValue::Raw("ESU6".to_string()))
+        );
+    }
+
+    #[test]
+    fn decimal_with_individual_operators() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <decimal name="Price" id="44" presence="optional">
+      <exponent><default value="-2"/></exponent>
+      <mantissa><delta value="100"/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+        let template = Template::new(xml).unwrap();
+        let instruction = template.iter_items().next().unwrap();
+        assert!(!instruction.is_mandatory());
+        assert_eq!(instruction.operator(), &FieldOperatorInstruction::None);
+        let (exponent, mantissa) = instruction.decimal_components().unwrap();
+        assert_eq!(exponent.operator(), &FieldOperatorInstruction::Default);
+        assert_eq!(exponent.initial_value(), Some(-2));
+        assert_eq!(mantissa.operator(), &FieldOperatorInstruction::Delta);
+        assert_eq!(mantissa.initial_value(), Some(100));
+        // The mantissa is always mandatory.
+        let xml = xml.replace(r#"<delta value="100"/>"#, "<default/>");
+        assert!(matches!(Template::new(&xml), Err(StaticError::S5)));
+        // Individual operators can't be mixed with a whole-field operator.
+        let xml = xml
+            .replace("<default/>", "<delta/>")
+            .replace("</mantissa>", "</mantissa><copy/>");
+        assert!(matches!(Template::new(&xml), Err(StaticError::S1)));
+    }
+
+    #[test]
+    fn malformed_xml_is_an_error() {
+        assert!(matches!(Template::new("<templates>"), Err(StaticError::S1)));
+        assert!(matches!(
+            Template::new("<templates/>"),
+            Err(StaticError::S1)
+        ));
+        let xml = r#"<templates><template name="Quote"><templateRef name="Order"/></template></templates>"#;
+        assert!(matches!(Template::new(xml), Err(StaticError::S1)));
+    }
+
+    #[test]
+    fn invalid_decimal_operator_value() {
+        let xml = r#"<templates><template name="Quote"><decimal name="Price" id="44"><constant value="1.5.0"/></decimal></template></templates>"#;
+        assert!(matches!(Template::new(xml), Err(StaticError::S3)));
+    }
+
+    const TEMPLATE_ORDER: &str = r#"
+<templates>
+  <template name="Order" id="1">
+    <uInt32 name="MsgSeqNum" id="34"/>
+    <string name="Symbol" id="55"/>
+    <decimal name="Price" id="44" presence="optional">
+      <exponent/>
+      <mantissa><copy/></mantissa>
+    </decimal>
+    <string name="Text" id="58" presence="optional"/>
+  </template>
+</templates>"#;
+
+    #[test]
+    fn encode_message_then_decode() {
+        use crate::codec::fast::Fast;
+        use crate::codec::Decoder;
+
+        let template = Template::new(TEMPLATE_ORDER).unwrap();
+        let mut message = slr::Message::new();
+        message.add_int(34, 1);
+        message.add_str(55, "EUR/USD");
+        message.add_str(44, "1.5");
+        let bytes = template.encode_message(&message).unwrap();
+        let mut decoder = Fast::new().with_template(template);
+        let decoded = decoder.decode(&bytes[..]).unwrap();
+        for tag in &[34, 55, 44, 58] {
+            assert_eq!(decoded.get_field(*tag), message.get_field(*tag));
+        }
+    }
+
+    #[test]
+    fn encode_decimal_with_constant_exponent() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <decimal name="Price" id="44">
+      <exponent><constant value="-2"/></exponent>
+      <mantissa><delta/></mantissa>
+    </decimal>
+  </template>
+</templates>"#;
+        let template = Template::new(xml).unwrap();
+        let mut message = slr::Message::new();
+        // Rescaled to a mantissa of 150.
+        message.add_str(44, "1.5");
+        assert_eq!(
+            template.encode_message(&message).unwrap(),
+            vec![0xc0, 0x81, 0x01, 0x96]
+        );
+        message.add_str(44, "1.505");
+        assert!(matches!(
+            template.encode_message(&message),
+            Err(Error::Dynamic(DynamicError::D3))
+        ));
+    }
+
+    const TEMPLATE_DEFAULT_INTEGERS: &str = r#"
+<templates>
+  <template name="Defaults" id="3">
+    <uInt32 name="MandatoryUnsigned" id="1"><default value="10"/></uInt32>
+    <uInt32 name="OptionalUnsigned" id="2" presence="optional"><default value="10"/></uInt32>
+    <int32 name="MandatorySigned" id="3"><default value="-5"/></int32>
+    <int32 name="OptionalSigned" id="4" presence="optional"><default/></int32>
+  </template>
+</templates>"#;
+
+    #[test]
+    fn integers_with_default_operator() {
+        use crate::codec::fast::Fast;
+        use crate::codec::Decoder;
+
+        let template = Template::new(TEMPLATE_DEFAULT_INTEGERS).unwrap();
+        let mut decoder = Fast::new().with_template(template.clone());
+        // Initial values (or no value at all, lacking one) take up neither
+        // bytes nor set bits.
+        let mut message = slr::Message::new();
+        message.add_int(1, 10);
+        message.add_int(2, 10);
+        message.add_int(3, -5);
+        let bytes = template.encode_message(&message).unwrap();
+        assert_eq!(bytes, vec![0xc0, 0x83]);
+        let decoded = decoder.decode(&bytes[..]).unwrap();
+        for tag in &[1, 2, 3, 4] {
+            assert_eq!(decoded.get_field(*tag), message.get_field(*tag));
+        }
+        // Explicit values, including a NULL one to override an initial value.
+        let mut message = slr::Message::new();
+        message.add_int(1, 1);
+        message.add_int(3, 7);
+        message.add_int(4, -1);
+        let bytes = template.encode_message(&message).unwrap();
+        assert_eq!(bytes, vec![0xfc, 0x83, 0x81, 0x80, 0x87, 0xff]);
+        let decoded = decoder.decode(&bytes[..]).unwrap();
+        for tag in &[1, 2, 3, 4] {
+            assert_eq!(decoded.get_field(*tag), message.get_field(*tag));
+        }
+    }
+
+    #[test]
+    fn mandatory_default_without_initial_value_is_an_error() {
+        let xml = TEMPLATE_DEFAULT_INTEGERS.replace(r#"<default value="10"/>"#, "<default/>");
+        assert!(matches!(Template::new(&xml), Err(StaticError::S5)));
+    }
+
+    #[test]
+    fn encode_message_without_mandatory_field_is_an_error() {
+        let template = Template::new(TEMPLATE_ORDER).unwrap();
+        let mut message = slr::Message::new();
+        message.add_int(34, 1);
+        message.add_str(44, "1.5");
+        assert!(matches!(
+            template.encode_message(&message),
+            Err(Error::Dynamic(DynamicError::D5))
+        ));
+    }
+
+    #[test]
+    fn encode_message_with_unsupported_operator_is_an_error() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <string name="Symbol" id="55"><copy/></string>
+  </template>
+  <template name="Price" id="2">
+    <decimal name="Price" id="44"><copy/></decimal>
+  </template>
+</templates>"#;
+        let templates = Templates::from_xml(xml).unwrap();
+        let mut message = slr::Message::new();
+        message.add_str(55, "EUR/USD");
+        message.add_str(44, "1.5");
+        for id in 1..=2 {
+            assert!(matches!(
+                templates.get_by_id(id).unwrap().encode_message(&message),
+                Err(Error::Static(StaticError::S2))
+            ));
+        }
+    }
+
+    #[test]
+    fn encode_message_with_dynamic_template_ref_is_an_error() {
+        let xml = r#"
+<templates>
+  <template name="Quote" id="1">
+    <string name="Symbol" id="55"/>
+    <templateRef/>
+  </template>
+</templates>"#;
+        let template = Template::new(xml).unwrap();
+        let mut message = slr::Message::new();
+        message.add_str(55, "EUR/USD");
+        assert!(matches!(
+            template.encode_message(&message),
+            Err(Error::Dynamic(DynamicError::D9))
+        ));
+    }
+
+    #[test]
+    fn string_with_unicode_charset() {
+        let xml = r#"
+<templates>
+  <template name="News" id="2">
+    <string name="Headline" id="148" charset="unicode"/>
+    <string name="Text" id="58" charset="ascii"/>
+  </template>
+</templates>"#;
+        let template = Template::new(xml).unwrap();
+        let kinds: Vec<&FieldType> = template.iter_items().map(|i| i.kind()).collect();
+        assert!(matches!(
+            kinds[0],
+            FieldType::Primitive(PrimitiveType::Utf8)
+        ));
+        assert!(matches!(
+            kinds[1],
+            FieldType::Primitive(PrimitiveType::Ascii)
+        ));
+    }
+
+    #[test]
+    fn unrecognized_attributes_are_ignored() {
+        let xml = r#"
+<templates xmlns="http://www.fixprotocol.org/ns/fast/td/1.1"
+    xmlns:ext="http://example.com/ns/fast-ext"
+    templateNs="http://www.fixprotocol.org/ns/templates/sample"
+    ns="http://www.fixprotocol.org/ns/fix"
+    dictionary="template">
+  <template name="Header" ns="http://www.fixprotocol.org/ns/fix" ext:owner="md">
+    <uInt32 name="MsgSeqNum" id="34" ns="http://www.fixprotocol.org/ns/fix" charset="ascii">
+      <increment dictionary="global" key="MsgSeqNum"/>
+    </uInt32>
+  </template>
+  <template name="Quote" id="3" templateNs="http://www.fixprotocol.org/ns/templates/sample"
+      dictionary="type" ext:version="2">
+    <templateRef name="Header" templateNs="http://www.fixprotocol.org/ns/templates/sample"/>
+    <string name="Symbol" id="55" ns="http://www.fixprotocol.org/ns/fix" ext:note="x">
+      <copy dictionary="template" key="Symbol"/>
+    </string>
+    <sequence name="Legs" ns="http://www.fixprotocol.org/ns/fix" dictionary="template">
+      <length name="NoLegs" id="555" ns="http://www.fixprotocol.org/ns/fix"/>
+      <int64 name="LegQty" id="687" presence="optional" ext:unit="shares"/>
+    </sequence>
+  </template>
+</templates>"#;
+        let templates = Templates::from_xml(xml).unwrap();
+        let template = templates.get("Quote").unwrap();
+        assert_eq!(template.id(), Some(3));
+        let fields: Vec<(&str, u32, bool, &FieldOperatorInstruction)> = template
+            .iter_items()
+            .map(|i| (i.name.as_str(), i.id(), i.is_mandatory(), i.operator()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("MsgSeqNum", 34, true, &FieldOperatorInstruction::Increment),
+                ("Symbol", 55, true, &FieldOperatorInstruction::Copy),
+                ("NoLegs", 555, true, &FieldOperatorInstruction::None),
+                ("LegQty", 687, false, &FieldOperatorInstruction::None),
+            ]
+        );
+    }
+
+    #[test]
+    fn pmap_layout_of_order_template() {
+        // Only the mantissa of `Price`, with its copy operator, takes a bit
+        // besides the template identifier.
+        let template = Template::new(TEMPLATE_ORDER).unwrap();
+        assert_eq!(
+            template.pmap_layout(),
+            vec![PmapBit::TemplateId, PmapBit::Mantissa(44)]
+        );
+        let with_operators = TEMPLATE_ORDER
+            .replace(
+                r#"<uInt32 name="MsgSeqNum" id="34"/>"#,
+                r#"<uInt32 name="MsgSeqNum" id="34"><increment/></uInt32>"#,
+            )
+            .replace(
+                r#"<string name="Text" id="58" presence="optional"/>"#,
+                r#"<string name="Text" id="58" presence="optional"><constant value="x"/></string>"#,
+            );
+        let template = Template::new(&with_operators).unwrap();
+        assert_eq!(
+            template.pmap_layout(),
+            vec![
+                PmapBit::TemplateId,
+                PmapBit::Field(34),
+                PmapBit::Mantissa(44),
+                PmapBit::Field(58)
+            ]
+        );
+    }
+
+    const TEMPLATE_GROUP: &str = r#"
+<templates>
+  <template name="Quote" id="2">
+    <string name="Symbol" id="55"/>
+    <group name="Bid" presence="optional">
+      <decimal name="BidPx" id="132">
+        <mantissa><copy/></mantissa>
+      </decimal>
+      <uInt32 name="BidSize" id="134"/>
+    </group>
+    <string name="Text" id="58" presence="optional"/>
+  </template>
+</templates>"#;
+
+    #[test]
+    fn group_is_inlined_behind_a_pmap_bit() {
+        use crate::codec::fast::Fast;
+        use crate::codec::Decoder;
+
+        let template = Template::new(TEMPLATE_GROUP).unwrap();
+        let kinds: Vec<&FieldType> = template.iter_items().map(|i| i.kind()).collect();
+        assert_eq!(kinds.len(), 5);
+        assert_eq!(kinds[1], &FieldType::Group(2));
+        // The mantissa of `BidPx` belongs to the presence map of the group.
+        assert_eq!(
+            template.pmap_layout(),
+            vec![PmapBit::TemplateId, PmapBit::Field(0)]
+        );
+        let mut decoder = Fast::new().with_template(template.clone());
+        let mut message = slr::Message::new();
+        message.add_str(55, "EUR/USD");
+        message.add_str(58, "indicative");
+        let bytes = template.encode_message(&message).unwrap();
+        let decoded = decoder.decode(&bytes[..]).unwrap();
+        for tag in &[55, 132, 134, 58] {
+            assert_eq!(decoded.get_field(*tag), message.get_field(*tag));
+        }
+        message.add_str(132, "1.25");
+        message.add_int(134, 100);
+        let bytes = template.encode_message(&message).unwrap();
+        let decoded = decoder.decode(&bytes[..]).unwrap();
+        for tag in &[55, 132, 134, 58] {
+            assert_eq!(decoded.get_field(*tag), message.get_field(*tag));
+        }
+    }
+
+    #[test]
+    fn field_after_group_has_pmap_bit() {
+        use crate::codec::fast::Fast;
+        use crate::codec::Decoder;
+
+        let xml = TEMPLATE_GROUP.replace(
+            r#"<string name="Text" id="58" presence="optional"/>"#,
+            r#"<uInt32 name="MsgSeqNum" id="34"><copy/></uInt32>"#,
+        );
+        let template = Template::new(&xml).unwrap();
+        assert_eq!(
+            template.pmap_layout(),
+            vec![PmapBit::TemplateId, PmapBit::Field(0), PmapBit::Field(34)]
+        );
+        let mut decoder = Fast::new().with_template(template.clone());
+        let mut message = slr::Message::new();
+        message.add_str(55, "EUR/USD");
+        message.add_int(34, 7);
+        let bytes = template.encode_message(&message).unwrap();
+        let decoded = decoder.decode(&bytes[..]).unwrap();
+        assert_eq!(decoded.seq_num(), Some(7));
+    }
+
+    #[test]
+    fn compatibility_with_older_template() {
+        let old = Template::new(TEMPLATE_ORDER).unwrap();
+        let added_optional_field = TEMPLATE_ORDER.replace(
+            "  </template>",
+            r#"  <uInt64 name="TransactTime" id="60" presence="optional"/>
+  </template>"#,
+        );
+        let new = Template::new(&added_optional_field).unwrap();
+        assert!(new.is_compatible_with(&old));
+        assert!(!old.is_compatible_with(&new));
+        let changed_type =
+            TEMPLATE_ORDER.replace(r#"<uInt32 name="MsgSeqNum""#, r#"<uInt64 name="MsgSeqNum""#);
+        let new = Template::new(&changed_type).unwrap();
+        assert!(!new.is_compatible_with(&old));
+        assert_eq!(
+            new.incompatibilities(&old),
+            vec![Incompatibility::FieldChanged(0)]
+        );
+    }
 }
@@ -1,12 +1,15 @@
 //! JSON encoding for FIX support.
 
 use crate::app::slr;
-use crate::app::TsrMessageRef;
+use crate::app::{TsrMessageRef, Version};
 use crate::codec::*;
+use crate::dictionary::Field;
+use crate::dt::{self, DataType};
 use crate::Dictionary;
 use serde_json::json;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::io;
 
 /// Configuration interface for [`json::Codec`](Codec).
 pub trait Config: Clone {
@@ -20,6 +23,217 @@ pub trait Config: Clone {
     fn pretty_print(&self) -> bool {
         false
     }
+
+    /// How to encode fields with an empty value. FIX doesn't distinguish
+    /// between null and empty values, but JSON does. Decoding accepts all
+    /// forms regardless of this setting.
+    ///
+    /// This is [`EmptyFieldPolicy::EmptyString`] by default.
+    #[inline(always)]
+    fn empty_field_policy(&self) -> EmptyFieldPolicy {
+        EmptyFieldPolicy::EmptyString
+    }
+
+    /// This setting indicates that fields which are not defined in the
+    /// dictionary should be carried through by tag number instead of being
+    /// rejected, e.g. `"9999": "foo"`. Useful for lossless relays of messages
+    /// with custom fields.
+    ///
+    /// This is turned off by default.
+    #[inline(always)]
+    fn allow_unknown_fields(&self) -> bool {
+        false
+    }
+
+    /// This setting indicates that extension fields introduced by FIXT.1.1
+    /// (e.g. `ApplExtID <1156>`) should be rejected if the dictionary doesn't
+    /// define them. Otherwise they're carried through by name as plain
+    /// strings in the header.
+    ///
+    /// This is turned off by default.
+    #[inline(always)]
+    fn strict_extension_fields(&self) -> bool {
+        false
+    }
+
+    /// How to encode `Boolean` fields (e.g. `PossDupFlag <43>`). Decoding
+    /// accepts all styles regardless of this setting.
+    ///
+    /// This is [`BooleanStyle::YN`] by default.
+    #[inline(always)]
+    fn boolean_style(&self) -> BooleanStyle {
+        BooleanStyle::YN
+    }
+
+    /// How to encode fields that are redacted with
+    /// [`Codec::set_redacted_tags`].
+    ///
+    /// This is [`RedactionStyle::Mask`] by default.
+    #[inline(always)]
+    fn redaction_style(&self) -> RedactionStyle {
+        RedactionStyle::Mask
+    }
+
+    /// This setting indicates that `Data` fields which hold a FIXML document
+    /// (e.g. `XmlData <213>`) should be encoded as nested JSON objects of
+    /// fields by name, rather than as Base64 strings. Decoding accepts both
+    /// forms regardless of this setting; nested objects are turned back into
+    /// FIXML documents.
+    ///
+    /// The built-in dictionaries don't come with the abbreviated names of
+    /// FIXML, so elements and attributes are matched against the full names
+    /// of messages, components and fields instead, e.g.
+    /// `<FIXML><NewOrderSingle ClOrdID="1"/></FIXML>`. Documents with
+    /// repeating groups or unknown names are left as Base64 strings.
+    ///
+    /// This is turned off by default.
+    #[inline(always)]
+    fn embedded_fixml(&self) -> bool {
+        false
+    }
+
+    /// How to encode string fields which hold invalid UTF-8 (i.e. raw bytes
+    /// rather than text, as decoded from a malformed feed). `Data` fields are
+    /// always Base64 strings regardless of this setting.
+    ///
+    /// This is [`InvalidUtf8Policy::Error`] by default.
+    #[inline(always)]
+    fn invalid_utf8_policy(&self) -> InvalidUtf8Policy {
+        InvalidUtf8Policy::Error
+    }
+}
+
+/// The JSON representation of `Boolean` fields. See
+/// [`Config::boolean_style`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BooleanStyle {
+    /// Booleans are encoded as `"Y"` and `"N"`, just like in FIX tag-value.
+    YN,
+    /// Booleans are encoded as `"true"` and `"false"`.
+    TrueFalse,
+    /// Booleans are encoded as `"1"` and `"0"`.
+    ZeroOne,
+}
+
+impl BooleanStyle {
+    fn encode(self, value: bool) -> &'static str {
+        match (self, value) {
+            (BooleanStyle::YN, true) => "Y",
+            (BooleanStyle::YN, false) => "N",
+            (BooleanStyle::TrueFalse, true) => "true",
+            (BooleanStyle::TrueFalse, false) => "false",
+            (BooleanStyle::ZeroOne, true) => "1",
+            (BooleanStyle::ZeroOne, false) => "0",
+        }
+    }
+
+    /// Parses a boolean in any style.
+    fn decode(s: &str) -> Option<bool> {
+        match s {
+            "Y" | "true" | "1" => Some(true),
+            "N" | "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// The JSON representation of fields with an empty value. See
+/// [`Config::empty_field_policy`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EmptyFieldPolicy {
+    /// Empty fields are encoded as `""`.
+    EmptyString,
+    /// Empty fields are encoded as `null`.
+    Null,
+    /// Empty fields are omitted altogether.
+    Omit,
+}
+
+/// The JSON representation of redacted fields. See
+/// [`Config::redaction_style`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RedactionStyle {
+    /// Redacted fields are omitted altogether.
+    Omit,
+    /// Redacted fields are encoded as `"***"`.
+    Mask,
+}
+
+/// The JSON representation of string fields with invalid UTF-8. See
+/// [`Config::invalid_utf8_policy`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InvalidUtf8Policy {
+    /// Encoding fails with [`EncoderError::InvalidUtf8`].
+    Error,
+    /// Invalid sequences are replaced with `U+FFFD`.
+    Replace,
+    /// The whole value is encoded as a Base64 string.
+    Base64,
+}
+
+/// The JSON type of a specific field, regardless of its dictionary data type.
+/// See [`Codec::set_field_type_overrides`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JsonFieldType {
+    /// The field is encoded as a JSON number, e.g. `1000`. Values which
+    /// aren't valid JSON numbers are left as strings.
+    Number,
+    /// The field is encoded as a JSON string, e.g. `"1000"`. Multiple values
+    /// are separated by spaces.
+    String,
+    /// The field is encoded as a JSON boolean. Values which aren't valid FIX
+    /// booleans are left as strings.
+    Boolean,
+    /// The field is encoded as a JSON array of strings, split by spaces.
+    Array,
+}
+
+impl JsonFieldType {
+    /// Converts `value` to this JSON type, as far as possible.
+    fn encode(self, value: serde_json::Value) -> serde_json::Value {
+        match (self, value) {
+            (JsonFieldType::Number, serde_json::Value::String(s)) => {
+                match serde_json::from_str::<serde_json::Number>(&s) {
+                    Ok(n) => serde_json::Value::Number(n),
+                    Err(_) => serde_json::Value::String(s),
+                }
+            }
+            (JsonFieldType::Boolean, serde_json::Value::String(s)) => {
+                match BooleanStyle::decode(&s) {
+                    Some(b) => serde_json::Value::Bool(b),
+                    None => serde_json::Value::String(s),
+                }
+            }
+            (JsonFieldType::Array, serde_json::Value::String(s)) if !s.is_empty() => {
+                serde_json::Value::Array(
+                    s.split(' ')
+                        .map(|v| serde_json::Value::String(v.to_string()))
+                        .collect(),
+                )
+            }
+            (JsonFieldType::String, serde_json::Value::Array(values)) => {
+                let values: Vec<&str> = values.iter().filter_map(|v| v.as_str()).collect();
+                serde_json::Value::String(values.join(" "))
+            }
+            (_, value) => value,
+        }
+    }
+
+    /// Converts `value` back to the string-based representation which is
+    /// used when no override is present.
+    fn decode(self, value: &serde_json::Value) -> serde_json::Value {
+        match (self, value) {
+            (_, serde_json::Value::Number(n)) => serde_json::Value::String(n.to_string()),
+            (_, serde_json::Value::Bool(b)) => {
+                serde_json::Value::String(BooleanStyle::YN.encode(*b).to_string())
+            }
+            (JsonFieldType::Array, serde_json::Value::Array(values)) => {
+                let values: Vec<&str> = values.iter().filter_map(|v| v.as_str()).collect();
+                serde_json::Value::String(values.join(" "))
+            }
+            (_, value) => value.clone(),
+        }
+    }
 }
 
 /// A [`Config`](Config) that "pretty-prints", i.e. always returns `true` from
@@ -71,6 +285,13 @@ impl Config for ConfigPrettyPrint {
 #[derive(Debug, Clone)]
 pub struct ConfigSettable {
     pretty_print: bool,
+    empty_field_policy: EmptyFieldPolicy,
+    allow_unknown_fields: bool,
+    strict_extension_fields: bool,
+    boolean_style: BooleanStyle,
+    redaction_style: RedactionStyle,
+    embedded_fixml: bool,
+    invalid_utf8_policy: InvalidUtf8Policy,
 }
 
 impl ConfigSettable {
@@ -84,12 +305,60 @@ impl ConfigSettable {
     pub fn set_pretty_print(&mut self, pretty_print: bool) {
         self.pretty_print = pretty_print;
     }
+
+    /// Sets [`Config::empty_field_policy`](Config::empty_field_policy) to
+    /// `policy`.
+    pub fn set_empty_field_policy(&mut self, policy: EmptyFieldPolicy) {
+        self.empty_field_policy = policy;
+    }
+
+    /// Enables [`Config::allow_unknown_fields`](Config::allow_unknown_fields)
+    /// if and only if `allow_unknown_fields` is true.
+    pub fn set_allow_unknown_fields(&mut self, allow_unknown_fields: bool) {
+        self.allow_unknown_fields = allow_unknown_fields;
+    }
+
+    /// Enables
+    /// [`Config::strict_extension_fields`](Config::strict_extension_fields)
+    /// if and only if `strict_extension_fields` is true.
+    pub fn set_strict_extension_fields(&mut self, strict_extension_fields: bool) {
+        self.strict_extension_fields = strict_extension_fields;
+    }
+
+    /// Sets [`Config::boolean_style`](Config::boolean_style) to `style`.
+    pub fn set_boolean_style(&mut self, style: BooleanStyle) {
+        self.boolean_style = style;
+    }
+
+    /// Sets [`Config::redaction_style`](Config::redaction_style) to `style`.
+    pub fn set_redaction_style(&mut self, style: RedactionStyle) {
+        self.redaction_style = style;
+    }
+
+    /// Enables [`Config::embedded_fixml`](Config::embedded_fixml) if and
+    /// only if `embedded_fixml` is true.
+    pub fn set_embedded_fixml(&mut self, embedded_fixml: bool) {
+        self.embedded_fixml = embedded_fixml;
+    }
+
+    /// Sets [`Config::invalid_utf8_policy`](Config::invalid_utf8_policy) to
+    /// `policy`.
+    pub fn set_invalid_utf8_policy(&mut self, policy: InvalidUtf8Policy) {
+        self.invalid_utf8_policy = policy;
+    }
 }
 
 impl Default for ConfigSettable {
     fn default() -> Self {
         Self {
             pretty_print: false,
+            empty_field_policy: EmptyFieldPolicy::EmptyString,
+            allow_unknown_fields: false,
+            strict_extension_fields: false,
+            boolean_style: BooleanStyle::YN,
+            redaction_style: RedactionStyle::Mask,
+            embedded_fixml: false,
+            invalid_utf8_policy: InvalidUtf8Policy::Error,
         }
     }
 }
@@ -98,12 +367,48 @@ impl Config for ConfigSettable {
     fn pretty_print(&self) -> bool {
         self.pretty_print
     }
+
+    fn empty_field_policy(&self) -> EmptyFieldPolicy {
+        self.empty_field_policy
+    }
+
+    fn allow_unknown_fields(&self) -> bool {
+        self.allow_unknown_fields
+    }
+
+    fn strict_extension_fields(&self) -> bool {
+        self.strict_extension_fields
+    }
+
+    fn boolean_style(&self) -> BooleanStyle {
+        self.boolean_style
+    }
+
+    fn redaction_style(&self) -> RedactionStyle {
+        self.redaction_style
+    }
+
+    fn embedded_fixml(&self) -> bool {
+        self.embedded_fixml
+    }
+
+    fn invalid_utf8_policy(&self) -> InvalidUtf8Policy {
+        self.invalid_utf8_policy
+    }
 }
 
 /// A codec device for the JSON data format.
+///
+/// The fields of `Header`, `Body` and `Trailer` are always encoded in
+/// alphabetical order of their names, as JSON objects are unordered. Unlike
+/// the tag-value encoder (see
+/// [`tagvalue::Codec::set_field_order`](crate::codec::tagvalue::Codec::set_field_order)),
+/// there's no way to choose a custom field order.
 #[derive(Debug, Clone)]
 pub struct Codec<T, Z> {
-    dictionaries: HashMap<String, Dictionary>,
+    dictionaries: HashMap<Version, Dictionary>,
+    field_type_overrides: HashMap<u32, JsonFieldType>,
+    redacted_tags: HashSet<u32>,
     message: T,
     config: Z,
 }
@@ -113,39 +418,135 @@ where
     T: TsrMessageRef,
     Z: Config,
 {
+    /// Creates a new JSON codec for messages of `dict`. FIX 5.0+ messages are
+    /// framed by the FIXT.1.1 session layer, so a FIXT.1.1 dictionary is also
+    /// loaded in that case. Dictionaries are looked up by
+    /// [`Dictionary::fix_version`], so `dict` must have a known FIX version.
     pub fn new(dict: Dictionary, config: Z) -> Self {
         let mut dictionaries = HashMap::new();
-        dictionaries.insert(dict.get_version().to_string(), dict);
+        if let Some(version) = dict.fix_version() {
+            if appl_ver_id_by_version(version).is_some() {
+                dictionaries.insert(Version::Fixt11, Dictionary::from_version(Version::Fixt11));
+            }
+            dictionaries.insert(version, dict);
+        }
         Self {
             dictionaries,
+            field_type_overrides: HashMap::new(),
+            redacted_tags: HashSet::new(),
             message: T::default(),
             config,
         }
     }
 
+    /// Overrides the JSON type of specific fields by tag, regardless of the
+    /// dictionary. Useful for venues that expect e.g. `OrderQty <38>` as a
+    /// JSON number. Overrides apply to both encoding and decoding and
+    /// replace any previous overrides.
+    ///
+    /// Numbers are parsed as IEEE 754 doubles, so decimal values may lose
+    /// trailing zeros or precision.
+    pub fn set_field_type_overrides(&mut self, overrides: HashMap<u32, JsonFieldType>) {
+        self.field_type_overrides = overrides;
+    }
+
+    /// Redacts the fields with the given tags (e.g. `Account <1>`,
+    /// `Password <554>`) from encoded messages, including those inside
+    /// repeating groups, according to [`Config::redaction_style`]. Replaces
+    /// any previously redacted tags. Decoding is not affected.
+    pub fn set_redacted_tags(&mut self, tags: HashSet<u32>) {
+        self.redacted_tags = tags;
+    }
+
+    /// Returns the application-level [`Dictionary`] for messages with
+    /// `BeginString <8>` equal to `begin_string`. FIXT.1.1 messages carry
+    /// their application version in `ApplVerID <1128>` instead.
+    fn dictionary_by_version(
+        &self,
+        begin_string: &str,
+        appl_ver_id: Option<&str>,
+    ) -> Option<&Dictionary> {
+        let version = match (begin_string, appl_ver_id.and_then(version_by_appl_ver_id)) {
+            (FIXT11, Some(version)) => version,
+            (begin_string, _) => version_by_begin_string(begin_string)?,
+        };
+        self.dictionaries.get(&version)
+    }
+
     fn decode_field(
         &self,
         dictionary: &Dictionary,
         key: &str,
         value: &serde_json::Value,
     ) -> Result<(u32, slr::FixFieldValue), DecodeError> {
-        if let Some(field) = dictionary.field_by_name(key) {
-            match value {
-                serde_json::Value::String(s) => Ok((
-                    field.tag() as u32,
-                    slr::FixFieldValue::String(s.to_string()),
-                )),
-                serde_json::Value::Array(values) => {
-                    let mut group = Vec::new();
-                    for item in values {
-                        group.push(self.decode_component_block(dictionary, item)?);
-                    }
-                    Ok((field.tag() as u32, slr::FixFieldValue::Group(group)))
+        let extension_field = EXTENSION_FIELDS.iter().find(|(_, name)| *name == key);
+        let (tag, basetype) = match (dictionary.field_by_name(key), extension_field) {
+            (Some(field), _) => (field.tag() as u32, field.basetype()),
+            (None, Some((tag, _))) if !self.config.strict_extension_fields() => {
+                (*tag, DataType::String)
+            }
+            // Unknown fields are keyed by their tag number.
+            (None, _) if self.config.allow_unknown_fields() => (
+                key.parse().map_err(|_| DecodeError::InvalidData)?,
+                DataType::String,
+            ),
+            (None, _) => return Err(DecodeError::InvalidData),
+        };
+        let overridden_value;
+        let value = match self.field_type_overrides.get(&tag) {
+            Some(field_type) => {
+                overridden_value = field_type.decode(value);
+                &overridden_value
+            }
+            None => value,
+        };
+        let is_multi_value = matches!(
+            basetype,
+            DataType::MultipleStringValue | DataType::MultipleCharValue
+        );
+        match value {
+            serde_json::Value::Null => Ok((tag, slr::FixFieldValue::String(String::new()))),
+            serde_json::Value::String(s) if s.is_empty() => {
+                Ok((tag, slr::FixFieldValue::String(String::new())))
+            }
+            // Embedded FIXML documents. See `Config::embedded_fixml`.
+            serde_json::Value::Object(_) if basetype == DataType::Data => {
+                let fields = self.decode_component_block(dictionary, value)?;
+                let fixml = fields_to_fixml(&fields, dictionary)?;
+                Ok((tag, slr::FixFieldValue::Data(fixml.into_bytes())))
+            }
+            // Binary data is Base64-encoded.
+            serde_json::Value::String(s) if basetype == DataType::Data => Ok((
+                tag,
+                slr::FixFieldValue::Data(base64::decode(s).map_err(|_| DecodeError::InvalidData)?),
+            )),
+            // Booleans are stored as "Y" and "N", whatever their style.
+            serde_json::Value::String(s) if basetype == DataType::Boolean => {
+                let value = BooleanStyle::decode(s).ok_or(DecodeError::InvalidData)?;
+                let value = BooleanStyle::YN.encode(value);
+                Ok((tag, slr::FixFieldValue::String(value.to_string())))
+            }
+            serde_json::Value::String(s) if is_multi_value => Ok((
+                tag,
+                slr::FixFieldValue::MultiValue(s.split(' ').map(|v| v.to_string()).collect()),
+            )),
+            serde_json::Value::String(s) => Ok((tag, slr::FixFieldValue::String(s.to_string()))),
+            serde_json::Value::Array(values) if is_multi_value => {
+                let mut multi_value = Vec::new();
+                for item in values {
+                    let s = item.as_str().ok_or(DecodeError::InvalidData)?;
+                    multi_value.push(s.to_string());
                 }
-                _ => Err(DecodeError::InvalidData),
+                Ok((tag, slr::FixFieldValue::MultiValue(multi_value)))
             }
-        } else {
-            Err(DecodeError::InvalidData)
+            serde_json::Value::Array(values) => {
+                let mut group = Vec::new();
+                for item in values {
+                    group.push(self.decode_component_block(dictionary, item)?);
+                }
+                Ok((tag, slr::FixFieldValue::Group(group)))
+            }
+            _ => Err(DecodeError::InvalidData),
         }
     }
 
@@ -162,31 +563,223 @@ where
         Ok(group)
     }
 
-    fn translate(&self, dict: &Dictionary, field: &slr::FixFieldValue) -> serde_json::Value {
-        match field {
+    /// Returns the JSON representation of `field`, or `None` if it must be
+    /// omitted.
+    fn translate(
+        &self,
+        dict: &Dictionary,
+        field: &slr::FixFieldValue,
+    ) -> Option<serde_json::Value> {
+        Some(match field {
+            slr::FixFieldValue::String(c) if c.is_empty() => {
+                match self.config.empty_field_policy() {
+                    EmptyFieldPolicy::EmptyString => serde_json::Value::String(String::new()),
+                    EmptyFieldPolicy::Null => serde_json::Value::Null,
+                    EmptyFieldPolicy::Omit => return None,
+                }
+            }
             slr::FixFieldValue::String(c) => serde_json::Value::String(c.to_string()),
+            slr::FixFieldValue::Value(v) => serde_json::Value::String(v.to_string()),
+            slr::FixFieldValue::Data(raw_data) => {
+                serde_json::Value::String(base64::encode(raw_data))
+            }
+            slr::FixFieldValue::MultiValue(values) => serde_json::Value::Array(
+                values
+                    .iter()
+                    .map(|v| serde_json::Value::String(v.to_string()))
+                    .collect(),
+            ),
             slr::FixFieldValue::Group(array) => {
                 let mut values = Vec::new();
                 for group in array {
                     let mut map = serde_json::Map::new();
                     for item in group {
-                        let field = dict
-                            .field_by_tag(*item.0 as u32)
-                            .ok_or(DecodeError::InvalidData)
-                            .unwrap();
-                        let field_name = field.name().to_string();
-                        let field_value = self.translate(dict, item.1);
-                        map.insert(field_name, field_value);
+                        // Unknown fields are rejected upfront unless allowed,
+                        // see `unknown_group_field`.
+                        let field_name = match dict.field_by_tag(*item.0 as u32) {
+                            Some(field) => field.name().to_string(),
+                            None => item.0.to_string(),
+                        };
+                        let field = dict.field_by_tag(*item.0 as u32);
+                        if let Some(field_value) =
+                            self.translate_field(dict, *item.0 as u32, field.as_ref(), item.1)
+                        {
+                            map.insert(field_name, field_value);
+                        }
                     }
                     values.push(serde_json::Value::Object(map));
                 }
                 serde_json::Value::Array(values)
             }
-            _ => panic!(),
+        })
+    }
+
+    /// Just like [`Codec::translate`], but `Boolean` fields are encoded
+    /// according to [`Config::boolean_style`], field type overrides are
+    /// applied and redacted fields are omitted or masked.
+    fn translate_field(
+        &self,
+        dict: &Dictionary,
+        tag: u32,
+        field: Option<&Field>,
+        value: &slr::FixFieldValue,
+    ) -> Option<serde_json::Value> {
+        if self.redacted_tags.contains(&tag) {
+            return match self.config.redaction_style() {
+                RedactionStyle::Omit => None,
+                RedactionStyle::Mask => Some(serde_json::Value::String("***".to_string())),
+            };
+        }
+        let value = self.translate_field_default(dict, field, value)?;
+        Some(match self.field_type_overrides.get(&tag) {
+            Some(field_type) => field_type.encode(value),
+            None => value,
+        })
+    }
+
+    fn translate_field_default(
+        &self,
+        dict: &Dictionary,
+        field: Option<&Field>,
+        value: &slr::FixFieldValue,
+    ) -> Option<serde_json::Value> {
+        let is_data = field.is_some_and(|field| field.basetype() == DataType::Data);
+        if let (false, slr::FixFieldValue::Data(data)) = (is_data, value) {
+            // Invalid UTF-8 in string fields is rejected upfront, see
+            // `invalid_utf8_field`.
+            return Some(serde_json::Value::String(match std::str::from_utf8(data) {
+                Ok(s) => s.to_string(),
+                Err(_) if self.config.invalid_utf8_policy() == InvalidUtf8Policy::Replace => {
+                    String::from_utf8_lossy(data).into_owned()
+                }
+                Err(_) => base64::encode(data),
+            }));
+        }
+        if let (true, slr::FixFieldValue::Data(data)) = (self.config.embedded_fixml(), value) {
+            if let Some(message) = fixml_to_message(data, dict) {
+                return Some(self.translate_embedded_message(dict, &message));
+            }
+        }
+        let is_boolean = field.is_some_and(|field| field.basetype() == DataType::Boolean);
+        let boolean = match value {
+            slr::FixFieldValue::String(s) if is_boolean => BooleanStyle::decode(s),
+            slr::FixFieldValue::Value(v @ dt::DataTypeValue::Char(_)) if is_boolean => {
+                BooleanStyle::decode(&v.to_string())
+            }
+            _ => None,
+        };
+        match boolean {
+            Some(boolean) => Some(serde_json::Value::String(
+                self.config.boolean_style().encode(boolean).to_string(),
+            )),
+            None => self.translate(dict, value),
         }
     }
 }
 
+impl<T, Z> Codec<T, Z>
+where
+    T: TsrMessageRef,
+    Z: Config,
+{
+    /// Returns the JSON object of an embedded FIXML `message`, with its fields
+    /// by name. See [`Config::embedded_fixml`].
+    fn translate_embedded_message(
+        &self,
+        dict: &Dictionary,
+        message: &slr::Message,
+    ) -> serde_json::Value {
+        let mut map = serde_json::Map::new();
+        for (tag, value) in message.fields.iter() {
+            // Fields of embedded messages always come from `dict`.
+            let field = dict.field_by_tag(*tag as u32).unwrap();
+            if let Some(value) = self.translate_field(dict, *tag as u32, Some(&field), value) {
+                map.insert(field.name().to_string(), value);
+            }
+        }
+        serde_json::Value::Object(map)
+    }
+}
+
+/// Decodes the FIXML document in `data`, if any, into a message with its
+/// `MsgType <35>`. The fields of components are flattened into the message.
+/// See [`Config::embedded_fixml`].
+fn fixml_to_message(data: &[u8], dict: &Dictionary) -> Option<slr::Message> {
+    let xml = std::str::from_utf8(data).ok()?;
+    let document = roxmltree::Document::parse(xml).ok()?;
+    let root = document.root_element();
+    if !root.has_tag_name("FIXML") {
+        return None;
+    }
+    let node = root.first_element_child()?;
+    let msg = dict.message_by_name(node.tag_name().name())?;
+    let mut message = slr::Message::new();
+    message.add_str(35, msg.msg_type());
+    add_fixml_fields(node, dict, &mut message)?;
+    Some(message)
+}
+
+fn add_fixml_fields(
+    node: roxmltree::Node,
+    dict: &Dictionary,
+    message: &mut slr::Message,
+) -> Option<()> {
+    for attribute in node.attributes() {
+        let field = dict.field_by_name(attribute.name())?;
+        // The same field twice means a repeating group.
+        if message.get_field(field.tag()).is_some() {
+            return None;
+        }
+        let value = match field.basetype() {
+            DataType::Data => slr::FixFieldValue::Data(attribute.value().as_bytes().to_vec()),
+            _ => slr::FixFieldValue::String(attribute.value().to_string()),
+        };
+        message.add_field(field.tag(), value);
+    }
+    for child in node.children().filter(|child| child.is_element()) {
+        add_fixml_fields(child, dict, message)?;
+    }
+    Some(())
+}
+
+/// Encodes `fields` as a FIXML document, with all fields as attributes of the
+/// message element. See [`Config::embedded_fixml`].
+fn fields_to_fixml(
+    fields: &BTreeMap<i64, slr::FixFieldValue>,
+    dict: &Dictionary,
+) -> Result<String, DecodeError> {
+    let msg_type = match fields.get(&35) {
+        Some(slr::FixFieldValue::String(msg_type)) => msg_type,
+        _ => return Err(DecodeError::Schema),
+    };
+    let msg = dict
+        .message_by_msgtype(msg_type)
+        .ok_or(DecodeError::InvalidMsgType)?;
+    let mut xml = format!("<FIXML><{}", msg.name());
+    for (tag, value) in fields.iter().filter(|(tag, _)| **tag != 35) {
+        let field = dict
+            .field_by_tag(*tag as u32)
+            .ok_or(DecodeError::InvalidData)?;
+        let value = match value {
+            slr::FixFieldValue::String(s) => s.clone(),
+            slr::FixFieldValue::Data(data) => {
+                String::from_utf8(data.clone()).map_err(|_| DecodeError::InvalidData)?
+            }
+            slr::FixFieldValue::Value(v) => v.to_string(),
+            slr::FixFieldValue::MultiValue(values) => values.join(" "),
+            slr::FixFieldValue::Group(_) => return Err(DecodeError::InvalidData),
+        };
+        let value = value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;");
+        xml.push_str(&format!(" {}=\"{}\"", field.name(), value));
+    }
+    xml.push_str("/></FIXML>");
+    Ok(xml)
+}
+
 impl<Z, T> Decoder<T> for Codec<T, Z>
 where
     T: TsrMessageRef,
@@ -217,9 +810,9 @@ where
             .get("BeginString")
             .and_then(|v| v.as_str())
             .ok_or(Self::Error::Schema)?;
+        let field_appl_ver_id = header.get("ApplVerID").and_then(|v| v.as_str());
         let dictionary = self
-            .dictionaries
-            .get(field_begin_string)
+            .dictionary_by_version(field_begin_string, field_appl_ver_id)
             .ok_or(Self::Error::InvalidMsgType)?;
         let mut message = T::default();
         for item in header.iter().chain(body).chain(trailer) {
@@ -243,34 +836,163 @@ where
         buffer: impl Buffer,
         message: &slr::Message,
     ) -> Result<usize, Self::Error> {
-        let dictionary =
+        let mut writer = BufferWriter::new(buffer);
+        self.encode_to_writer(&mut writer, message)?;
+        Ok(writer.as_slice().len())
+    }
+}
+
+impl<Z, T> Codec<T, Z>
+where
+    Z: Config,
+    T: TsrMessageRef,
+{
+    /// Encodes `message` straight into `writer`, e.g. a file or a socket. The
+    /// output is the same as that of [`Encoder::encode`].
+    pub fn encode_to_writer<W: io::Write>(
+        &self,
+        writer: W,
+        message: &slr::Message,
+    ) -> Result<(), EncoderError> {
+        let value = self.to_json_value(message)?;
+        if self.config.pretty_print() {
+            serde_json::to_writer_pretty(writer, &value).map_err(|_| EncoderError::Io)
+        } else {
+            serde_json::to_writer(writer, &value).map_err(|_| EncoderError::Io)
+        }
+    }
+
+    fn to_json_value(&self, message: &slr::Message) -> Result<serde_json::Value, EncoderError> {
+        let begin_string =
             if let Some(slr::FixFieldValue::String(fix_version)) = message.fields.get(&8) {
-                self.dictionaries
-                    .get(fix_version.as_str())
-                    .ok_or(Self::Error::Dictionary)?
+                fix_version.as_str()
             } else {
-                return Err(Self::Error::Dictionary);
+                return Err(EncoderError::Dictionary);
             };
-        let component_std_header = dictionary
+        let appl_ver_id = match message.fields.get(&1128) {
+            Some(slr::FixFieldValue::String(s)) => Some(s.as_str()),
+            _ => None,
+        };
+        let dictionary = self
+            .dictionary_by_version(begin_string, appl_ver_id)
+            .ok_or(EncoderError::Dictionary)?;
+        // FIX 5.0+ messages must be framed by the FIXT.1.1 session layer, which
+        // also defines `StandardHeader` and `StandardTrailer`.
+        let default_appl_ver_id =
+            version_by_begin_string(begin_string).and_then(appl_ver_id_by_version);
+        let is_fixt = begin_string == FIXT11 || default_appl_ver_id.is_some();
+        let transport_dictionary = if is_fixt {
+            self.dictionaries
+                .get(&Version::Fixt11)
+                .ok_or(EncoderError::Dictionary)?
+        } else {
+            dictionary
+        };
+        // Header and trailer layouts differ across FIX versions (e.g. FIX 4.0
+        // lists `MsgSeqNum` after `SecureData`), so they're always taken from
+        // the dictionary of the transport version rather than hardcoded.
+        let component_std_header = transport_dictionary
             .component_by_name("StandardHeader")
             .expect("The `StandardHeader` component is mandatory.");
-        let component_std_traler = dictionary
+        let component_std_traler = transport_dictionary
             .component_by_name("StandardTrailer")
             .expect("The `StandardTrailer` component is mandatory.");
         let msg_type = if let Some(slr::FixFieldValue::String(s)) = message.get_field(35) {
             s
         } else {
-            return Err(Self::Error::Dictionary);
+            return Err(EncoderError::Dictionary);
         };
+        if self.config.invalid_utf8_policy() == InvalidUtf8Policy::Error {
+            let dicts = [dictionary, transport_dictionary];
+            if let Some(tag) = invalid_utf8_field(&dicts, &message.fields) {
+                return Err(EncoderError::InvalidUtf8(tag));
+            }
+        }
+        if !self.config.allow_unknown_fields() {
+            let groups = message.fields.values().filter_map(|value| match value {
+                slr::FixFieldValue::Group(entries) => Some(entries),
+                _ => None,
+            });
+            for entries in groups {
+                if entries
+                    .iter()
+                    .any(|entry| unknown_group_field(dictionary, entry).is_some())
+                {
+                    return Err(EncoderError::Dictionary);
+                }
+            }
+        }
         let mut map_body = json!({});
         let mut map_trailer = json!({});
         let mut map_header = json!({ "MsgType": msg_type });
-        for (field_tag, field_value) in message.fields.iter() {
-            let field = dictionary
+        if is_fixt {
+            let header = map_header.as_object_mut().unwrap();
+            header.insert("BeginString".to_string(), json!(FIXT11));
+            if let (None, Some(id)) = (appl_ver_id, default_appl_ver_id) {
+                header.insert("ApplVerID".to_string(), json!(id));
+            }
+        }
+        // Length fields always hold the actual length of their data field,
+        // even if missing or stale in `message`.
+        let data_lengths: BTreeMap<i64, slr::FixFieldValue> = data_lengths(dictionary, message)
+            .values()
+            .map(|(length_tag, len)| (i64::from(*length_tag), slr::FixFieldValue::from(*len)))
+            .collect();
+        let missing_data_lengths = data_lengths
+            .iter()
+            .filter(|(tag, _)| !message.fields.contains_key(tag));
+        for (field_tag, field_value) in message.fields.iter().chain(missing_data_lengths) {
+            let field_value = data_lengths.get(field_tag).unwrap_or(field_value);
+            if is_fixt && *field_tag == 8 {
+                continue;
+            }
+            let field = match dictionary
                 .field_by_tag(*field_tag as u32)
-                .ok_or(Self::Error::Dictionary)?;
+                .or_else(|| transport_dictionary.field_by_tag(*field_tag as u32))
+            {
+                Some(field) => field,
+                None if !self.config.strict_extension_fields()
+                    && is_extension_field(*field_tag as u32) =>
+                {
+                    let (_, name) = EXTENSION_FIELDS
+                        .iter()
+                        .find(|(tag, _)| *tag == *field_tag as u32)
+                        .unwrap();
+                    if let Some(field_value) =
+                        self.translate_field(dictionary, *field_tag as u32, None, field_value)
+                    {
+                        map_header
+                            .as_object_mut()
+                            .unwrap()
+                            .insert(name.to_string(), field_value);
+                    }
+                    continue;
+                }
+                // Unknown fields can't be placed in the header or trailer, so
+                // they always go in the body.
+                None if self.config.allow_unknown_fields() => {
+                    if let Some(field_value) =
+                        self.translate_field(dictionary, *field_tag as u32, None, field_value)
+                    {
+                        map_body
+                            .as_object_mut()
+                            .unwrap()
+                            .insert(field_tag.to_string(), field_value);
+                    }
+                    continue;
+                }
+                None => return Err(EncoderError::Dictionary),
+            };
             let field_name = field.name().to_string();
-            let field_value = self.translate(dictionary, field_value);
+            let field_value = match self.translate_field(
+                dictionary,
+                *field_tag as u32,
+                Some(&field),
+                field_value,
+            ) {
+                Some(value) => value,
+                None => continue,
+            };
             if component_std_header.contains_field(&field) {
                 map_header
                     .as_object_mut()
@@ -288,18 +1010,93 @@ where
                     .insert(field_name, field_value);
             }
         }
-        let value = json!({
+        Ok(json!({
             "Header": map_header,
             "Body": map_body,
             "Trailer": map_trailer,
-        });
-        let mut writer = BufferWriter::new(buffer);
-        if self.config.pretty_print() {
-            serde_json::to_writer_pretty(&mut writer, &value).unwrap();
-        } else {
-            serde_json::to_writer(&mut writer, &value).unwrap();
+        }))
+    }
+}
+
+/// Returns the tag of the first string field in `fields` (or in any of its
+/// repeating groups) with invalid UTF-8. Fields are looked up in `dicts`, in
+/// order; unknown fields are considered string fields.
+fn invalid_utf8_field(dicts: &[&Dictionary], fields: &slr::GroupEntry) -> Option<u32> {
+    fields.iter().find_map(|(tag, value)| match value {
+        slr::FixFieldValue::Data(data) if std::str::from_utf8(data).is_err() => {
+            let is_data = dicts
+                .iter()
+                .find_map(|dict| dict.field_by_tag(*tag as u32))
+                .is_some_and(|field| field.basetype() == DataType::Data);
+            if is_data {
+                None
+            } else {
+                Some(*tag as u32)
+            }
         }
-        Ok(writer.as_slice().len())
+        slr::FixFieldValue::Group(entries) => entries
+            .iter()
+            .find_map(|entry| invalid_utf8_field(dicts, entry)),
+        _ => None,
+    })
+}
+
+/// Returns the tag of the first field in the repeating group `entry` (or in
+/// any of its nested groups) that is unknown to `dict`.
+fn unknown_group_field(dict: &Dictionary, entry: &slr::GroupEntry) -> Option<u32> {
+    entry.iter().find_map(|(tag, value)| {
+        if dict.field_by_tag(*tag as u32).is_none() {
+            return Some(*tag as u32);
+        }
+        match value {
+            slr::FixFieldValue::Group(entries) => entries
+                .iter()
+                .find_map(|entry| unknown_group_field(dict, entry)),
+            _ => None,
+        }
+    })
+}
+
+/// `BeginString <8>` of all FIX 5.0+ messages.
+const FIXT11: &str = "FIXT.1.1";
+
+/// Returns the `ApplVerID <1128>` of the FIX 5.0+ application version
+/// `version`, if any.
+fn appl_ver_id_by_version(version: Version) -> Option<&'static str> {
+    match version {
+        Version::Fix50 => Some("7"),
+        Version::Fix50SP1 => Some("8"),
+        Version::Fix50SP2 => Some("9"),
+        _ => None,
+    }
+}
+
+/// The inverse of [`appl_ver_id_by_version`].
+fn version_by_appl_ver_id(appl_ver_id: &str) -> Option<Version> {
+    match appl_ver_id {
+        "7" => Some(Version::Fix50),
+        "8" => Some(Version::Fix50SP1),
+        "9" => Some(Version::Fix50SP2),
+        _ => None,
+    }
+}
+
+/// Returns the [`Version`] of messages with `BeginString <8>` equal to
+/// `begin_string`. Since FIX 5.0+ messages are framed by FIXT.1.1, their
+/// application version can also be given in place of `BeginString <8>`,
+/// e.g. `FIX.5.0SP2`.
+fn version_by_begin_string(begin_string: &str) -> Option<Version> {
+    match begin_string {
+        "FIX.4.0" => Some(Version::Fix40),
+        "FIX.4.1" => Some(Version::Fix41),
+        "FIX.4.2" => Some(Version::Fix42),
+        "FIX.4.3" => Some(Version::Fix43),
+        "FIX.4.4" => Some(Version::Fix44),
+        "FIX.5.0" => Some(Version::Fix50),
+        "FIX.5.0SP1" => Some(Version::Fix50SP1),
+        "FIX.5.0SP2" => Some(Version::Fix50SP2),
+        FIXT11 => Some(Version::Fixt11),
+        _ => None,
     }
 }
 
@@ -308,6 +1105,11 @@ where
 #[derive(Copy, Clone, Debug)]
 pub enum EncoderError {
     Dictionary,
+    /// Writing to the output failed.
+    Io,
+    /// The string field with this tag holds invalid UTF-8. See
+    /// [`Config::invalid_utf8_policy`].
+    InvalidUtf8(u32),
 }
 
 /// The error type that can be returned if some error is detected when decoding
@@ -333,6 +1135,7 @@ impl fmt::Display for DecodeError {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::codec::fast::Decimal;
     use serde_json::*;
 
     const MESSAGE_SIMPLE: &str = r#"
@@ -375,6 +1178,42 @@ mod test {
 }
     "#;
 
+    const MESSAGE_WITH_EXEC_INST: &str = r#"
+{
+    "Header": {
+        "BeginString": "FIX.4.4",
+        "MsgType": "D",
+        "MsgSeqNum": "4567",
+        "SenderCompID": "SENDER",
+        "TargetCompID": "TARGET",
+        "SendingTime": "20160802-21:14:38.717"
+    },
+    "Body": {
+        "ClOrdID": "ORD1",
+        "ExecInst": ["1", "6"]
+    },
+    "Trailer": {
+    }
+}
+    "#;
+
+    const MESSAGE_WITH_UNKNOWN_FIELD: &str = r#"
+{
+    "Header": {
+        "BeginString": "FIX.4.4",
+        "MsgType": "D",
+        "SenderCompID": "SENDER",
+        "TargetCompID": "TARGET"
+    },
+    "Body": {
+        "ClOrdID": "ORD1",
+        "9999": "foo"
+    },
+    "Trailer": {
+    }
+}
+    "#;
+
     fn dict_fix44() -> Dictionary {
         Dictionary::from_version(crate::app::Version::Fix44)
     }
@@ -395,6 +1234,21 @@ mod test {
         assert_eq!(json_value_before, json_value_after);
     }
 
+    #[test]
+    fn groups_of_market_data_message() {
+        let mut decoder = encoder_fix44();
+        let message = Decoder::decode(&mut decoder, &mut MESSAGE_SIMPLE.as_bytes()).unwrap();
+        let groups: Vec<(u32, &[slr::GroupEntry])> = message.groups().collect();
+        assert_eq!(groups.len(), 1);
+        let (tag, entries) = groups[0];
+        assert_eq!(tag, 268);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[1].get(&270),
+            Some(&slr::FixFieldValue::String("1.75".to_string()))
+        );
+    }
+
     #[test]
     fn message_without_header() {
         let mut encoder = encoder_fix44();
@@ -414,4 +1268,514 @@ mod test {
             _ => panic!(),
         };
     }
+
+    #[test]
+    fn multi_value_field_round_trip() {
+        let mut decoder = encoder_fix44();
+        let mut encoder = encoder_fix44();
+        let json_value_before: Value = from_str(MESSAGE_WITH_EXEC_INST).unwrap();
+        let message =
+            Decoder::decode(&mut decoder, &mut MESSAGE_WITH_EXEC_INST.as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(18),
+            Some(&slr::FixFieldValue::MultiValue(vec![
+                "1".to_string(),
+                "6".to_string()
+            ]))
+        );
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut encoder, &mut buffer, message).unwrap();
+        let json_value_after: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value_before, json_value_after);
+    }
+
+    #[test]
+    fn fix50_message_has_fixt_framing() {
+        let mut encoder = Codec::<slr::Message, _>::new(
+            Dictionary::from_version(crate::app::Version::Fix50),
+            ConfigPrettyPrint,
+        );
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.5.0");
+        message.add_str(35, "D");
+        message.add_str(49, "SENDER");
+        message.add_str(56, "TARGET");
+        message.add_str(11, "ORD1");
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut encoder, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Header"]["BeginString"], "FIXT.1.1");
+        assert_eq!(json_value["Header"]["ApplVerID"], "7");
+        assert_eq!(json_value["Header"]["SenderCompID"], "SENDER");
+        assert_eq!(json_value["Body"]["ClOrdID"], "ORD1");
+        let mut decoder = encoder;
+        let decoded = Decoder::decode(&mut decoder, &buffer[..]).unwrap();
+        assert_eq!(
+            decoded.get_field(11),
+            Some(&slr::FixFieldValue::String("ORD1".to_string()))
+        );
+    }
+
+    #[test]
+    fn fix50sp2_message_has_its_own_appl_ver_id() {
+        let mut codec = Codec::<slr::Message, _>::new(
+            Dictionary::from_version(crate::app::Version::Fix50SP2),
+            ConfigPrettyPrint,
+        );
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.5.0SP2");
+        message.add_str(35, "D");
+        message.add_str(11, "ORD1");
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Header"]["BeginString"], "FIXT.1.1");
+        assert_eq!(json_value["Header"]["ApplVerID"], "9");
+        let decoded = Decoder::decode(&mut codec, &buffer[..]).unwrap();
+        assert_eq!(
+            decoded.get_field(11),
+            Some(&slr::FixFieldValue::String("ORD1".to_string()))
+        );
+        // FIX 5.0 messages can't be decoded with FIX 5.0 SP2 definitions.
+        let fix50 = String::from_utf8(buffer)
+            .unwrap()
+            .replace(r#""9""#, r#""7""#);
+        assert!(matches!(
+            Decoder::decode(&mut codec, fix50.as_bytes()),
+            Err(DecodeError::InvalidMsgType)
+        ));
+    }
+
+    #[test]
+    fn fix50_nested_components_round_trip() {
+        let dict = Dictionary::from_version(crate::app::Version::Fix50);
+        let mut encoder = Codec::<slr::Message, _>::new(dict, ConfigPrettyPrint);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.5.0");
+        message.add_str(35, "D");
+        message.add_str(49, "SENDER");
+        message.add_str(56, "TARGET");
+        message.add_str(11, "ORD1");
+        // `Instrument` nests the `SecAltIDGrp` component.
+        message.add_str(55, "EUR/USD");
+        let mut alt_id = BTreeMap::new();
+        alt_id.insert(455, slr::FixFieldValue::String("EURUSD=X".to_string()));
+        alt_id.insert(456, slr::FixFieldValue::String("8".to_string()));
+        message.add_field(454, slr::FixFieldValue::Group(vec![alt_id]));
+        // `Parties` nests the `PtysSubGrp` component.
+        let mut sub_party = BTreeMap::new();
+        sub_party.insert(523, slr::FixFieldValue::String("DESK1".to_string()));
+        sub_party.insert(803, slr::FixFieldValue::String("4".to_string()));
+        let mut party = BTreeMap::new();
+        party.insert(448, slr::FixFieldValue::String("BROKER".to_string()));
+        party.insert(452, slr::FixFieldValue::String("1".to_string()));
+        party.insert(802, slr::FixFieldValue::Group(vec![sub_party]));
+        message.add_field(453, slr::FixFieldValue::Group(vec![party]));
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut encoder, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Body"]["Symbol"], "EUR/USD");
+        assert_eq!(
+            json_value["Body"]["NoPartyIDs"][0]["NoPartySubIDs"][0]["PartySubID"],
+            "DESK1"
+        );
+        let mut decoder = encoder;
+        let decoded = Decoder::decode(&mut decoder, &buffer[..]).unwrap();
+        for tag in &[11, 55, 454, 453] {
+            assert_eq!(decoded.get_field(*tag), message.get_field(*tag));
+        }
+    }
+
+    #[test]
+    fn unknown_field_in_group_is_an_error() {
+        let encoder = encoder_fix44();
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        let mut party = BTreeMap::new();
+        party.insert(448, slr::FixFieldValue::String("BROKER".to_string()));
+        party.insert(9999, slr::FixFieldValue::String("foo".to_string()));
+        message.add_field(453, slr::FixFieldValue::Group(vec![party]));
+        let mut buffer = Vec::<u8>::new();
+        assert!(matches!(
+            encoder.encode_to_writer(&mut buffer, &message),
+            Err(EncoderError::Dictionary)
+        ));
+    }
+
+    #[test]
+    fn stale_data_length_is_corrected() {
+        let encoder = encoder_fix44();
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "A");
+        message.add_int(95, 10);
+        message.add_field(96, slr::FixFieldValue::Data(b"abc".to_vec()));
+        let mut buffer = Vec::<u8>::new();
+        encoder.encode_to_writer(&mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Body"]["RawDataLength"], "3");
+        message.fields.remove(&95);
+        let mut buffer = Vec::<u8>::new();
+        encoder.encode_to_writer(&mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Body"]["RawDataLength"], "3");
+    }
+
+    #[test]
+    fn fix40_header_fields_are_encoded_in_header() {
+        let mut encoder = Codec::<slr::Message, _>::new(
+            Dictionary::from_version(crate::app::Version::Fix40),
+            ConfigPrettyPrint,
+        );
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.0");
+        message.add_str(35, "D");
+        message.add_int(34, 1);
+        message.add_str(49, "SENDER");
+        message.add_str(56, "TARGET");
+        message.add_str(11, "ORD1");
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut encoder, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Header"]["BeginString"], "FIX.4.0");
+        assert_eq!(json_value["Header"]["MsgSeqNum"], "1");
+        assert_eq!(json_value["Header"]["SenderCompID"], "SENDER");
+        assert_eq!(json_value["Header"]["TargetCompID"], "TARGET");
+        assert!(json_value["Body"].get("MsgSeqNum").is_none());
+        assert_eq!(json_value["Body"]["ClOrdID"], "ORD1");
+    }
+
+    fn empty_field_round_trip(policy: EmptyFieldPolicy, expected: Option<Value>) {
+        let mut config = ConfigSettable::new();
+        config.set_empty_field_policy(policy);
+        let mut codec = Codec::<slr::Message, _>::new(dict_fix44(), config);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(11, "ORD1");
+        message.add_str(58, "");
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Body"].get("Text"), expected.as_ref());
+        let decoded = Decoder::decode(&mut codec, &buffer[..]).unwrap();
+        let expected_text = expected.map(|_| slr::FixFieldValue::String(String::new()));
+        assert_eq!(decoded.get_field(58), expected_text.as_ref());
+        assert_eq!(
+            decoded.get_field(11),
+            Some(&slr::FixFieldValue::String("ORD1".to_string()))
+        );
+    }
+
+    #[test]
+    fn unknown_field_is_rejected_by_default() {
+        let mut decoder = encoder_fix44();
+        let message = MESSAGE_SIMPLE.replace(r#""MDReqID": "789","#, r#""9999": "foo","#);
+        let result = Decoder::decode(&mut decoder, message.as_bytes());
+        assert!(matches!(result, Err(DecodeError::InvalidData)));
+    }
+
+    #[test]
+    fn unknown_field_relay_from_json_to_tagvalue() {
+        use crate::codec::tagvalue;
+        let mut config = ConfigSettable::new();
+        config.set_allow_unknown_fields(true);
+        let mut decoder_json = Codec::<slr::Message, _>::new(dict_fix44(), config.clone());
+        let mut encoder_json = Codec::<slr::Message, _>::new(dict_fix44(), config);
+        let mut codec_tagvalue =
+            tagvalue::Codec::<slr::Message, _>::with_dict(dict_fix44(), tagvalue::ConfigDefault);
+        let json_before: Value = from_str(MESSAGE_WITH_UNKNOWN_FIELD).unwrap();
+        let message =
+            Decoder::decode(&mut decoder_json, MESSAGE_WITH_UNKNOWN_FIELD.as_bytes()).unwrap();
+        let mut buffer_json = Vec::<u8>::new();
+        Encoder::encode(&mut encoder_json, &mut buffer_json, message).unwrap();
+        let json_after: Value = from_slice(&buffer_json[..]).unwrap();
+        assert_eq!(json_before, json_after);
+        let mut buffer_tagvalue = Vec::<u8>::new();
+        Encoder::encode(&mut codec_tagvalue, &mut buffer_tagvalue, message).unwrap();
+        let message = Decoder::decode(&mut codec_tagvalue, &buffer_tagvalue[..]).unwrap();
+        assert_eq!(
+            message.get_field(9999),
+            Some(&slr::FixFieldValue::String("foo".to_string()))
+        );
+        assert_eq!(
+            message.get_field(11),
+            Some(&slr::FixFieldValue::String("ORD1".to_string()))
+        );
+    }
+
+    #[test]
+    fn signature_is_base64_encoded() {
+        let mut codec = encoder_fix44();
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(11, "ORD1");
+        let signature = b"\x01sig=\x00nature".to_vec();
+        message.add_int(93, signature.len() as i64);
+        message.add_field(89, slr::FixFieldValue::Data(signature.clone()));
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Trailer"]["SignatureLength"], "12");
+        assert_eq!(json_value["Trailer"]["Signature"], "AXNpZz0AbmF0dXJl");
+        let decoded = Decoder::decode(&mut codec, &buffer[..]).unwrap();
+        assert_eq!(
+            decoded.get_field(89),
+            Some(&slr::FixFieldValue::Data(signature))
+        );
+        crate::codec::test_util::assert_roundtrip_all(decoded, &dict_fix44());
+    }
+
+    #[test]
+    fn embedded_fixml_is_a_nested_object() {
+        let fixml = r#"<FIXML><NewOrderSingle ClOrdID="ORD1" Side="1"><Instrument Symbol="IBM"/></NewOrderSingle></FIXML>"#;
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "0");
+        message.add_field(213, slr::FixFieldValue::Data(fixml.as_bytes().to_vec()));
+        let json_value = encoder_fix44().to_json_value(&message).unwrap();
+        assert!(json_value["Header"]["XmlData"].is_string());
+        let mut config = ConfigSettable::new();
+        config.set_embedded_fixml(true);
+        let mut codec = Codec::<slr::Message, _>::new(dict_fix44(), config);
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(
+            json_value["Header"]["XmlData"],
+            json!({ "MsgType": "D", "ClOrdID": "ORD1", "Side": "1", "Symbol": "IBM" })
+        );
+        let decoded = Decoder::decode(&mut codec, &buffer[..]).unwrap();
+        let data = match decoded.get_field(213) {
+            Some(slr::FixFieldValue::Data(data)) => data,
+            other => panic!("unexpected XmlData {:?}", other),
+        };
+        assert_eq!(
+            fixml_to_message(data, &dict_fix44()),
+            fixml_to_message(fixml.as_bytes(), &dict_fix44())
+        );
+    }
+
+    /// Returns the encoded `TestReqID <112>`, or `None` if it's rejected.
+    fn encode_invalid_utf8(policy: InvalidUtf8Policy) -> Option<Value> {
+        let mut config = ConfigSettable::new();
+        config.set_invalid_utf8_policy(policy);
+        let mut codec = Codec::<slr::Message, _>::new(dict_fix44(), config);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "0");
+        message.add_field(112, slr::FixFieldValue::Data(b"caf\xe9".to_vec()));
+        let mut buffer = Vec::new();
+        match codec.encode(&mut buffer, &message) {
+            Ok(_) => Some(from_slice::<Value>(&buffer[..]).unwrap()["Body"]["TestReqID"].clone()),
+            Err(EncoderError::InvalidUtf8(tag)) => {
+                assert_eq!(tag, 112);
+                None
+            }
+            Err(err) => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_error_by_default() {
+        assert_eq!(encode_invalid_utf8(InvalidUtf8Policy::Error), None);
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        assert_eq!(
+            encode_invalid_utf8(InvalidUtf8Policy::Replace),
+            Some(Value::String("caf\u{fffd}".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_base64_encoded() {
+        assert_eq!(
+            encode_invalid_utf8(InvalidUtf8Policy::Base64),
+            Some(Value::String("Y2Fm6Q==".to_string()))
+        );
+    }
+
+    #[test]
+    fn empty_field_as_empty_string_round_trip() {
+        empty_field_round_trip(EmptyFieldPolicy::EmptyString, Some(json!("")));
+    }
+
+    #[test]
+    fn empty_field_as_null_round_trip() {
+        empty_field_round_trip(EmptyFieldPolicy::Null, Some(Value::Null));
+    }
+
+    #[test]
+    fn empty_field_omitted_round_trip() {
+        empty_field_round_trip(EmptyFieldPolicy::Omit, None);
+    }
+
+    #[test]
+    fn extension_field_with_fix44_dictionary() {
+        let message =
+            MESSAGE_SIMPLE.replace(r#""MsgType": "W","#, r#""MsgType": "W", "ApplExtID": "1","#);
+        let mut codec = encoder_fix44();
+        let decoded = Decoder::decode(&mut codec, message.as_bytes())
+            .unwrap()
+            .clone();
+        assert_eq!(
+            decoded.get_field(1156),
+            Some(&slr::FixFieldValue::String("1".to_string()))
+        );
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &decoded).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Header"]["ApplExtID"], "1");
+        let mut config = ConfigSettable::new();
+        config.set_strict_extension_fields(true);
+        let mut codec = Codec::<slr::Message, _>::new(dict_fix44(), config);
+        let result = Decoder::decode(&mut codec, message.as_bytes());
+        assert!(matches!(result, Err(DecodeError::InvalidData)));
+    }
+
+    #[test]
+    fn price_round_trip_without_precision_loss() {
+        let message = MESSAGE_WITH_EXEC_INST.replace(
+            r#""ClOrdID": "ORD1","#,
+            r#""ClOrdID": "ORD1", "Price": "123456789.123456789","#,
+        );
+        let mut codec = encoder_fix44();
+        let decoded = Decoder::decode(&mut codec, message.as_bytes())
+            .unwrap()
+            .clone();
+        assert_eq!(
+            decoded.price(),
+            Some(Decimal::new(123_456_789_123_456_789, -9))
+        );
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &decoded).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Body"]["Price"], "123456789.123456789");
+    }
+
+    fn boolean_round_trip(style: BooleanStyle, expected: &str) {
+        let mut config = ConfigSettable::new();
+        config.set_boolean_style(style);
+        let mut codec = Codec::<slr::Message, _>::new(dict_fix44(), config);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(11, "ORD1");
+        message.add_str(43, "Y");
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Header"]["PossDupFlag"], expected);
+        let decoded = Decoder::decode(&mut codec, &buffer[..]).unwrap();
+        assert_eq!(decoded, &message);
+    }
+
+    #[test]
+    fn boolean_style_yn_round_trip() {
+        boolean_round_trip(BooleanStyle::YN, "Y");
+    }
+
+    #[test]
+    fn boolean_style_true_false_round_trip() {
+        boolean_round_trip(BooleanStyle::TrueFalse, "true");
+    }
+
+    #[test]
+    fn boolean_style_zero_one_round_trip() {
+        boolean_round_trip(BooleanStyle::ZeroOne, "1");
+    }
+
+    #[test]
+    fn any_boolean_style_is_decoded() {
+        let mut codec = encoder_fix44();
+        for (value, expected) in &[("N", "N"), ("false", "N"), ("0", "N"), ("true", "Y")] {
+            let message = MESSAGE_WITH_EXEC_INST.replace(
+                r#""MsgType": "D","#,
+                &format!(r#""MsgType": "D", "PossDupFlag": "{}","#, value),
+            );
+            let decoded = Decoder::decode(&mut codec, message.as_bytes()).unwrap();
+            assert_eq!(
+                decoded.get_field(43),
+                Some(&slr::FixFieldValue::String(expected.to_string()))
+            );
+        }
+        let message = MESSAGE_WITH_EXEC_INST.replace(
+            r#""MsgType": "D","#,
+            r#""MsgType": "D", "PossDupFlag": "maybe","#,
+        );
+        let result = Decoder::decode(&mut codec, message.as_bytes());
+        assert!(matches!(result, Err(DecodeError::InvalidData)));
+    }
+
+    #[test]
+    fn field_type_override_encodes_order_qty_as_number() {
+        let mut codec = encoder_fix44();
+        let mut overrides = HashMap::new();
+        overrides.insert(38, JsonFieldType::Number);
+        codec.set_field_type_overrides(overrides);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(49, "SENDER");
+        message.add_str(11, "ORD1");
+        message.add_str(38, "1000");
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        let json_value: Value = from_slice(&buffer[..]).unwrap();
+        assert_eq!(json_value["Body"]["OrderQty"], json!(1000));
+        assert_eq!(json_value["Body"]["ClOrdID"], json!("ORD1"));
+        assert_eq!(json_value["Header"]["SenderCompID"], json!("SENDER"));
+        let decoded = Decoder::decode(&mut codec, &buffer[..]).unwrap();
+        assert_eq!(
+            decoded.get_field(38),
+            Some(&slr::FixFieldValue::String("1000".to_string()))
+        );
+    }
+
+    fn logon_with_password(config: ConfigSettable) -> Value {
+        let mut codec = Codec::<slr::Message, _>::new(dict_fix44(), config);
+        codec.set_redacted_tags([554].iter().cloned().collect());
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "A");
+        message.add_str(49, "SENDER");
+        message.add_str(553, "user");
+        message.add_str(554, "hunter2");
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        from_slice(&buffer[..]).unwrap()
+    }
+
+    #[test]
+    fn redacted_password_is_masked() {
+        let json_value = logon_with_password(ConfigSettable::new());
+        assert_eq!(json_value["Body"]["Password"], "***");
+        assert_eq!(json_value["Body"]["Username"], "user");
+    }
+
+    #[test]
+    fn redacted_password_is_omitted() {
+        let mut config = ConfigSettable::new();
+        config.set_redaction_style(RedactionStyle::Omit);
+        let json_value = logon_with_password(config);
+        assert!(json_value["Body"].get("Password").is_none());
+        assert_eq!(json_value["Body"]["Username"], "user");
+    }
+
+    #[test]
+    fn encode_to_writer_matches_encode() {
+        let mut codec = encoder_fix44();
+        let message = Decoder::decode(&mut codec, MESSAGE_SIMPLE.as_bytes())
+            .unwrap()
+            .clone();
+        let mut buffer = Vec::<u8>::new();
+        Encoder::encode(&mut codec, &mut buffer, &message).unwrap();
+        let mut writer = Vec::<u8>::new();
+        codec.encode_to_writer(&mut writer, &message).unwrap();
+        assert_eq!(writer, buffer);
+    }
 }
@@ -10,10 +10,16 @@
 //! - JSON: [`json::Codec`].
 //! - SOFH: [`sofh::Codec`].
 //!
+//! Round-trip assertions for tests live in `test_util`, behind the
+//! `test-util` feature.
+//!
 //! Most encoding types support configuration options via the *transmuter
 //! pattern*. Transmuters are traits that define all configurable options for a
 //! specific encoding.
+use crate::app::slr;
+use crate::dictionary::Dictionary;
 use crate::utils::*;
+use std::collections::HashMap;
 use std::io;
 use std::marker::PhantomData;
 
@@ -21,6 +27,84 @@ pub mod fast;
 pub mod json;
 pub mod sofh;
 pub mod tagvalue;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
+/// Fields introduced by FIXT.1.1 to identify application versions and
+/// extension packs, by tag and name. Counterparties may send them even if
+/// the dictionary in use predates them.
+const EXTENSION_FIELDS: &[(u32, &str)] = &[
+    (1128, "ApplVerID"),
+    (1129, "CstmApplVerID"),
+    (1137, "DefaultApplVerID"),
+    (1156, "ApplExtID"),
+    (1407, "DefaultApplExtID"),
+    (1408, "DefaultCstmApplVerID"),
+];
+
+fn is_extension_field(tag: u32) -> bool {
+    EXTENSION_FIELDS.iter().any(|(t, _)| *t == tag)
+}
+
+/// Returns the tag of the length field and the actual length of all data
+/// fields in `message` (not inside repeating groups) that have a length field
+/// in `dict`, by tag of the data field. Encoders write these lengths rather
+/// than trusting the stored length fields, which may be stale.
+fn data_lengths(dict: &Dictionary, message: &slr::Message) -> HashMap<u32, (u32, usize)> {
+    message
+        .fields
+        .iter()
+        .filter_map(|(tag, value)| {
+            let len = match value {
+                slr::FixFieldValue::Data(raw_data) => raw_data.len(),
+                slr::FixFieldValue::String(s) => s.len(),
+                _ => return None,
+            };
+            let length_field = dict.length_field_for(*tag as u32)?;
+            Some((*tag as u32, (length_field.tag(), len)))
+        })
+        .collect()
+}
+
+/// The encoding of a payload, as guessed by [`detect_format`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DetectedFormat {
+    /// FIX tag-value, i.e. starting with `8=FIX`. See [`tagvalue::Codec`].
+    TagValue,
+    /// JSON, i.e. starting with `{`. See [`json::Codec`].
+    Json,
+    /// FIXML, i.e. starting with `<`.
+    Fixml,
+    /// None of the above.
+    Unknown,
+}
+
+/// Guesses the encoding of `data` by inspecting its leading bytes, so that
+/// servers which accept several encodings can pick the right decoder. Leading
+/// whitespace is ignored, except for FIX tag-value.
+///
+/// The payload itself is not validated in any way.
+///
+/// ```
+/// use fefix::codec::{detect_format, DetectedFormat};
+///
+/// assert_eq!(detect_format(b"8=FIX.4.2\x019=42\x01"), DetectedFormat::TagValue);
+/// assert_eq!(detect_format(b"{\"Header\": {}}"), DetectedFormat::Json);
+/// ```
+pub fn detect_format(data: &[u8]) -> DetectedFormat {
+    if data.starts_with(b"8=FIX") {
+        return DetectedFormat::TagValue;
+    }
+    let start = data
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(data.len());
+    match data.get(start) {
+        Some(b'{') => DetectedFormat::Json,
+        Some(b'<') => DetectedFormat::Fixml,
+        _ => DetectedFormat::Unknown,
+    }
+}
 
 /// A device that can parse a stream of bytes into messages.
 ///
@@ -39,6 +123,11 @@ where
     /// is a good guess. All bytes should be set to 0.
     fn supply_buffer(&mut self) -> &mut [u8];
 
+    /// Tells `self` that only the first `len` bytes of the slice returned by
+    /// the last call to [`StreamingDecoder::supply_buffer`] were filled, e.g.
+    /// after a short read. Without this, all of them are assumed to be.
+    fn set_filled_len(&mut self, _len: usize) {}
+
     /// Validates the contents of the internal buffer and possibly caches the
     /// resulting message. When successful, this method will return a [`Poll`] to
     /// let the caller know whether more bytes are needed or not.
@@ -88,6 +177,24 @@ pub trait Encoder<M> {
         self.encode(&mut buffer, message)?;
         Ok(buffer.as_slice().iter().cloned().collect())
     }
+
+    /// Draws a buffer from `pool` and encodes `message` to it. The buffer
+    /// should be given back to `pool` via [`BufferPool::release`] once it's
+    /// not needed anymore.
+    fn encode_with_pool(
+        &mut self,
+        pool: &mut impl BufferPool,
+        message: &M,
+    ) -> Result<Vec<u8>, Self::Error> {
+        let mut buffer = pool.acquire();
+        match self.encode(&mut buffer, message) {
+            Ok(_) => Ok(buffer),
+            Err(err) => {
+                pool.release(buffer);
+                Err(err)
+            }
+        }
+    }
 }
 
 /// A [`StreamIterator`] that iterates over all the messages that come from a
@@ -112,10 +219,13 @@ where
 {
     pub fn next(&mut self) -> Result<Option<&M>, &FramelessError<E>> {
         loop {
-            let mut buffer = self.decoder.supply_buffer();
-            if let Err(e) = self.source.read(&mut buffer) {
-                self.err = Some(e.into());
-                break;
+            let buffer = self.decoder.supply_buffer();
+            match self.source.read(buffer) {
+                Ok(len) => self.decoder.set_filled_len(len),
+                Err(e) => {
+                    self.err = Some(e.into());
+                    break;
+                }
             }
             match self.decoder.attempt_decoding() {
                 Ok(Some(_)) => break,
@@ -155,3 +265,41 @@ pub enum Poll {
     /// decoding.
     Incomplete,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detect_tagvalue() {
+        let data = b"8=FIX.4.2\x019=42\x0135=0\x0149=A\x0156=B\x0134=12\x0152=20100304-07:59:30\x0110=185\x01";
+        assert_eq!(detect_format(&data[..]), DetectedFormat::TagValue);
+        assert_eq!(detect_format(b"8=FIXT.1.1|9=5|"), DetectedFormat::TagValue);
+    }
+
+    #[test]
+    fn detect_json() {
+        let data = br#"
+            {
+                "Header": { "BeginString": "FIX.4.4", "MsgType": "0" },
+                "Body": {},
+                "Trailer": {}
+            }"#;
+        assert_eq!(detect_format(&data[..]), DetectedFormat::Json);
+    }
+
+    #[test]
+    fn detect_fixml() {
+        let data = br#"<FIXML><Order ClOrdID="123"/></FIXML>"#;
+        assert_eq!(detect_format(&data[..]), DetectedFormat::Fixml);
+        let data = b"<?xml version=\"1.0\"?>\n<FIXML/>";
+        assert_eq!(detect_format(&data[..]), DetectedFormat::Fixml);
+    }
+
+    #[test]
+    fn detect_unknown() {
+        assert_eq!(detect_format(b""), DetectedFormat::Unknown);
+        assert_eq!(detect_format(b"   "), DetectedFormat::Unknown);
+        assert_eq!(detect_format(b"35=0|8=FIX.4.2|"), DetectedFormat::Unknown);
+    }
+}
@@ -4,16 +4,20 @@
 //! currently used by the FIX session layer.
 
 use crate::app::{slr, TsrMessageRef, Version};
-use crate::codec::{Decoder, Encoder, StreamingDecoder};
+use crate::codec::{
+    data_lengths, is_extension_field, Decoder, Encoder, FramelessError, StreamingDecoder,
+};
 use crate::dt;
 use crate::dt::DataType;
 use crate::dictionary::Dictionary;
 use crate::utils::{Buffer, BufferWriter};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 use std::fmt::Debug;
 use std::io;
-use std::rc::Rc;
 use std::str;
+use std::sync::Arc;
 
 /// A (de)serializer for the classic FIX tag-value encoding.
 ///
@@ -25,14 +29,88 @@ use std::str;
 /// [^1]: [FIX TagValue Encoding: Online reference.](https://www.fixtrading.org/standards/tagvalue-online)
 ///
 /// [^2]: [FIX TagValue Encoding: PDF.](https://www.fixtrading.org/standards/tagvalue/)
+///
+/// A [`Codec`] can be moved to another thread, but it's not [`Sync`]: it
+/// caches dictionary lookups internally, even while decoding through a shared
+/// reference.
 #[derive(Debug)]
 pub struct Codec<T, Z> {
-    dict: Dictionary,
+    // The dictionary of the last decoded message, which is either
+    // `default_dict` or one of `appl_ver_dicts`.
+    dict: Arc<Dictionary>,
+    default_dict: Arc<Dictionary>,
+    appl_ver_dicts: HashMap<String, Arc<Dictionary>>,
     buffer: Vec<u8>,
-    state: DecoderState,
+    // How many bytes at the end of `buffer`, as supplied to the reader, are
+    // not filled yet.
+    unfilled_len: usize,
+    body_buffer: Vec<u8>,
+    // The tags decoded by the last call to `Codec::decode_into`.
+    decoded_tags: Vec<i64>,
     message: T,
     body: Body,
     config: Z,
+    warnings: Vec<DecodeWarning>,
+    checksum_fn: Option<ChecksumFn>,
+    field_order: Vec<u32>,
+    fields: FieldCache,
+}
+
+/// A user-provided replacement for the checksum calculation of the encoder.
+/// See [`Codec::set_checksum_fn`].
+pub type CustomChecksumFn = Box<dyn Fn(&[u8]) -> u8 + Send>;
+
+struct ChecksumFn(CustomChecksumFn);
+
+impl Debug for ChecksumFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ChecksumFn")
+    }
+}
+
+/// Memoizes [`Dictionary::field_by_tag`] lookups, which come up again and
+/// again for the same tags while decoding, as well as repeating group
+/// definitions. Entries are added lazily and must be cleared whenever the
+/// [`Dictionary`] of the [`Codec`] changes.
+#[derive(Debug, Default)]
+struct FieldCache {
+    fields: RefCell<HashMap<u32, Option<FieldInfo>>>,
+    groups: RefCell<HashMap<u32, Option<Arc<BTreeSet<u32>>>>>,
+}
+
+/// The properties of a field definition that the decoder cares about.
+#[derive(Debug, Copy, Clone)]
+struct FieldInfo {
+    basetype: DataType,
+    exact_data_type: DataType,
+    is_deprecated: bool,
+}
+
+impl FieldCache {
+    fn get(&self, dict: &Dictionary, tag: u32) -> Option<FieldInfo> {
+        *self.fields.borrow_mut().entry(tag).or_insert_with(|| {
+            dict.field_by_tag(tag).map(|field| FieldInfo {
+                basetype: field.basetype(),
+                exact_data_type: field.exact_data_type(),
+                is_deprecated: field.is_deprecated(),
+            })
+        })
+    }
+
+    /// Returns the tags of the entry fields of the repeating group counted by
+    /// `tag`, if any. See [`Dictionary::group_entry_tags`].
+    fn group_entry_tags(&self, dict: &Dictionary, tag: u32) -> Option<Arc<BTreeSet<u32>>> {
+        self.groups
+            .borrow_mut()
+            .entry(tag)
+            .or_insert_with(|| dict.group_entry_tags(tag).map(Arc::new))
+            .clone()
+    }
+
+    fn clear(&mut self) {
+        self.fields.get_mut().clear();
+        self.groups.get_mut().clear();
+    }
 }
 
 impl<T, Z> Codec<T, Z>
@@ -47,24 +125,71 @@ where
 
     /// Creates a new codec for the tag-value format. `dict` is used to parse messages.
     pub fn with_dict(dict: Dictionary, config: Z) -> Self {
+        let dict = Arc::new(dict);
         Self {
-            dict,
+            dict: dict.clone(),
+            default_dict: dict,
+            appl_ver_dicts: HashMap::new(),
             buffer: Vec::new(),
-            state: DecoderState::Header,
+            unfilled_len: 0,
+            body_buffer: Vec::new(),
+            decoded_tags: Vec::new(),
             message: T::default(),
             body: Body::new(&[]),
             config,
+            warnings: Vec::new(),
+            checksum_fn: None,
+            field_order: Vec::new(),
+            fields: FieldCache::default(),
         }
     }
-}
 
-#[derive(Debug)]
-enum DecoderState {
-    Header,
-    Body(usize),
-    Trailer,
+    /// Replaces the [`Dictionary`] used to parse messages with `dict`, e.g.
+    /// after a counterparty switched FIX version.
+    pub fn set_dict(&mut self, dict: Dictionary) {
+        self.default_dict = Arc::new(dict);
+        self.dict = self.default_dict.clone();
+        self.fields.clear();
+    }
+
+    /// Maps values of `ApplVerID <1128>` (e.g. "7" for FIX 5.0) to the
+    /// [`Dictionary`] of their application messages, replacing any previous
+    /// mapping. `FIXT.1.1` messages are then decoded with the dictionary of
+    /// their `ApplVerID <1128>`, if mapped, and all other messages with the
+    /// dictionary of `self`.
+    pub fn set_appl_ver_dicts(&mut self, dicts: HashMap<String, Dictionary>) {
+        self.appl_ver_dicts = dicts
+            .into_iter()
+            .map(|(appl_ver_id, dict)| (appl_ver_id, Arc::new(dict)))
+            .collect();
+    }
+
+    /// Switches to the dictionary that the message in `data` must be decoded
+    /// with. See [`Codec::set_appl_ver_dicts`].
+    fn select_dict(&mut self, data: &[u8]) {
+        let appl_ver_dict = if data.starts_with(b"8=FIXT.1.1") {
+            peek_field(data, Z::SOH_SEPARATOR, b"1128=")
+                .and_then(|appl_ver_id| self.appl_ver_dicts.get(appl_ver_id))
+        } else {
+            None
+        };
+        let dict = appl_ver_dict.unwrap_or(&self.default_dict);
+        if !Arc::ptr_eq(&self.dict, dict) {
+            self.dict = dict.clone();
+            self.fields.clear();
+        }
+    }
+
+    /// Returns the [`DecodeWarning`]s collected while decoding the last
+    /// message. Warnings are only collected if enabled by [`Config`].
+    pub fn warnings(&self) -> &[DecodeWarning] {
+        &self.warnings[..]
+    }
 }
 
+/// How many bytes [`StreamingDecoder::supply_buffer`] asks for at a time.
+const STREAMING_READ_SIZE: usize = 50;
+
 #[derive(Debug)]
 pub struct Body {
     len: usize,
@@ -86,54 +211,29 @@ where
 
     fn supply_buffer(&mut self) -> &mut [u8] {
         let buffer_len = self.buffer.len();
-        let additional_capacity = match self.state {
-            DecoderState::Header => 50,
-            DecoderState::Body(n) => n,
-            DecoderState::Trailer => 7,
-        };
-        for _ in 0..additional_capacity {
+        for _ in 0..STREAMING_READ_SIZE {
             self.buffer.push(0);
         }
+        self.unfilled_len = STREAMING_READ_SIZE;
         &mut self.buffer[buffer_len..]
     }
 
+    fn set_filled_len(&mut self, len: usize) {
+        let unfilled_len = self.unfilled_len.saturating_sub(len);
+        self.buffer.truncate(self.buffer.len() - unfilled_len);
+        self.unfilled_len = 0;
+    }
+
     fn attempt_decoding(&mut self) -> Result<Option<&Body>, Self::Error> {
-        let mut field_iter: &mut FieldIter<_, Z> = &mut FieldIter {
-            handle: &mut &self.buffer[..],
-            designator: Z::TagLookup::from_dict(&self.dict),
-            is_last: false,
-            data_length: 0,
-        };
-        let mut message = slr::Message::new();
-        {
-            // `BeginString(8)`.
-            let f = field_iter.next().ok_or(Error::Eof)??;
-            if f.tag() == 8 {
-                message.set_field(f.tag() as u32, f.value().clone());
-            } else {
-                return Err(Error::InvalidStandardHeader);
-            }
-        };
-        {
-            // `BodyLength(9)`.
-            let f = field_iter.next().ok_or(Error::InvalidStandardHeader)??;
-            if f.tag() == 9 {
-                message.set_field(f.tag() as u32, f.value().clone());
-            } else {
-                return Err(Error::InvalidStandardHeader);
-            }
-        };
-        {
-            // `MsgType(35)`.
-            let f = field_iter.next().ok_or(Error::InvalidStandardHeader)??;
-            if f.tag() == 35 {
-                message.set_field(f.tag() as u32, f.value().clone());
-            } else {
-                return Err(Error::InvalidStandardHeader);
-            }
+        // Messages are delimited just like by `Codec::frame_len`, including
+        // the `Config::LENIENT_BODY_LENGTH` fallback.
+        let len = match frame_len::<Z>(&self.buffer[..])? {
+            Some(len) => len,
+            None => return Ok(None),
         };
-        self.state = DecoderState::Body(0);
-        self.state = DecoderState::Trailer;
+        let frame: Vec<u8> = self.buffer.drain(..len).collect();
+        self.decode(&frame[..])?;
+        self.body = Body::new(&frame[..]);
         Ok(Some(&self.body))
     }
 
@@ -149,52 +249,249 @@ where
 {
     type Error = DecodeError;
 
-    fn decode(&mut self, mut data: &[u8]) -> Result<&T, Self::Error> {
+    fn decode(&mut self, data: &[u8]) -> Result<&T, Self::Error> {
+        let mut message = T::default();
+        self.decode_fields(data, None, &mut message)?;
+        self.message = message;
+        Ok(&self.message)
+    }
+}
+
+impl<T, Z> Codec<T, Z>
+where
+    T: TsrMessageRef,
+    Z: Config,
+{
+    /// Decodes the message in `s`. This is a shorthand for
+    /// [`Decoder::decode`] which comes in handy in tests, where messages
+    /// are often written as string literals.
+    pub fn decode_str(&mut self, s: &str) -> Result<&T, DecodeError> {
+        self.decode(s.as_bytes())
+    }
+
+    /// Decodes `data` into `target`. The same [`slr::Message`] can thus be
+    /// reused across many calls instead of building a new one for every
+    /// message: fields that `target` already has (e.g. header fields) are
+    /// overwritten in place, and only the fields that are not in `data` are
+    /// removed.
+    ///
+    /// In case of failure, the contents of `target` are unspecified.
+    pub fn decode_into(
+        &mut self,
+        data: &[u8],
+        target: &mut slr::Message,
+    ) -> Result<(), DecodeError> {
+        let mut tags = std::mem::take(&mut self.decoded_tags);
+        tags.clear();
+        let result = self.visit_fields(data, None, |_, tag, value| {
+            let tag = i64::from(tag);
+            match target.fields.get_mut(&tag) {
+                Some(old_value) => *old_value = value.clone(),
+                None => {
+                    target.fields.insert(tag, value.clone());
+                }
+            }
+            tags.push(tag);
+        });
+        tags.sort_unstable();
+        target
+            .fields
+            .retain(|tag, _| tags.binary_search(tag).is_ok());
+        self.decoded_tags = tags;
+        result
+    }
+
+    /// Decodes `data`, which lacks `BeginString <8>` (e.g. because the
+    /// transport strips it and supplies the version separately). The decoded
+    /// message will have `BeginString <8>` set according to `version`.
+    ///
+    /// Messages are still decoded with the [`Dictionary`] of `self`.
+    pub fn decode_with_version(
+        &mut self,
+        data: &[u8],
+        version: Version,
+    ) -> Result<&T, DecodeError> {
+        let mut message = T::default();
+        self.decode_fields(data, Some(version), &mut message)?;
+        self.message = message;
+        Ok(&self.message)
+    }
+
+    /// Returns the length of the first message in `data`, up to and including
+    /// the separator after `CheckSum <10>`, or `None` if `data` doesn't
+    /// contain a whole message yet. This is useful to split a stream of bytes
+    /// into messages before decoding them.
+    ///
+    /// Messages are delimited by `BodyLength <9>`. If it's zero or missing,
+    /// [`Config::LENIENT_BODY_LENGTH`] allows scanning for `CheckSum <10>`
+    /// instead; otherwise, that's an [`Error::InvalidStandardHeader`].
+    pub fn frame_len(&self, data: &[u8]) -> Result<Option<usize>, DecodeError> {
+        frame_len::<Z>(data)
+    }
+
+    /// Decodes `data` field by field, feeding them to `visitor` instead of
+    /// building a message, which saves allocations. Repeating groups are
+    /// delimited according to the [`Dictionary`] of `self`: a group starts
+    /// right after its counter field and ends before the first field which
+    /// can't be part of its entries.
+    ///
+    /// Fields are visited as soon as they are decoded, i.e. before the checksum
+    /// is verified. In case of failure, `visitor` may thus have seen part of
+    /// the message.
+    pub fn decode_visit<V: FieldVisitor>(
+        &mut self,
+        data: &[u8],
+        visitor: &mut V,
+    ) -> Result<(), DecodeError> {
+        let mut groups: Vec<Arc<BTreeSet<u32>>> = Vec::new();
+        self.visit_fields(data, None, |codec, tag, value| {
+            while let Some(entry_tags) = groups.last() {
+                if entry_tags.contains(&tag) {
+                    break;
+                }
+                groups.pop();
+                visitor.on_group_end();
+            }
+            visitor.on_field(tag, value);
+            if let slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(n))) = value {
+                if *n > 0 {
+                    if let Some(entry_tags) = codec.fields.group_entry_tags(&codec.dict, tag) {
+                        visitor.on_group_start(tag, *n as usize);
+                        groups.push(entry_tags);
+                    }
+                }
+            }
+        })?;
+        for _ in groups {
+            visitor.on_group_end();
+        }
+        Ok(())
+    }
+
+    fn decode_fields<M>(
+        &mut self,
+        data: &[u8],
+        version: Option<Version>,
+        message: &mut M,
+    ) -> Result<(), DecodeError>
+    where
+        M: TsrMessageRef,
+    {
+        self.visit_fields(data, version, |_, tag, value| {
+            message.set_field(tag, value.clone())
+        })
+    }
+
+    /// Decodes `data` and calls `on_field` with every field, in order.
+    fn visit_fields<F>(
+        &mut self,
+        mut data: &[u8],
+        version: Option<Version>,
+        mut on_field: F,
+    ) -> Result<(), DecodeError>
+    where
+        F: FnMut(&Self, u32, &slr::FixFieldValue),
+    {
+        self.select_dict(data);
+        if Z::LENIENT_BODY_LENGTH {
+            // `BodyLength <9>` can't be trusted, so anything after
+            // `CheckSum <10>` is ignored.
+            if let Some(n) = checksum_end(data, Z::SOH_SEPARATOR) {
+                data = &data[..n];
+            }
+        }
+        let terminated_data;
+        let mut data = data;
+        if Z::LENIENT_TRAILING_SOH && data.last() != Some(&Z::SOH_SEPARATOR) {
+            terminated_data = [data, &[Z::SOH_SEPARATOR]].concat();
+            data = &terminated_data[..];
+        }
+        // `10=NNN` and its separator, unless the checksum may have any number
+        // of digits.
+        let trailer_len = if Z::LENIENT_CHECKSUM_FORMAT {
+            data.windows(4)
+                .rposition(|window| window[0] == Z::SOH_SEPARATOR && &window[1..] == b"10=")
+                .map_or(7, |i| data.len() - i - 1)
+        } else {
+            7
+        };
         let mut checksum = Z::ChecksumAlgo::default();
-        checksum.roll(&data[..data.len() - 7]);
+        let body = &data[..data.len() - trailer_len];
+        roll_with_soh(&mut checksum, body, Z::SOH_SEPARATOR);
         let mut field_iter: &mut FieldIter<_, Z> = &mut FieldIter {
             handle: &mut data,
             designator: Z::TagLookup::from_dict(&self.dict),
+            dict: &self.dict,
+            fields: &self.fields,
             is_last: false,
-            data_length: 0,
+            data_length: None,
         };
-        let mut message = T::default();
-        {
+        if let Some(version) = version {
+            // `BeginString(8)` is supplied out-of-band.
+            let begin_string = version.begin_string().to_string();
+            on_field(self, 8, &slr::FixFieldValue::String(begin_string));
+        } else {
             // `BeginString(8)`.
             let f = field_iter.next().ok_or(Error::Eof)??;
             if f.tag() == 8 {
-                message.set_field(f.tag() as u32, f.value().clone());
+                on_field(self, f.tag() as u32, f.value());
             } else {
                 return Err(Error::InvalidStandardHeader);
             }
         };
-        {
+        let mut f = field_iter.next().ok_or(Error::InvalidStandardHeader)??;
+        if f.tag() == 9 {
             // `BodyLength(9)`.
-            let f = field_iter.next().ok_or(Error::InvalidStandardHeader)??;
-            if f.tag() == 9 {
-                message.set_field(f.tag() as u32, f.value().clone());
-            } else {
-                return Err(Error::InvalidStandardHeader);
-            }
-        };
-        {
+            on_field(self, f.tag() as u32, f.value());
+            f = field_iter.next().ok_or(Error::InvalidStandardHeader)??;
+        } else if !Z::LENIENT_BODY_LENGTH {
+            return Err(Error::InvalidStandardHeader);
+        }
+        if f.tag() == 35 {
             // `MsgType(35)`.
-            let f = field_iter.next().ok_or(Error::InvalidStandardHeader)??;
-            if f.tag() == 35 {
-                message.set_field(f.tag() as u32, f.value().clone());
-            } else {
-                return Err(Error::InvalidStandardHeader);
-            }
-        };
+            on_field(self, f.tag() as u32, f.value());
+        } else {
+            return Err(Error::InvalidStandardHeader);
+        }
         let mut last_tag = 35;
+        let mut checksum_field = None;
+        self.warnings.clear();
         for f_result in &mut field_iter {
             let f = f_result?;
-            message.set_field(f.tag() as u32, f.value().clone());
+            if Z::WARN_ON_DEPRECATED_FIELDS {
+                let tag = f.tag() as u32;
+                let field = self.fields.get(&self.dict, tag);
+                if field.is_some_and(|field| field.is_deprecated) {
+                    self.warnings.push(DecodeWarning::DeprecatedField(tag));
+                }
+            }
+            if let Some(max) = self.config.max_group_entries() {
+                // Fail fast on the declared number of entries, before reading
+                // any of them.
+                if let slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(n))) = f.value() {
+                    let is_group_counter = self
+                        .fields
+                        .get(&self.dict, f.tag() as u32)
+                        .is_some_and(|field| field.basetype == DataType::NumInGroup);
+                    if is_group_counter && *n as usize > max {
+                        return Err(Error::GroupTooLarge);
+                    }
+                }
+            }
+            on_field(self, f.tag() as u32, f.value());
+            if f.tag() == 10 {
+                checksum_field = Some(f.value().clone());
+            }
             last_tag = f.tag();
         }
-        let chesksum_field = message.get_field(10);
-        if let Some(slr::FixFieldValue::String(s)) = chesksum_field {
-            let n = s.as_str().parse::<u8>().unwrap();
+        if let Some(slr::FixFieldValue::String(s)) = checksum_field {
+            let is_well_formed = Z::LENIENT_CHECKSUM_FORMAT
+                || (s.len() == 3 && s.bytes().all(|byte| byte.is_ascii_digit()));
+            let n = s
+                .parse::<u8>()
+                .ok()
+                .filter(|_| is_well_formed)
+                .ok_or(Error::InvalidStandardTrailer)?;
             if !checksum.verify(n) {
                 let checksum_error = InvalidChecksum {
                     actual: n,
@@ -204,8 +501,7 @@ where
             }
         }
         if last_tag == 10 {
-            self.message = message;
-            Ok(&self.message)
+            Ok(())
         } else {
             Err(Error::InvalidStandardTrailer)
         }
@@ -224,62 +520,240 @@ where
         message: &slr::Message,
     ) -> Result<usize, Self::Error> {
         let mut writer = BufferWriter::new(&mut buffer);
-        // First, write `BeginString(8)`.
+        self.encode_to_writer(&mut writer, message)?;
+        Ok(writer.as_slice().len())
+    }
+}
+
+impl<Z> Codec<slr::Message, Z>
+where
+    Z: Config,
+{
+    /// Replaces the checksum calculation of the encoder with `checksum_fn`,
+    /// which is given everything before `CheckSum <10>` in its SOH-delimited
+    /// form. This is meant for testing counterparties, e.g. by deliberately
+    /// producing wrong checksums.
+    pub fn set_checksum_fn(&mut self, checksum_fn: CustomChecksumFn) {
+        self.checksum_fn = Some(ChecksumFn(checksum_fn));
+    }
+
+    /// Makes the encoder write the fields with the given tags right after
+    /// `MsgType <35>`, in this order, e.g. to please a counterparty that
+    /// expects a specific layout. All other fields follow in ascending tag
+    /// order, as usual. Replaces any previous order; repeated tags only count
+    /// once.
+    ///
+    /// `BeginString <8>`, `BodyLength <9>`, `SignatureLength <93>`,
+    /// `Signature <89>` and `CheckSum <10>` always keep their positions, and
+    /// data fields are always preceded by their length field.
+    ///
+    /// The JSON encoder has no counterpart to this, as JSON objects are
+    /// unordered; see [`json::Codec`](crate::codec::json::Codec).
+    pub fn set_field_order(&mut self, tags: Vec<u32>) {
+        self.field_order.clear();
+        for tag in tags {
+            if !self.field_order.contains(&tag) {
+                self.field_order.push(tag);
+            }
+        }
+    }
+
+    /// Encodes `message` into `writer`, e.g. a file or a socket, rather than
+    /// into a [`Buffer`]. The output is the same as that of
+    /// [`Encoder::encode`].
+    ///
+    /// The message is still buffered: `BodyLength <9>` must be written before
+    /// the body, so the body is serialized into an internal buffer (which is
+    /// reused across calls) and `BeginString <8>`, `BodyLength <9>` and
+    /// `CheckSum <10>` into small buffers of their own. With a custom checksum
+    /// function (see [`Codec::set_checksum_fn`]), header and body are also
+    /// copied into a single buffer to compute the checksum.
+    pub fn encode_to_writer<W: io::Write>(
+        &mut self,
+        mut writer: W,
+        message: &slr::Message,
+    ) -> Result<(), EncodeError> {
+        // `BodyLength(9)` comes before the body, but obviously the length of
+        // the message is unknown until later in the serialization phase. We
+        // thus serialize the body into an internal buffer first and write it
+        // out once `BodyLength(9)` is written. Zero-padding `BodyLength(9)`
+        // instead would avoid the buffer, but the output wouldn't match
+        // messages written by other engines byte for byte.
+        let mut body = std::mem::take(&mut self.body_buffer);
+        body.clear();
+        self.encode_body(message, &mut body)?;
+        let mut header = Vec::new();
+        // First, `BeginString(8)` and `BodyLength(9)`.
         encode_field(
             8.into(),
             message.get_field(8).unwrap(),
-            &mut writer,
+            &mut header,
+            Z::SOH_SEPARATOR,
+        );
+        encode_field(
+            9.into(),
+            &slr::FixFieldValue::from(body.len()),
+            &mut header,
+            Z::SOH_SEPARATOR,
+        );
+        writer.write_all(&header[..])?;
+        writer.write_all(&body[..])?;
+        // Finally, we need to serialize the `Checksum(10)` field. It's always
+        // three digits long.
+        let checksum = match &self.checksum_fn {
+            Some(ChecksumFn(checksum_fn)) => {
+                let mut bytes = [&header[..], &body[..]].concat();
+                for byte in bytes.iter_mut().filter(|byte| **byte == Z::SOH_SEPARATOR) {
+                    *byte = SOH;
+                }
+                checksum_fn(&bytes[..])
+            }
+            None => {
+                let mut checksum = Z::ChecksumAlgo::default();
+                roll_with_soh(&mut checksum, &header[..], Z::SOH_SEPARATOR);
+                roll_with_soh(&mut checksum, &body[..], Z::SOH_SEPARATOR);
+                checksum.result()
+            }
+        };
+        self.body_buffer = body;
+        let mut trailer = Vec::new();
+        encode_field(
+            10.into(),
+            &slr::FixFieldValue::String(format!("{:03}", checksum)),
+            &mut trailer,
             Z::SOH_SEPARATOR,
         );
-        // The second field is supposed to be `BodyLength(9)`, but obviously
-        // the length of the message is unknow until later in the
-        // serialization phase. This alone would usually require to
-        //
-        //  1. Serialize the rest of the message into an external buffer.
-        //  2. Calculate the length of the message.
-        //  3. Serialize `BodyLength(9)`.
-        //  4. Copy the contents of the external buffer into `buffer`.
-        //  5. ... go on with the serialization process.
-        //
-        // Luckily, FIX allows for zero-padded integer values and we can
-        // leverage this to reserve some space for the value. We might waste
-        // some bytes but the benefits largely outweight the costs.
-        //
-        // Six digits (~1MB) ought to be enough for every message.
-        writer.extend_from_slice(b"9=000000");
-        writer.extend_from_slice(&[Z::SOH_SEPARATOR]);
-        let body_length_range = writer.as_slice().len() - 7..writer.as_slice().len() - 2;
-        // We now must start to calculate the message length.
-        let mut len = writer.as_slice().len();
+        if !Z::TRAILING_SOH {
+            trailer.pop();
+        }
+        writer.write_all(&trailer[..])?;
+        Ok(())
+    }
+
+    /// Serializes all fields of `message` from `MsgType(35)` up to, but
+    /// excluding, `CheckSum(10)` into `body`.
+    fn encode_body(&self, message: &slr::Message, body: &mut Vec<u8>) -> Result<(), EncodeError> {
         // Third field: `MsgType(35)`.
         encode_field(
             35.into(),
             message.get_field(35).unwrap(),
-            &mut writer,
+            body,
             Z::SOH_SEPARATOR,
         );
-        // Now all the other fields.
-        for (tag, value) in message.fields.iter() {
-            if *tag != 35 {
-                encode_field((*tag as u16).into(), value, &mut writer, Z::SOH_SEPARATOR);
+        // Now all the other fields. Data fields are always preceded by their
+        // actual length, whatever the value of their length field in
+        // `message`.
+        let data_lengths = data_lengths(&self.dict, message);
+        let ordered_fields = self
+            .field_order
+            .iter()
+            .filter_map(|tag| message.fields.get_key_value(&i64::from(*tag)));
+        let other_fields = message
+            .fields
+            .iter()
+            .filter(|(tag, _)| !self.field_order.contains(&(**tag as u32)));
+        for (tag, value) in ordered_fields.chain(other_fields) {
+            // These are written separately, if at all.
+            if let 8 | 9 | 10 | 35 | 89 | 93 = *tag {
+                continue;
+            }
+            if data_lengths
+                .values()
+                .any(|(length_tag, _)| i64::from(*length_tag) == *tag)
+            {
+                continue;
             }
+            if let Some((length_tag, len)) = data_lengths.get(&(*tag as u32)) {
+                encode_field(
+                    (*length_tag as u16).into(),
+                    &slr::FixFieldValue::from(*len),
+                    body,
+                    Z::SOH_SEPARATOR,
+                );
+            }
+            encode_field((*tag as u16).into(), value, body, Z::SOH_SEPARATOR);
         }
-        len = writer.as_slice().len() - len;
-        // Finally, we need to serialize the `Checksum(10)` field.
-        //encode_field(9.into(), &slr::FixFieldValue::Int(len as i64), &mut writer)?;
-        for i in body_length_range.rev() {
-            writer.as_mut_slice()[i] = (len % 10) as u8 + '0' as u8;
-            len /= 10;
+        // `Signature <89>` must be the last field before `CheckSum <10>`,
+        // right after its length in `SignatureLength <93>`.
+        match message.get_field(89) {
+            Some(signature) => {
+                let signature_len = match signature {
+                    slr::FixFieldValue::Data(raw_data) => raw_data.len(),
+                    slr::FixFieldValue::String(s) => s.len(),
+                    _ => return Err(Error::InvalidData),
+                };
+                encode_field(
+                    93.into(),
+                    &slr::FixFieldValue::from(signature_len),
+                    body,
+                    Z::SOH_SEPARATOR,
+                );
+                encode_field(89.into(), signature, body, Z::SOH_SEPARATOR);
+            }
+            None => {
+                if let Some(signature_len) = message.get_field(93) {
+                    encode_field(93.into(), signature_len, body, Z::SOH_SEPARATOR);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Z> Codec<slr::Message, Z>
+where
+    Z: Config,
+{
+    /// Decodes `data` and immediately re-encodes it, checking that the
+    /// result is identical to `data`. SOH and the configured separator are
+    /// considered equal. This is useful for conformance testing, as it
+    /// pinpoints exactly where the codec loses or alters information.
+    ///
+    /// Note that the encoder always writes fields in its own canonical order,
+    /// so messages with reordered body fields don't round-trip.
+    pub fn verify_roundtrip(&mut self, data: &[u8]) -> Result<(), RoundtripError> {
+        let message = self.decode(data).map_err(RoundtripError::Decode)?.clone();
+        let mut output = Vec::new();
+        self.encode(&mut output, &message)
+            .map_err(RoundtripError::Encode)?;
+        let normalize = |byte: &u8| {
+            if *byte == Z::SOH_SEPARATOR {
+                SOH
+            } else {
+                *byte
+            }
+        };
+        let position = data
+            .iter()
+            .map(normalize)
+            .zip(output.iter().map(normalize))
+            .position(|(expected, actual)| expected != actual);
+        match position {
+            Some(position) => Err(RoundtripError::Mismatch(position)),
+            None if data.len() != output.len() => {
+                Err(RoundtripError::Mismatch(data.len().min(output.len())))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Rolls `checksum` over `window` as if `separator` were SOH (ASCII 0x1).
+///
+/// The FIX checksum is always calculated over the SOH-delimited form of a
+/// message, so custom separators (e.g. `|` in test fixtures) must be mapped
+/// back to SOH, or validation would fail spuriously.
+fn roll_with_soh(checksum: &mut impl ChecksumAlgo, window: &[u8], separator: u8) {
+    if separator == SOH {
+        checksum.roll(window);
+    } else {
+        for byte in window {
+            if *byte == separator {
+                checksum.roll(&[SOH]);
+            } else {
+                checksum.roll(&[*byte]);
+            }
         }
-        let mut checksum = Z::ChecksumAlgo::default();
-        checksum.roll(writer.as_slice());
-        encode_field(
-            10.into(),
-            &slr::FixFieldValue::from(checksum.result() as i64),
-            &mut writer,
-            Z::SOH_SEPARATOR,
-        );
-        Ok(writer.as_slice().len())
     }
 }
 
@@ -295,97 +769,566 @@ fn encode_field(
         slr::FixFieldValue::String(s) => write.extend_from_slice(s.as_bytes()),
         slr::FixFieldValue::Data(raw_data) => write.extend_from_slice(&raw_data),
         slr::FixFieldValue::Group(_) => panic!("Can't encode a group!"),
+        slr::FixFieldValue::MultiValue(values) => {
+            write.extend_from_slice(values.join(" ").as_bytes())
+        }
         slr::FixFieldValue::Value(field) => write.extend_from_slice(field.to_string().as_bytes()),
     };
     write.extend_from_slice(&[separator]);
 }
 
-/// This trait describes dynamic tag lookup logic.
+/// Returns the number of `tag=value` pairs in `data`, including framing fields
+/// and the fields of repeating groups, without decoding it. The last field
+/// counts even if it lacks its final `separator`.
 ///
-/// In this context, "tag lookup"
-/// means to search in the dictionary the data type associated with a specific
-/// tag number. This may seem trivial at best, but it can actually be quite
-/// convoluted and require internal state (thus it is "dynamic" tag lookup). In
-/// particular, several fields affect the internal state of a
-/// [`TagLookup`](TagLookup):
+/// This is a single scan for `separator`, so data fields that contain it are
+/// miscounted.
 ///
-///  - `ApplVerID <1128>`
-///  - `ApplExtID <1156>`
-///  - `CstmApplVerID <1129>`
-///  - `DefaultApplVerID <1137>`
-///  - `DefaultApplExtID <1407>`
-///  - `DefaultCstmApplVerID <1408>`
+/// ```
+/// use fefix::codec::tagvalue::count_fields;
 ///
-/// Each of these fields affects the internal state and thus changes how
-/// subsequent fields (and messages) are interpreted.
+/// let heartbeat = b"8=FIX.4.2|9=42|35=0|49=A|56=B|34=12|52=20100304-07:59:30|10=185|";
+/// assert_eq!(count_fields(heartbeat, b'|'), 8);
+/// ```
+pub fn count_fields(data: &[u8], separator: u8) -> usize {
+    let count = data.iter().filter(|byte| **byte == separator).count();
+    match data.last() {
+        Some(byte) if *byte != separator => count + 1,
+        _ => count,
+    }
+}
+
+/// Scans the fields of the message in `data` as `(tag, value)` pairs, with
+/// neither a dictionary nor any heap allocation. Meant for hot paths that only
+/// look at a few raw values. See [`TagScanner`].
 ///
-/// # Naming conventions
-/// Implementors of this trait should start with `TagLookup`.
-pub trait TagLookup {
-    type Error: Debug;
+/// ```
+/// use fefix::codec::tagvalue::scan;
+///
+/// let heartbeat = b"8=FIX.4.2|9=42|35=0|49=A|56=B|34=12|52=20100304-07:59:30|10=185|";
+/// let mut fields = scan(heartbeat, b'|');
+/// assert_eq!(fields.next(), Some((8, &b"FIX.4.2"[..])));
+/// assert_eq!(fields.nth(1), Some((35, &b"0"[..])));
+/// assert_eq!(fields.last(), Some((10, &b"185"[..])));
+/// ```
+pub fn scan(data: &[u8], separator: u8) -> TagScanner<'_> {
+    TagScanner { data, separator }
+}
+
+/// An [`Iterator`] over the fields of a message, as returned by [`scan`].
+///
+/// Tags are parsed on the fly, while values are left as they are. Iteration
+/// stops right after `CheckSum <10>`, at the end of the data or at the first
+/// malformed field (i.e. without a numeric tag followed by `=`). Data fields
+/// are not special-cased, so they must not contain the separator.
+#[derive(Debug, Clone)]
+pub struct TagScanner<'a> {
+    data: &'a [u8],
+    separator: u8,
+}
 
-    fn from_dict(dict: &Dictionary) -> Self;
+impl<'a> Iterator for TagScanner<'a> {
+    type Item = (u32, &'a [u8]);
 
-    /// Returns the [`BaseType`] of the tag number `tag`.
-    fn lookup(&mut self, tag: u32) -> Result<dt::DataType, Self::Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut tag = 0u32;
+        let mut digits = 0;
+        while let Some(byte) = self.data.get(digits).filter(|byte| byte.is_ascii_digit()) {
+            tag = tag.checked_mul(10)?.checked_add(u32::from(byte - b'0'))?;
+            digits += 1;
+        }
+        if digits == 0 || self.data.get(digits) != Some(&b'=') {
+            return None;
+        }
+        let rest = &self.data[digits + 1..];
+        let len = rest
+            .iter()
+            .position(|byte| *byte == self.separator)
+            .unwrap_or(rest.len());
+        self.data = match tag {
+            10 => &[],
+            _ => rest.get(len + 1..).unwrap_or(&[]),
+        };
+        Some((tag, &rest[..len]))
+    }
 }
 
-/// A [`TagLookup`] that only allows a specific revision of the standard, like
-/// most venues do.
-#[derive(Debug)]
-pub struct TagLookupPredetermined {
-    current_dict: Rc<Dictionary>,
+/// Returns the value of `MsgType <35>` in the message in `data` without
+/// decoding it, or `None` if there's no such field. `MsgType <35>` is the
+/// third field of well-formed messages, but it's accepted anywhere.
+///
+/// ```
+/// use fefix::codec::tagvalue::peek_msg_type;
+///
+/// let heartbeat = b"8=FIX.4.2|9=42|35=0|49=A|56=B|34=12|52=20100304-07:59:30|10=185|";
+/// assert_eq!(peek_msg_type(heartbeat, b'|'), Some("0"));
+/// ```
+pub fn peek_msg_type(data: &[u8], separator: u8) -> Option<&str> {
+    peek_field(data, separator, b"35=")
 }
 
-impl TagLookup for TagLookupPredetermined {
-    type Error = TagLookupPredeterminedError;
+/// Returns the value of the first field in `data` that starts with `prefix`,
+/// e.g. `b"35="`.
+fn peek_field<'a>(data: &'a [u8], separator: u8, prefix: &[u8]) -> Option<&'a str> {
+    data.split(|byte| *byte == separator)
+        .find_map(|field| field.strip_prefix(prefix))
+        .and_then(|value| str::from_utf8(value).ok())
+}
 
-    fn from_dict(dict: &Dictionary) -> Self {
-        Self {
-            current_dict: Rc::new(dict.clone()),
+/// Counts the messages in a log by `MsgType <35>`, without decoding them.
+/// Messages start with `8=FIX` and end with `CheckSum <10>`; anything in
+/// between (e.g. newlines or timestamps) is skipped, and so is a trailing
+/// incomplete message.
+pub fn msg_type_histogram<R: io::Read>(
+    mut reader: R,
+    separator: u8,
+) -> io::Result<HashMap<String, usize>> {
+    let mut histogram = HashMap::new();
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 8192];
+    loop {
+        let n = reader.read(&mut chunk)?;
+        buffer.extend_from_slice(&chunk[..n]);
+        let mut consumed = 0;
+        while let Some((start, end)) = logged_message(&buffer[consumed..], separator) {
+            let message = &buffer[consumed + start..consumed + end];
+            if let Some(msg_type) = peek_msg_type(message, separator) {
+                *histogram.entry(msg_type.to_string()).or_insert(0) += 1;
+            }
+            consumed += end;
+        }
+        buffer.drain(..consumed);
+        if n == 0 {
+            return Ok(histogram);
         }
     }
+}
 
-    fn lookup(&mut self, tag: u32) -> Result<dt::DataType, Self::Error> {
-        // TODO
-        match tag {
-            // `ApplVerID <1128>`
-            1128 => {}
-            // `ApplExtID <1156>`
-            1156 => {
-                return Err(Self::Error::InvalidApplExtID);
-            }
-            // `CstmApplVerID <1129>`
-            1129 => {
-                return Err(Self::Error::InvalidCstmApplVerID);
-            }
-            // `DefaultApplVerID <1137>`
-            1137 => {
-                return Err(Self::Error::InvalidApplExtID);
+/// Returns the position of the first whole message in the log `data`. See
+/// [`msg_type_histogram`].
+fn logged_message(data: &[u8], separator: u8) -> Option<(usize, usize)> {
+    let start = data.windows(5).position(|window| window == b"8=FIX")?;
+    let len = checksum_end(&data[start..], separator)?;
+    Some((start, start + len))
+}
+
+/// Checks `BodyLength <9>` and `CheckSum <10>` of the message in `data`
+/// without decoding any other field, e.g. in gateways that only need to
+/// reject corrupted messages. Both are verified in a single pass over `data`,
+/// which must hold exactly one message delimited by `separator`. The checksum
+/// is calculated as if `separator` were SOH.
+///
+/// ```
+/// use fefix::codec::tagvalue::validate_framing;
+///
+/// let heartbeat = b"8=FIX.4.2|9=42|35=0|49=A|56=B|34=12|52=20100304-07:59:30|10=185|";
+/// assert!(validate_framing(heartbeat, b'|').is_ok());
+/// ```
+pub fn validate_framing(data: &[u8], separator: u8) -> Result<(), DecodeError> {
+    let mut checksum = 0u8;
+    let mut fields = data.split(|byte| *byte == separator);
+    let mut bytes_read = 0;
+    let mut body_end = None;
+    for (i, field) in (&mut fields).enumerate() {
+        let field_len = field.len() + 1;
+        let is_checksum_field = match (i, body_end) {
+            (0, _) if !field.starts_with(b"8=") => return Err(Error::InvalidStandardHeader),
+            (1, _) => {
+                let body_length = field
+                    .strip_prefix(b"9=")
+                    .and_then(|value| str::from_utf8(value).ok())
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .ok_or(Error::InvalidStandardHeader)?;
+                body_end = Some(bytes_read + field_len + body_length);
+                false
             }
-            // `DefaultApplExtID <1407>`
-            1407 => {
-                return Err(Self::Error::InvalidApplExtID);
+            (_, Some(body_end)) => bytes_read == body_end,
+            _ => false,
+        };
+        if is_checksum_field {
+            let actual = field
+                .strip_prefix(b"10=")
+                .filter(|value| value.len() == 3)
+                .and_then(|value| str::from_utf8(value).ok())
+                .and_then(|value| value.parse::<u8>().ok())
+                .ok_or(Error::InvalidStandardTrailer)?;
+            // Nothing but the final separator may follow `CheckSum <10>`.
+            if bytes_read + field_len != data.len() {
+                return Err(Error::InvalidStandardTrailer);
             }
-            // `DefaultCstmApplVerID <1408>`
-            1408 => {
-                return Err(Self::Error::InvalidCstmApplVerID);
+            if actual != checksum {
+                return Err(Error::InvalidChecksum(InvalidChecksum {
+                    expected: checksum,
+                    actual,
+                }));
             }
-            _ => (),
-        };
-        Ok(self
-            .current_dict
-            .field_by_tag(tag)
-            .map(|f| f.basetype())
-            .unwrap_or(DataType::String))
+            return Ok(());
+        }
+        checksum = field
+            .iter()
+            .fold(checksum, |sum, byte| sum.wrapping_add(*byte))
+            .wrapping_add(SOH);
+        bytes_read += field_len;
+        if body_end.is_some_and(|body_end| bytes_read > body_end) {
+            return Err(Error::InvalidStandardTrailer);
+        }
     }
+    Err(Error::InvalidStandardTrailer)
 }
 
+/// Splits the bytes that come from a [reader](std::io::Read) into FIX
+/// messages, e.g. to decode them one by one with a [`Codec`] configured with
+/// the same [`Config`].
+///
+/// Some transports enclose each FIX message in their own framing. The
+/// [`OuterFraming`] of a [`FrameReader`] is stripped before looking for
+/// `BodyLength <9>` and `CheckSum <10>`, so the returned frames only contain
+/// FIX bytes.
 #[derive(Debug)]
-pub enum TagLookupPredeterminedError {
-    InvalidApplVerID,
-    InvalidApplExtID,
-    InvalidCstmApplVerID,
+pub struct FrameReader<R, Z> {
+    reader: R,
+    config: Z,
+    outer_framing: OuterFraming,
+    buffer: Vec<u8>,
+    // Bytes at the start of `buffer` that belong to the last frame.
+    consumed: usize,
+    // Bytes of the current frame that were already searched for a repeated
+    // `BeginString <8>`, so that they aren't searched again after every read.
+    scanned: usize,
+}
+
+impl<R, Z> FrameReader<R, Z>
+where
+    R: io::Read,
+    Z: Config,
+{
+    /// Creates a new [`FrameReader`] for a stream of FIX messages without
+    /// any outer framing.
+    pub fn new(reader: R, config: Z) -> Self {
+        Self::with_outer_framing(reader, config, OuterFraming::None)
+    }
+
+    /// Creates a new [`FrameReader`] that strips `outer_framing` from every
+    /// message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `outer_framing` is an [`OuterFraming::LengthPrefix`] of less
+    /// than 1 or more than 8 bytes.
+    pub fn with_outer_framing(reader: R, config: Z, outer_framing: OuterFraming) -> Self {
+        if let OuterFraming::LengthPrefix(len) = outer_framing {
+            assert!(
+                (1..=8).contains(&len),
+                "length prefixes must be 1 to 8 bytes long, not {}",
+                len
+            );
+        }
+        Self {
+            reader,
+            config,
+            outer_framing,
+            buffer: Vec::new(),
+            consumed: 0,
+            scanned: 0,
+        }
+    }
+
+    /// Reads the next FIX message, without its outer framing. Returns `None`
+    /// once the reader is exhausted on a message boundary; a message that is
+    /// cut short is an [`Error::Eof`].
+    pub fn next_frame(&mut self) -> Result<Option<&[u8]>, FramelessError<DecodeError>> {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+        self.scanned = 0;
+        loop {
+            if let Some((start, end)) = self.frame().map_err(FramelessError::Decoder)? {
+                self.consumed = end;
+                return Ok(Some(&self.buffer[start..end]));
+            }
+            let mut chunk = [0; 1024];
+            let n = self.reader.read(&mut chunk)?;
+            if n == 0 {
+                return if self.buffer.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(FramelessError::Decoder(Error::Eof))
+                };
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Returns the [`Config`] of `self`.
+    pub fn config(&self) -> &Z {
+        &self.config
+    }
+
+    /// Returns the position of the first FIX message in the buffer, if it's
+    /// complete.
+    fn frame(&mut self) -> Result<Option<(usize, usize)>, DecodeError> {
+        let start = match self.outer_framing {
+            OuterFraming::None => 0,
+            OuterFraming::FixedHeader(len) | OuterFraming::LengthPrefix(len) => len,
+        };
+        if self.buffer.len() < start {
+            return Ok(None);
+        }
+        let data = &self.buffer[start..];
+        if self.outer_framing == OuterFraming::None {
+            match next_begin_string(data, Z::SOH_SEPARATOR, self.scanned) {
+                // Only the bytes before the second `BeginString <8>` can hold
+                // the end of the first message.
+                Some(position) => {
+                    self.scanned = position;
+                    if checksum_end(&data[..position], Z::SOH_SEPARATOR).is_none() {
+                        return if Z::SPLIT_ON_REPEATED_BEGIN_STRING {
+                            Ok(Some((start, start + position)))
+                        } else {
+                            Err(Error::RepeatedBeginString)
+                        };
+                    }
+                }
+                // A `BeginString <8>` may still start in the last few bytes.
+                None => self.scanned = data.len().saturating_sub(b"8=FIX".len() - 1),
+            }
+        }
+        match self.outer_framing {
+            OuterFraming::LengthPrefix(len) => {
+                let fix_len = self.buffer[..len]
+                    .iter()
+                    .fold(0usize, |n, byte| (n << 8) | *byte as usize);
+                if data.len() < fix_len {
+                    Ok(None)
+                } else if frame_len::<Z>(&data[..fix_len])? == Some(fix_len) {
+                    Ok(Some((start, start + fix_len)))
+                } else {
+                    Err(Error::InvalidStandardTrailer)
+                }
+            }
+            _ => Ok(frame_len::<Z>(data)?.map(|len| (start, start + len))),
+        }
+    }
+}
+
+/// The framing of a transport around each FIX message, as read by a
+/// [`FrameReader`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OuterFraming {
+    /// FIX messages are sent as they are.
+    None,
+    /// Every FIX message is preceded by a header of this many bytes, which is
+    /// ignored.
+    FixedHeader(usize),
+    /// Every FIX message is preceded by its length in bytes, as a big-endian
+    /// unsigned integer of this many bytes (1 to 8).
+    LengthPrefix(usize),
+}
+
+/// Returns a human-readable representation of `message`, with one
+/// `tag=value (Name)` field per line in the same order as the tag-value
+/// encoder. Field names are looked up in `dict` and omitted for unknown fields.
+/// The entries of repeating groups are indented below their counter field.
+///
+/// This is meant for logs and diffs; the output can't be decoded.
+pub fn pretty(message: &slr::Message, dict: &Dictionary) -> String {
+    let mut output = String::new();
+    let fields = &message.fields;
+    for tag in &[8, 9, 35] {
+        if let Some(value) = fields.get(tag) {
+            pretty_field(&mut output, *tag, value, dict, 0);
+        }
+    }
+    for (tag, value) in fields.iter() {
+        if let 8 | 9 | 10 | 35 = *tag {
+            continue;
+        }
+        pretty_field(&mut output, *tag, value, dict, 0);
+    }
+    if let Some(value) = fields.get(&10) {
+        pretty_field(&mut output, 10, value, dict, 0);
+    }
+    output
+}
+
+fn pretty_field(
+    output: &mut String,
+    tag: i64,
+    value: &slr::FixFieldValue,
+    dict: &Dictionary,
+    depth: usize,
+) {
+    output.push_str(&"  ".repeat(depth));
+    output.push_str(&format!("{}={}", tag, value_text(value)));
+    if let Some(field) = dict.field_by_tag(tag as u32) {
+        output.push_str(&format!(" ({})", field.name()));
+    }
+    output.push('\n');
+    if let slr::FixFieldValue::Group(entries) = value {
+        for entry in entries {
+            for (tag, value) in entry {
+                pretty_field(output, *tag, value, dict, depth + 1);
+            }
+        }
+    }
+}
+
+/// Returns the textual value of `value`, as it would be encoded. Repeating
+/// groups are represented by their number of entries.
+fn value_text(value: &slr::FixFieldValue) -> String {
+    match value {
+        slr::FixFieldValue::String(s) => s.clone(),
+        slr::FixFieldValue::Data(raw_data) => String::from_utf8_lossy(raw_data).into_owned(),
+        slr::FixFieldValue::Group(entries) => entries.len().to_string(),
+        slr::FixFieldValue::MultiValue(values) => values.join(" "),
+        slr::FixFieldValue::Value(field) => field.to_string(),
+    }
+}
+
+/// Returns a CSV row (without line terminator) with the values of the
+/// `columns` tags of `message`, in order. Missing fields are left empty, and
+/// values are quoted if needed.
+///
+/// Repeating groups are rendered as their entries separated by `;`, and each
+/// entry as its `Name=value` fields separated by spaces. Field names are
+/// looked up in `dict` and replaced by tags for unknown fields. Nested groups
+/// are rendered the same way, within square brackets.
+///
+/// ```
+/// use fefix::app::{slr, Version};
+/// use fefix::codec::tagvalue::to_csv_row;
+/// use fefix::Dictionary;
+///
+/// let dict = Dictionary::from_version(Version::Fix44);
+/// let mut message = slr::Message::new();
+/// message.add_str(35, "0");
+/// message.add_str(58, "Hello, world");
+/// assert_eq!(to_csv_row(&message, &[35, 112, 58], &dict), "0,,\"Hello, world\"");
+/// ```
+pub fn to_csv_row(message: &slr::Message, columns: &[u32], dict: &Dictionary) -> String {
+    let cells: Vec<String> = columns
+        .iter()
+        .map(|tag| match message.get_field(*tag) {
+            Some(value) => csv_escape(&csv_value(value, dict)),
+            None => String::new(),
+        })
+        .collect();
+    cells.join(",")
+}
+
+fn csv_value(value: &slr::FixFieldValue, dict: &Dictionary) -> String {
+    let entries = match value {
+        slr::FixFieldValue::Group(entries) => entries,
+        value => return value_text(value),
+    };
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let fields: Vec<String> = entry
+                .iter()
+                .map(|(tag, value)| {
+                    let name = match dict.field_by_tag(*tag as u32) {
+                        Some(field) => field.name().to_string(),
+                        None => tag.to_string(),
+                    };
+                    match value {
+                        slr::FixFieldValue::Group(_) => {
+                            format!("{}=[{}]", name, csv_value(value, dict))
+                        }
+                        value => format!("{}={}", name, value_text(value)),
+                    }
+                })
+                .collect();
+            fields.join(" ")
+        })
+        .collect();
+    entries.join(";")
+}
+
+/// Quotes `cell` if it contains commas, quotes or line breaks, as per RFC 4180.
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// This trait describes dynamic tag lookup logic.
+///
+/// In this context, "tag lookup"
+/// means to search in the dictionary the data type associated with a specific
+/// tag number. This may seem trivial at best, but it can actually be quite
+/// convoluted and require internal state (thus it is "dynamic" tag lookup). In
+/// particular, several fields affect the internal state of a
+/// [`TagLookup`](TagLookup):
+///
+///  - `ApplVerID <1128>`
+///  - `ApplExtID <1156>`
+///  - `CstmApplVerID <1129>`
+///  - `DefaultApplVerID <1137>`
+///  - `DefaultApplExtID <1407>`
+///  - `DefaultCstmApplVerID <1408>`
+///
+/// Each of these fields affects the internal state and thus changes how
+/// subsequent fields (and messages) are interpreted.
+///
+/// # Naming conventions
+/// Implementors of this trait should start with `TagLookup`.
+pub trait TagLookup {
+    type Error: Debug;
+
+    /// Creates a [`TagLookup`] for `dict`, which is shared with the codec
+    /// rather than copied on every message.
+    fn from_dict(dict: &Arc<Dictionary>) -> Self;
+
+    /// Returns the [`BaseType`] of the tag number `tag`.
+    fn lookup(&mut self, tag: u32) -> Result<dt::DataType, Self::Error>;
+}
+
+/// A [`TagLookup`] that only allows a specific revision of the standard, like
+/// most venues do.
+#[derive(Debug)]
+pub struct TagLookupPredetermined {
+    current_dict: Arc<Dictionary>,
+}
+
+impl TagLookup for TagLookupPredetermined {
+    type Error = TagLookupPredeterminedError;
+
+    fn from_dict(dict: &Arc<Dictionary>) -> Self {
+        Self {
+            current_dict: dict.clone(),
+        }
+    }
+
+    fn lookup(&mut self, tag: u32) -> Result<dt::DataType, Self::Error> {
+        // Extension fields are fine as long as the dictionary defines them,
+        // e.g. FIXT.1.1.
+        if let Some(field) = self.current_dict.field_by_tag(tag) {
+            return Ok(field.basetype());
+        }
+        // TODO
+        match tag {
+            // `ApplExtID <1156>`
+            1156 => Err(Self::Error::InvalidApplExtID),
+            // `CstmApplVerID <1129>`
+            1129 => Err(Self::Error::InvalidCstmApplVerID),
+            // `DefaultApplVerID <1137>`
+            1137 => Err(Self::Error::InvalidApplExtID),
+            // `DefaultApplExtID <1407>`
+            1407 => Err(Self::Error::InvalidApplExtID),
+            // `DefaultCstmApplVerID <1408>`
+            1408 => Err(Self::Error::InvalidCstmApplVerID),
+            _ => Ok(DataType::String),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TagLookupPredeterminedError {
+    InvalidApplVerID,
+    InvalidApplExtID,
+    InvalidCstmApplVerID,
 }
 
 #[derive(Debug)]
@@ -397,16 +1340,20 @@ pub enum TypeInfo {
     Data(usize),
 }
 
-struct FieldIter<R, Z: Config> {
+struct FieldIter<'a, R, Z: Config> {
     handle: R,
     is_last: bool,
-    data_length: u32,
+    /// The value of the previous field, if it was an integer. Data fields
+    /// take their length from it.
+    data_length: Option<u32>,
     designator: Z::TagLookup,
+    dict: &'a Dictionary,
+    fields: &'a FieldCache,
 }
 
-impl<'d, R, Z> Iterator for &mut FieldIter<&'d mut R, Z>
+impl<'a, 'd, R, Z> Iterator for &mut FieldIter<'a, &'d mut R, Z>
 where
-    R: io::Read,
+    R: io::BufRead,
     Z: Config,
 {
     type Item = Result<slr::Field, DecodeError>;
@@ -415,7 +1362,7 @@ where
         if self.is_last {
             return None;
         }
-        let mut buffer: Vec<u8> = Vec::new();
+        let mut buffer: Vec<u8>;
         let mut tag: u32 = 0;
         let mut buf = [0];
         loop {
@@ -433,14 +1380,37 @@ where
         } else if tag == 0 {
             return None;
         }
-        let datatype = self.designator.lookup(tag as u32);
+        let datatype = match self.designator.lookup(tag as u32) {
+            Ok(datatype) => datatype,
+            // Newer extension fields (e.g. `ApplExtID <1156>`) are preserved as
+            // plain strings, unless in strict mode.
+            Err(_) if !Z::STRICT_EXTENSION_FIELDS && is_extension_field(tag) => DataType::String,
+            Err(_) => return Some(Err(Error::ExtensionField(tag))),
+        };
         match datatype {
-            Ok(DataType::Data) => {
-                buffer = vec![0u8; self.data_length as usize];
-                self.handle.read_exact(&mut buffer).unwrap();
-                self.handle.read_exact(&mut buffer[0..1]).unwrap();
-            }
-            Ok(_basetype) => {
+            DataType::Data => match self.data_length {
+                Some(data_length) => {
+                    buffer = vec![0u8; data_length as usize];
+                    self.handle.read_exact(&mut buffer).unwrap();
+                    // Skip the separator.
+                    self.handle.read_exact(&mut buf).unwrap();
+                }
+                None if Z::BEST_EFFORT_DATA => {
+                    let rest = match self.handle.fill_buf() {
+                        Ok(rest) => rest,
+                        Err(err) => return Some(Err(err.into())),
+                    };
+                    let len = match best_effort_data_len(rest, Z::SOH_SEPARATOR, self.dict) {
+                        Some(len) => len,
+                        None => return Some(Err(Error::Eof)),
+                    };
+                    buffer = rest[..len].to_vec();
+                    // Skip the separator, too.
+                    self.handle.consume(len + 1);
+                }
+                None => return Some(Err(Error::DataWithoutLength(tag))),
+            },
+            basetype => {
                 buffer = vec![];
                 loop {
                     if self.handle.read(&mut buf).unwrap() == 0 {
@@ -453,50 +1423,182 @@ where
                         buffer.push(byte);
                     }
                 }
+                // Only length-prefixed data fields may contain NUL and SOH.
+                if Z::VALIDATE_TYPES && buffer.iter().any(|byte| *byte == 0 || *byte == SOH) {
+                    return Some(Err(Error::InvalidData));
+                }
+                if Z::VALIDATE_TYPES && basetype.max_len().is_some_and(|n| buffer.len() > n) {
+                    return Some(Err(Error::InvalidData));
+                }
+                if Z::VALIDATE_TYPES
+                    && basetype == DataType::Tenor
+                    && dt::Tenor::parse(&buffer[..]).is_none()
+                {
+                    return Some(Err(Error::InvalidData));
+                }
             }
-            Err(_) => (),
         };
-        let datatype = datatype.unwrap();
-        let field_value = field_value(datatype, &buffer[..]).unwrap();
-        if let slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(l))) = field_value {
-            self.data_length = l as u32;
-        }
+        // `SeqNum`, `Length` etc. are decoded as plain integers, so their
+        // signedness is only known to the dictionary.
+        let is_unsigned = self
+            .fields
+            .get(self.dict, tag)
+            .map_or(datatype.is_unsigned(), |field| {
+                field.exact_data_type.is_unsigned()
+            });
+        let field_value = match field_value(datatype, is_unsigned, &buffer[..]) {
+            Ok(field_value) => field_value,
+            Err(err) => return Some(Err(err)),
+        };
+        self.data_length = match field_value {
+            slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(l))) => Some(l as u32),
+            _ => None,
+        };
         Some(Ok(slr::Field::new(tag, field_value)))
     }
 }
 
-fn field_value(datatype: DataType, buf: &[u8]) -> Result<slr::FixFieldValue, Error> {
+/// Returns the length of the first message in `data`; see [`Codec::frame_len`].
+fn frame_len<Z: Config>(data: &[u8]) -> Result<Option<usize>, DecodeError> {
+    let separator = Z::SOH_SEPARATOR;
+    let begin_string_end = match data.iter().position(|byte| *byte == separator) {
+        Some(i) => i + 1,
+        None => return Ok(None),
+    };
+    let rest = &data[begin_string_end..];
+    // Not enough bytes yet to tell whether `BodyLength <9>` follows.
+    if rest.len() < 2 {
+        return Ok(None);
+    }
+    let (body_start, body_length) = if rest.starts_with(b"9=") {
+        let end = match rest.iter().position(|byte| *byte == separator) {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+        let body_length = str::from_utf8(&rest[2..end])
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .ok_or(Error::InvalidStandardHeader)?;
+        (begin_string_end + end + 1, body_length)
+    } else {
+        (begin_string_end, 0)
+    };
+    if body_length > 0 {
+        // `10=NNN` and its separator.
+        let end = body_start + body_length + 7;
+        if data.len() < end {
+            Ok(None)
+        } else if data[body_start + body_length..].starts_with(b"10=") {
+            Ok(Some(end))
+        } else {
+            Err(Error::InvalidStandardTrailer)
+        }
+    } else if Z::LENIENT_BODY_LENGTH {
+        Ok(checksum_end(&data[body_start..], separator).map(|n| body_start + n))
+    } else {
+        Err(Error::InvalidStandardHeader)
+    }
+}
+
+/// Returns the length of `bytes` up to and including the separator after the
+/// first `CheckSum <10>` field, if any. See [`Config::LENIENT_BODY_LENGTH`].
+fn checksum_end(bytes: &[u8], separator: u8) -> Option<usize> {
+    let start = (0..bytes.len())
+        .find(|i| bytes[*i] == separator && bytes[i + 1..].starts_with(b"10="))?
+        + 1;
+    bytes[start..]
+        .iter()
+        .position(|byte| *byte == separator)
+        .map(|n| start + n + 1)
+}
+
+/// Returns the position of the first `BeginString <8>` in `bytes` after the
+/// one at the start, searching from position `from` onwards. See
+/// [`Config::SPLIT_ON_REPEATED_BEGIN_STRING`].
+fn next_begin_string(bytes: &[u8], separator: u8, from: usize) -> Option<usize> {
+    (from.max(1)..bytes.len())
+        .find(|i| bytes[i - 1] == separator && bytes[*i..].starts_with(b"8=FIX"))
+}
+
+/// Returns the length of the data field value at the start of `bytes`, which
+/// is assumed to extend up to the first separator that is followed by a tag
+/// known to `dict`. See [`Config::BEST_EFFORT_DATA`].
+fn best_effort_data_len(bytes: &[u8], separator: u8, dict: &Dictionary) -> Option<usize> {
+    let is_followed_by_known_tag = |i: usize| {
+        let rest = &bytes[i + 1..];
+        let digits = rest.iter().take_while(|byte| byte.is_ascii_digit()).count();
+        let tag = str::from_utf8(&rest[..digits])
+            .ok()
+            .and_then(|tag| tag.parse().ok());
+        rest.get(digits) == Some(&b'=') && tag.is_some_and(|tag| dict.field_by_tag(tag).is_some())
+    };
+    (0..bytes.len()).find(|i| bytes[*i] == separator && is_followed_by_known_tag(*i))
+}
+
+fn field_value(
+    datatype: DataType,
+    is_unsigned: bool,
+    buf: &[u8],
+) -> Result<slr::FixFieldValue, Error> {
     debug_assert!(!buf.is_empty());
     Ok(match datatype {
-        DataType::Char => slr::FixFieldValue::from(buf[0] as char),
-        DataType::String => {
+        DataType::Char | DataType::Boolean => slr::FixFieldValue::from(buf[0] as char),
+        // Strings with invalid UTF-8 are kept as raw bytes, so that relays
+        // can decide what to do with them.
+        DataType::String => match str::from_utf8(buf) {
+            Ok(s) => slr::FixFieldValue::String(s.to_string()),
+            Err(_) => slr::FixFieldValue::Data(buf.to_vec()),
+        },
+        // Tenors are validated, if at all, by `FieldIter`. Prices and
+        // quantities are kept verbatim, so that they never lose precision.
+        DataType::Tenor | DataType::Price | DataType::Qty => {
             slr::FixFieldValue::String(str::from_utf8(buf).map_err(|_| Error::Syntax)?.to_string())
         }
         DataType::Data => slr::FixFieldValue::Data(buf.to_vec()),
+        DataType::MultipleStringValue | DataType::MultipleCharValue => {
+            slr::FixFieldValue::MultiValue(
+                str::from_utf8(buf)
+                    .map_err(|_| Error::Syntax)?
+                    .split(' ')
+                    .map(|value| value.to_string())
+                    .collect(),
+            )
+        }
         DataType::Float => slr::FixFieldValue::Value(dt::DataTypeValue::Float(dt::Float::from(
             str::from_utf8(buf)
                 .map_err(|_| Error::Syntax)?
                 .parse::<f32>()
                 .map_err(|_| Error::Syntax)?,
         ))),
-        DataType::Int => {
+        DataType::Int | DataType::NumInGroup => {
+            // A sign is only allowed at the very start, and only if signed.
+            let (sign, digits) = match buf[0] {
+                b'-' if !is_unsigned => (-1, &buf[1..]),
+                b'+' if !is_unsigned => (1, &buf[1..]),
+                _ => (1, buf),
+            };
+            if digits.is_empty() {
+                return Err(Error::InvalidData);
+            }
             let mut n: i64 = 0;
-            for byte in buf {
-                if *byte >= '0' as u8 && *byte <= '9' as u8 {
-                    let digit = byte - '0' as u8;
-                    n = n * 10 + digit as i64;
-                } else if *byte == '-' as u8 {
-                    n *= -1;
-                } else if *byte != '+' as u8 {
-                    return Err(Error::Syntax);
+            for byte in digits {
+                if !byte.is_ascii_digit() {
+                    return Err(Error::InvalidData);
                 }
+                n = n
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add((byte - b'0') as i64))
+                    .ok_or(Error::InvalidData)?;
             }
-            slr::FixFieldValue::from(n)
+            slr::FixFieldValue::from(sign * n)
         }
         _ => return Err(Error::Syntax),
     })
 }
 
+/// The standard FIX field delimiter, ASCII 0x1.
+const SOH: u8 = 0x1;
+
 /// The [`Config`](Config) pattern allows deep customization of encoding
 /// and decoding behavior without relying on runtime settings. By using this
 /// trait and specializing the behavior of particular methods, users can change
@@ -512,7 +1614,69 @@ pub trait Config: Clone {
     /// the last one.
     ///
     /// ASCII 0x1 is the default SOH separator character.
-    const SOH_SEPARATOR: u8 = 0x1;
+    const SOH_SEPARATOR: u8 = SOH;
+
+    /// Whether to collect a [`DecodeWarning`] for every field that the
+    /// dictionary marks as deprecated. Disabled by default.
+    const WARN_ON_DEPRECATED_FIELDS: bool = false;
+
+    /// Whether to check that field values conform to their datatype, e.g.
+    /// that non-data fields don't contain NUL or SOH bytes and that values
+    /// don't exceed the maximum length of their datatype (see
+    /// [`DataType::max_len`]). Disabled by default.
+    ///
+    /// Integers are always checked: they must be made of digits only, with an
+    /// optional leading "+" or "-" if signed (see [`DataType::is_unsigned`]).
+    const VALIDATE_TYPES: bool = false;
+
+    /// Whether to reject extension fields that were introduced by FIXT.1.1
+    /// (e.g. `ApplExtID <1156>`) and that the dictionary doesn't support.
+    /// Otherwise they're preserved as plain strings, which is the default.
+    const STRICT_EXTENSION_FIELDS: bool = false;
+
+    /// Whether to decode data fields that are not preceded by their length
+    /// field, as sent by some nonstandard feeds. Their value then extends up to
+    /// the first separator that is followed by a known tag. Disabled by
+    /// default, see [`Error::DataWithoutLength`] for the risks involved.
+    const BEST_EFFORT_DATA: bool = false;
+
+    /// Whether to tolerate a zero or missing `BodyLength <9>`, as sent by
+    /// some tools. Messages are then delimited by scanning for the
+    /// `CheckSum <10>` field instead. Disabled by default.
+    const LENIENT_BODY_LENGTH: bool = false;
+
+    /// Whether to accept messages that end right after the value of
+    /// `CheckSum <10>`, without the final separator, as found in some
+    /// buffers. Otherwise decoding such a message fails with [`Error::Eof`],
+    /// which is the default.
+    const LENIENT_TRAILING_SOH: bool = false;
+
+    /// Whether to accept a `CheckSum <10>` value that isn't made of exactly
+    /// three digits, e.g. `10=5` instead of `10=005`, as sent by some tools.
+    /// Otherwise that's an [`Error::InvalidStandardTrailer`], which is the
+    /// default. Encoded checksums are always zero-padded to three digits.
+    const LENIENT_CHECKSUM_FORMAT: bool = false;
+
+    /// Whether [`FrameReader`] should treat a second `BeginString <8>` before
+    /// the `CheckSum <10>` of a message (as found in malformed or concatenated
+    /// buffers) as the start of the next message. The message is then cut
+    /// short right before it, and decoding it will fail. Otherwise that's an
+    /// [`Error::RepeatedBeginString`], which is the default. Messages with
+    /// [`OuterFraming`] are always delimited by it instead.
+    const SPLIT_ON_REPEATED_BEGIN_STRING: bool = false;
+
+    /// Whether to terminate encoded messages with a separator after the value
+    /// of `CheckSum <10>`, as required on the wire. Storage formats may want to
+    /// omit it. Enabled by default.
+    const TRAILING_SOH: bool = true;
+
+    /// The maximum number of entries that a repeating group can declare
+    /// (e.g. in `NoMDEntries <268>`) before decoding fails with
+    /// [`Error::GroupTooLarge`]. Unlimited by default.
+    #[inline(always)]
+    fn max_group_entries(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// A [`Config`] for [`Codec`] with default configuration
@@ -540,7 +1704,7 @@ impl Config for ConfigVerticalSlash {
     const SOH_SEPARATOR: u8 = '|' as u8;
 }
 
-/// A [`Config`](Config) for [`Codec`] with `^` (ASCII 0x5F)
+/// A [`Config`](Config) for [`Codec`] with `^` (ASCII 0x5E)
 /// as a field separator.
 #[derive(Debug, Clone)]
 pub struct ConfigCaret;
@@ -552,6 +1716,33 @@ impl Config for ConfigCaret {
     const SOH_SEPARATOR: u8 = '^' as u8;
 }
 
+/// A [`Config`](Config) for [`Codec`] with `\t` (ASCII 0x9)
+/// as a field separator, as found in the output of some logging tools.
+#[derive(Debug, Clone)]
+pub struct ConfigTab;
+
+impl Config for ConfigTab {
+    type ChecksumAlgo = ChecksumAlgoDefault;
+    type TagLookup = TagLookupPredetermined;
+
+    const SOH_SEPARATOR: u8 = '\t' as u8;
+}
+
+/// Callbacks for [`Codec::decode_visit`], which decodes messages without
+/// building them in memory.
+pub trait FieldVisitor {
+    /// Called for every field, in order of appearance. Group counter fields
+    /// (e.g. `NoMDEntries <268>`) are visited right before their group starts.
+    fn on_field(&mut self, tag: u32, value: &slr::FixFieldValue);
+
+    /// Called when the repeating group counted by the field with tag
+    /// `count_tag` starts. `n` is its declared number of entries.
+    fn on_group_start(&mut self, _count_tag: u32, _n: usize) {}
+
+    /// Called after the last field of the innermost repeating group.
+    fn on_group_end(&mut self) {}
+}
+
 /// Checksum calculation & verification algorithm. The API is designed to work
 /// only with so-called "rolling" checksum algorithms, much like the one used by
 /// the FIX tag-value encoding.
@@ -647,6 +1838,24 @@ pub enum Error {
     InvalidStandardTrailer,
     InvalidChecksum(InvalidChecksum),
     Syntax,
+    /// A field value doesn't conform to its datatype.
+    InvalidData,
+    /// A repeating group declares more entries than allowed by
+    /// [`Config::max_group_entries`].
+    GroupTooLarge,
+    /// An extension field (e.g. `ApplExtID <1156>`) is not supported. See
+    /// [`Config::STRICT_EXTENSION_FIELDS`].
+    ExtensionField(u32),
+    /// The data field with this tag is not preceded by its length field.
+    ///
+    /// [`Config::BEST_EFFORT_DATA`] allows decoding such fields anyway, but
+    /// there's no way to tell where their value really ends. Binary data that
+    /// happens to contain a separator followed by a known tag (e.g. `|35=`)
+    /// is cut short, and the rest of it is misread as other fields.
+    DataWithoutLength(u32),
+    /// A second `BeginString <8>` was found before `CheckSum <10>`. See
+    /// [`Config::SPLIT_ON_REPEATED_BEGIN_STRING`].
+    RepeatedBeginString,
 }
 
 impl fmt::Display for Error {
@@ -667,12 +1876,54 @@ impl From<io::Error> for Error {
     }
 }
 
+/// The error type returned by [`Codec::verify_roundtrip`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum RoundtripError {
+    /// The original message couldn't be decoded.
+    Decode(DecodeError),
+    /// The decoded message couldn't be encoded again.
+    Encode(EncodeError),
+    /// The encoded message differs from the original message, starting at
+    /// this byte offset.
+    Mismatch(usize),
+}
+
+impl fmt::Display for RoundtripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(err) => write!(f, "Decoding failed: {}", err),
+            Self::Encode(err) => write!(f, "Encoding failed: {}", err),
+            Self::Mismatch(position) => {
+                write!(f, "Round trip mismatch at byte {}", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RoundtripError {}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct InvalidChecksum {
     pub expected: u8,
     pub actual: u8,
 }
 
+/// A non-fatal anomaly detected while decoding a message.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DecodeWarning {
+    /// The message contains a field with this tag, which is deprecated in the
+    /// FIX version of the dictionary.
+    DeprecatedField(u32),
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::DeprecatedField(tag) => write!(f, "Deprecated field <{}>", tag),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -701,63 +1952,168 @@ mod test {
         Codec::new(ConfigVerticalSlashNoVerify)
     }
 
-    fn with_soh(msg: &str) -> String {
-        msg.split("|").collect::<Vec<&str>>().join("\x01")
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashWarnings;
+
+    impl Config for ConfigVerticalSlashWarnings {
+        type ChecksumAlgo = ChecksumAlgoLazy;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const WARN_ON_DEPRECATED_FIELDS: bool = true;
     }
 
-    #[test]
-    fn can_parse_simple_message() {
-        let msg = with_soh("8=FIX.4.2|9=251|35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|10=050|");
-        let mut codec = encoder_with_soh();
-        let result = codec.decode(&mut msg.as_bytes());
-        assert!(result.is_ok());
+    fn encoder_slash_with_warnings() -> Codec<slr::Message, impl Config> {
+        Codec::new(ConfigVerticalSlashWarnings)
     }
 
-    const RANDOM_MESSAGES: &[&str] = &[
-        "8=FIX.4.2|9=42|35=0|49=A|56=B|34=12|52=20100304-07:59:30|10=185|",
-        "8=FIX.4.2|9=97|35=6|49=BKR|56=IM|34=14|52=20100204-09:18:42|23=115685|28=N|55=SPMI.MI|54=2|44=2200.75|27=S|25=H|10=248|",
-        "8=FIX.4.4|9=117|35=AD|34=2|49=A|50=1|52=20100219-14:33:32.258|56=B|57=M|263=1|568=1|569=0|580=1|75=20100218|60=20100218-00:00:00.000|10=202|",
-        "8=FIX.4.4|9=94|35=3|34=214|49=A|50=U1|52=20100304-09:42:23.130|56=AB|128=B1|45=176|58=txt|371=15|372=X|373=1|10=058|",
-        "8=FIX.4.4|9=70|35=4|49=A|56=XYZ|34=129|52=20100302-19:38:21|43=Y|57=LOL|123=Y|36=175|10=192|",
-        "8=FIX.4.4|9=122|35=D|34=215|49=CLIENT12|52=20100225-19:41:57.316|56=B|1=Marcel|11=13346|21=1|40=2|44=5|54=1|59=0|60=20100225-19:39:52.020|10=072|",
-        "8=FIX.4.2|9=196|35=X|49=A|56=B|34=12|52=20100318-03:21:11.364|262=A|268=2|279=0|269=0|278=BID|55=EUR/USD|270=1.37215|15=EUR|271=2500000|346=1|279=0|269=1|278=OFFER|55=EUR/USD|270=1.37224|15=EUR|271=2503200|346=1|10=171|",
-    ];
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashValidateTypes;
 
-    #[test]
-    fn assortment_of_random_messages_is_ok() {
-        for msg_with_vertical_bar in RANDOM_MESSAGES {
-            let msg = with_soh(msg_with_vertical_bar);
-            let mut codec = encoder_with_soh();
-            let result = codec.decode(&mut msg.as_bytes());
-            assert!(result.is_ok());
-        }
-    }
+    impl Config for ConfigVerticalSlashValidateTypes {
+        type ChecksumAlgo = ChecksumAlgoLazy;
+        type TagLookup = TagLookupPredetermined;
 
-    #[test]
-    fn heartbeat_message_fields_are_ok() {
-        let mut codec = encoder_slash_no_verify();
-        let message = codec.decode(&mut RANDOM_MESSAGES[0].as_bytes()).unwrap();
-        assert_eq!(
-            message.get_field(8),
-            Some(&slr::FixFieldValue::String("FIX.4.2".to_string()))
-        );
-        assert_eq!(message.get_field(9), Some(&slr::FixFieldValue::from(42i64)));
-        assert_eq!(
-            message.get_field(35),
-            Some(&slr::FixFieldValue::String("0".to_string()))
-        );
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const VALIDATE_TYPES: bool = true;
     }
 
-    #[test]
-    fn new_order_single_without_final_separator() {
-        let msg = "8=FIX.4.4|9=122|35=D|34=215|49=CLIENT12|52=20100225-19:41:57.316|56=B|1=Marcel|11=13346|21=1|40=2|44=5|54=1|59=0|60=20100225-19:39:52.020|10=072";
-        let mut codec = encoder();
-        let result = codec.decode(&mut msg.as_bytes());
-        assert_eq!(result, Err(Error::Eof));
+    fn encoder_slash_validate_types() -> Codec<slr::Message, impl Config> {
+        Codec::new(ConfigVerticalSlashValidateTypes)
     }
 
-    #[test]
-    fn message_must_end_with_separator() {
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashMaxGroupEntries(usize);
+
+    impl Config for ConfigVerticalSlashMaxGroupEntries {
+        type ChecksumAlgo = ChecksumAlgoLazy;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+
+        fn max_group_entries(&self) -> Option<usize> {
+            Some(self.0)
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashLenientBodyLength;
+
+    impl Config for ConfigVerticalSlashLenientBodyLength {
+        type ChecksumAlgo = ChecksumAlgoLazy;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const LENIENT_BODY_LENGTH: bool = true;
+    }
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashLenientTrailingSoh;
+
+    impl Config for ConfigVerticalSlashLenientTrailingSoh {
+        type ChecksumAlgo = ChecksumAlgoDefault;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const LENIENT_TRAILING_SOH: bool = true;
+    }
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashSplitOnRepeatedBeginString;
+
+    impl Config for ConfigVerticalSlashSplitOnRepeatedBeginString {
+        type ChecksumAlgo = ChecksumAlgoDefault;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const SPLIT_ON_REPEATED_BEGIN_STRING: bool = true;
+    }
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashLenientChecksumFormat;
+
+    impl Config for ConfigVerticalSlashLenientChecksumFormat {
+        type ChecksumAlgo = ChecksumAlgoDefault;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const LENIENT_CHECKSUM_FORMAT: bool = true;
+    }
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashNoTrailingSoh;
+
+    impl Config for ConfigVerticalSlashNoTrailingSoh {
+        type ChecksumAlgo = ChecksumAlgoDefault;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const TRAILING_SOH: bool = false;
+    }
+
+    fn with_soh(msg: &str) -> String {
+        msg.split("|").collect::<Vec<&str>>().join("\x01")
+    }
+
+    #[test]
+    fn codec_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Codec<slr::Message, ConfigDefault>>();
+    }
+
+    #[test]
+    fn can_parse_simple_message() {
+        let msg = with_soh("8=FIX.4.2|9=251|35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|10=050|");
+        let mut codec = encoder_with_soh();
+        let result = codec.decode(&mut msg.as_bytes());
+        assert!(result.is_ok());
+    }
+
+    const RANDOM_MESSAGES: &[&str] = &[
+        "8=FIX.4.2|9=42|35=0|49=A|56=B|34=12|52=20100304-07:59:30|10=185|",
+        "8=FIX.4.2|9=97|35=6|49=BKR|56=IM|34=14|52=20100204-09:18:42|23=115685|28=N|55=SPMI.MI|54=2|44=2200.75|27=S|25=H|10=248|",
+        "8=FIX.4.4|9=117|35=AD|34=2|49=A|50=1|52=20100219-14:33:32.258|56=B|57=M|263=1|568=1|569=0|580=1|75=20100218|60=20100218-00:00:00.000|10=202|",
+        "8=FIX.4.4|9=94|35=3|34=214|49=A|50=U1|52=20100304-09:42:23.130|56=AB|128=B1|45=176|58=txt|371=15|372=X|373=1|10=058|",
+        "8=FIX.4.4|9=70|35=4|49=A|56=XYZ|34=129|52=20100302-19:38:21|43=Y|57=LOL|123=Y|36=175|10=192|",
+        "8=FIX.4.4|9=122|35=D|34=215|49=CLIENT12|52=20100225-19:41:57.316|56=B|1=Marcel|11=13346|21=1|40=2|44=5|54=1|59=0|60=20100225-19:39:52.020|10=072|",
+        "8=FIX.4.2|9=196|35=X|49=A|56=B|34=12|52=20100318-03:21:11.364|262=A|268=2|279=0|269=0|278=BID|55=EUR/USD|270=1.37215|15=EUR|271=2500000|346=1|279=0|269=1|278=OFFER|55=EUR/USD|270=1.37224|15=EUR|271=2503200|346=1|10=171|",
+    ];
+
+    #[test]
+    fn assortment_of_random_messages_is_ok() {
+        for msg_with_vertical_bar in RANDOM_MESSAGES {
+            let msg = with_soh(msg_with_vertical_bar);
+            let mut codec = encoder_with_soh();
+            let result = codec.decode(&mut msg.as_bytes());
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn heartbeat_message_fields_are_ok() {
+        let mut codec = encoder_slash_no_verify();
+        let message = codec.decode(&mut RANDOM_MESSAGES[0].as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(8),
+            Some(&slr::FixFieldValue::String("FIX.4.2".to_string()))
+        );
+        assert_eq!(message.get_field(9), Some(&slr::FixFieldValue::from(42i64)));
+        assert_eq!(
+            message.get_field(35),
+            Some(&slr::FixFieldValue::String("0".to_string()))
+        );
+    }
+
+    #[test]
+    fn new_order_single_without_final_separator() {
+        let msg = "8=FIX.4.4|9=122|35=D|34=215|49=CLIENT12|52=20100225-19:41:57.316|56=B|1=Marcel|11=13346|21=1|40=2|44=5|54=1|59=0|60=20100225-19:39:52.020|10=072";
+        let mut codec = encoder();
+        let result = codec.decode(&mut msg.as_bytes());
+        assert_eq!(result, Err(Error::Eof));
+    }
+
+    #[test]
+    fn message_must_end_with_separator() {
         let msg = "8=FIX.4.2|9=251|35=D|49=AFUNDMGR|56=ABROKERt|15=USD|59=0|10=127";
         let mut codec = encoder();
         let result = codec.decode(&mut msg.as_bytes());
@@ -780,6 +2136,145 @@ mod test {
         assert_eq!(result, Err(Error::InvalidStandardHeader));
     }
 
+    #[test]
+    fn vertical_slash_message_with_soh_checksum_is_ok() {
+        let mut codec = encoder();
+        let message = codec.decode(&mut RANDOM_MESSAGES[0].as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(10),
+            Some(&slr::FixFieldValue::String("185".to_string()))
+        );
+    }
+
+    #[test]
+    fn message_without_trailing_soh() {
+        let msg = RANDOM_MESSAGES[0];
+        let without_trailing_soh = &msg[..msg.len() - 1];
+        let mut codec = encoder();
+        assert!(codec.decode(msg.as_bytes()).is_ok());
+        assert_eq!(
+            codec.decode(without_trailing_soh.as_bytes()),
+            Err(Error::Eof)
+        );
+        let mut codec = Codec::<slr::Message, _>::new(ConfigVerticalSlashLenientTrailingSoh);
+        let expected = codec.decode(msg.as_bytes()).unwrap().clone();
+        let message = codec.decode(without_trailing_soh.as_bytes()).unwrap();
+        assert_eq!(message, &expected);
+        assert_eq!(
+            message.get_field(10),
+            Some(&slr::FixFieldValue::String("185".to_string()))
+        );
+    }
+
+    #[test]
+    fn fixt_message_with_appl_ver_id() {
+        let msg =
+            "8=FIXT.1.1|9=57|35=AE|49=A|56=B|34=1|52=20100304-07:59:30|1128=7|970=100|10=227|";
+        let mut codec = encoder();
+        // `PositionLimit <970>` is unknown to FIX 4.4.
+        let message = codec.decode(msg.as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(970),
+            Some(&slr::FixFieldValue::String("100".to_string()))
+        );
+        let mut dicts = HashMap::new();
+        dicts.insert("7".to_string(), Dictionary::from_version(Version::Fix50));
+        codec.set_appl_ver_dicts(dicts);
+        let message = codec.decode(msg.as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(970),
+            Some(&slr::FixFieldValue::from(100i64))
+        );
+        // Other messages are still decoded with the FIX 4.4 dictionary.
+        assert!(codec.decode(RANDOM_MESSAGES[0].as_bytes()).is_ok());
+        let other_appl_ver_id = msg.replace("1128=7", "1128=8").replace("10=227", "10=228");
+        let message = codec.decode(other_appl_ver_id.as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(970),
+            Some(&slr::FixFieldValue::String("100".to_string()))
+        );
+    }
+
+    #[test]
+    fn checksum_is_zero_padded() {
+        let msg = "8=FIX.4.2|9=42|35=0|49=g|56=h|34=12|52=20100304-07:59:30|10=005|";
+        let mut codec = encoder();
+        let message = codec.decode(msg.as_bytes()).unwrap().clone();
+        let encoded = codec.encode_to_vec(&message).unwrap();
+        assert!(encoded.ends_with(b"|10=005|"));
+        let message = codec.decode(RANDOM_MESSAGES[0].as_bytes()).unwrap().clone();
+        let encoded = codec.encode_to_vec(&message).unwrap();
+        assert!(encoded.ends_with(b"|10=185|"));
+    }
+
+    #[test]
+    fn string_with_invalid_utf8_is_kept_as_bytes() {
+        let msg = b"8=FIX.4.2|9=44|35=0|49=A|56=B|34=12|52=20100304-07:59:30|58=\xe9|10=000|";
+        let mut codec = encoder_slash_no_verify();
+        let message = codec.decode(&msg[..]).unwrap();
+        assert_eq!(
+            message.get_field(58),
+            Some(&slr::FixFieldValue::Data(vec![0xe9]))
+        );
+    }
+
+    #[test]
+    fn checksum_without_zero_padding() {
+        let msg = "8=FIX.4.2|9=42|35=0|49=g|56=h|34=12|52=20100304-07:59:30|10=5|";
+        assert_eq!(
+            encoder().decode(msg.as_bytes()),
+            Err(Error::InvalidStandardTrailer)
+        );
+        let mut codec = Codec::<slr::Message, _>::new(ConfigVerticalSlashLenientChecksumFormat);
+        let message = codec.decode(msg.as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(10),
+            Some(&slr::FixFieldValue::String("5".to_string()))
+        );
+        // Malformed checksums are never accepted.
+        let msg = msg.replace("10=5", "10=x5");
+        assert_eq!(
+            codec.decode(msg.as_bytes()),
+            Err(Error::InvalidStandardTrailer)
+        );
+    }
+
+    fn decode_with_separator<Z: Config>(config: Z, separator: char) -> slr::Message {
+        // `RawData <96>` contains SOH and '=', which must be skipped thanks to
+        // `RawDataLength <95>` rather than parsed.
+        let fields = [
+            "8=FIX.4.2",
+            "9=56",
+            "35=0",
+            "49=A",
+            "56=B",
+            "34=12",
+            "52=20100304-07:59:30",
+            "95=5",
+            "96=a\x01b=c",
+            "10=176",
+        ];
+        let mut msg = String::new();
+        for field in fields.iter() {
+            msg.push_str(field);
+            msg.push(separator);
+        }
+        let mut codec = Codec::<slr::Message, _>::new(config);
+        codec.decode(msg.as_bytes()).unwrap().clone()
+    }
+
+    #[test]
+    fn same_message_with_different_separators() {
+        let message = decode_with_separator(ConfigDefault, '\x01');
+        assert_eq!(
+            message.get_field(96),
+            Some(&slr::FixFieldValue::Data(b"a\x01b=c".to_vec()))
+        );
+        assert_eq!(decode_with_separator(ConfigVerticalSlash, '|'), message);
+        assert_eq!(decode_with_separator(ConfigCaret, '^'), message);
+        assert_eq!(decode_with_separator(ConfigTab, '\t'), message);
+    }
+
     #[test]
     fn detect_incorrect_checksum() {
         let msg = "8=FIX.4.2|9=251|35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|10=146|";
@@ -790,4 +2285,838 @@ mod test {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn deprecated_field_emits_warning() {
+        let msg = "8=FIX.4.4|9=40|35=D|49=AFUNDMGR|56=ABROKER|239=CASH|15=USD|10=000|";
+        let mut codec = encoder_slash_with_warnings();
+        codec.decode(&mut msg.as_bytes()).unwrap();
+        assert_eq!(codec.warnings(), &[DecodeWarning::DeprecatedField(239)]);
+    }
+
+    #[test]
+    fn set_dict_invalidates_cached_fields() {
+        let msg = "8=FIX.4.4|9=40|35=D|49=AFUNDMGR|56=ABROKER|24=C|239=CASH|15=USD|10=000|";
+        let mut codec = encoder_slash_with_warnings();
+        codec.decode(&mut msg.as_bytes()).unwrap();
+        assert_eq!(codec.warnings(), &[DecodeWarning::DeprecatedField(239)]);
+        codec.set_dict(Dictionary::from_version(Version::Fix42));
+        codec.decode(&mut msg.as_bytes()).unwrap();
+        assert_eq!(codec.warnings(), &[DecodeWarning::DeprecatedField(24)]);
+    }
+
+    #[test]
+    fn deprecated_field_warnings_are_disabled_by_default() {
+        let msg = "8=FIX.4.4|9=40|35=D|49=AFUNDMGR|56=ABROKER|239=CASH|15=USD|10=000|";
+        let mut codec = encoder_slash_no_verify();
+        codec.decode(&mut msg.as_bytes()).unwrap();
+        assert!(codec.warnings().is_empty());
+    }
+
+    #[test]
+    fn encode_with_pool_reuses_buffers() {
+        use crate::utils::{BufferPool, VecPool};
+
+        let mut codec = encoder();
+        let mut pool = VecPool::new();
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "0");
+        let buffer = codec.encode_with_pool(&mut pool, &message).unwrap();
+        let ptr = buffer.as_ptr();
+        pool.release(buffer);
+        let buffer = codec.encode_with_pool(&mut pool, &message).unwrap();
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert_eq!(pool.allocations(), 1);
+    }
+
+    #[test]
+    fn multi_value_field_round_trip() {
+        let msg = "8=FIX.4.4|9=34|35=D|49=AFUNDMGR|56=ABROKER|18=1 6|10=000|";
+        let mut codec = encoder_slash_no_verify();
+        let message = codec.decode(&mut msg.as_bytes()).unwrap().clone();
+        assert_eq!(
+            message.get_field(18),
+            Some(&slr::FixFieldValue::MultiValue(vec![
+                "1".to_string(),
+                "6".to_string()
+            ]))
+        );
+        let encoded = codec.encode_to_vec(&message).unwrap();
+        assert!(str::from_utf8(&encoded[..]).unwrap().contains("|18=1 6|"));
+    }
+
+    #[test]
+    fn decode_into_reuses_target() {
+        let mut codec = encoder_slash_no_verify();
+        let mut message = slr::Message::new();
+        let msg = "8=FIX.4.2|9=40|35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|10=000|";
+        codec.decode_into(msg.as_bytes(), &mut message).unwrap();
+        assert_eq!(message.msg_type(), Some("D"));
+        assert_eq!(
+            message.get_field(15),
+            Some(&slr::FixFieldValue::String("USD".to_string()))
+        );
+        let msg = "8=FIX.4.2|9=30|35=0|49=AFUNDMGR|56=ABROKER|10=000|";
+        codec.decode_into(msg.as_bytes(), &mut message).unwrap();
+        assert_eq!(message.msg_type(), Some("0"));
+        assert_eq!(message.get_field(15), None);
+        assert_eq!(
+            message.get_field(56),
+            Some(&slr::FixFieldValue::String("ABROKER".to_string()))
+        );
+    }
+
+    #[test]
+    fn decode_str_with_vertical_slash() {
+        let mut codec = encoder();
+        let message = codec.decode_str(RANDOM_MESSAGES[0]).unwrap();
+        assert_eq!(message.msg_type(), Some("0"));
+        assert_eq!(
+            message.get_field(49),
+            Some(&slr::FixFieldValue::String("A".to_string()))
+        );
+    }
+
+    #[test]
+    fn string_field_with_nul_byte_is_invalid() {
+        let msg = "8=FIX.4.2|9=40|35=D|49=AFUND\0MGR|56=ABROKER|15=USD|59=0|10=000|";
+        let mut codec = encoder_slash_validate_types();
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let mut codec = encoder_slash_no_verify();
+        assert!(codec.decode_str(msg).is_ok());
+    }
+
+    #[test]
+    fn leading_plus_sign_only_for_signed_ints() {
+        let msg = "8=FIX.4.4|9=40|35=8|34=12|157=+100|10=000|";
+        let mut codec = encoder_slash_validate_types();
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(
+            message.get_field(157),
+            Some(&slr::FixFieldValue::from(100i64))
+        );
+        let msg = "8=FIX.4.4|9=40|35=8|34=12|157=-100|10=000|";
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(
+            message.get_field(157),
+            Some(&slr::FixFieldValue::from(-100i64))
+        );
+        let msg = "8=FIX.4.4|9=40|35=8|34=+12|157=100|10=000|";
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let msg = "8=FIX.4.4|9=40|35=8|34=12|157=1+00|10=000|";
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+    }
+
+    #[test]
+    fn misplaced_signs_are_rejected_without_type_validation() {
+        let mut codec = encoder_slash_no_verify();
+        let msg = "8=FIX.4.4|9=40|35=8|34=12|157=1-2|10=000|";
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let msg = "8=FIX.4.4|9=40|35=8|34=-12|157=12|10=000|";
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let msg = "8=FIX.4.4|9=40|35=8|34=12|157=-|10=000|";
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let msg = "8=FIX.4.4|9=40|35=8|34=12|157=-12|10=000|";
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(
+            message.get_field(157),
+            Some(&slr::FixFieldValue::from(-12i64))
+        );
+    }
+
+    #[test]
+    fn char_field_with_two_characters_is_invalid() {
+        let msg = "8=FIX.4.4|9=40|35=D|11=ORD1|54=12|10=000|";
+        let mut codec = encoder_slash_validate_types();
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let msg = "8=FIX.4.4|9=40|35=D|11=ORD1|43=YY|54=1|10=000|";
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let msg = "8=FIX.4.4|9=40|35=D|11=ORD1|43=Y|54=1|10=000|";
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(message.get_field(54), Some(&slr::FixFieldValue::from('1')));
+        assert!(message.poss_dup());
+    }
+
+    #[test]
+    fn group_with_too_many_declared_entries() {
+        let msg = "8=FIX.4.4|9=40|35=W|262=1|268=1000000|269=0|270=1.5|10=000|";
+        let mut codec: Codec<slr::Message, _> =
+            Codec::new(ConfigVerticalSlashMaxGroupEntries(100));
+        assert_eq!(codec.decode_str(msg), Err(Error::GroupTooLarge));
+        let msg = "8=FIX.4.4|9=40|35=W|262=1|268=1|269=0|270=1.5|10=000|";
+        assert!(codec.decode_str(msg).is_ok());
+    }
+
+    #[test]
+    fn decode_without_begin_string() {
+        let msg = "9=40|35=D|49=AFUNDMGR|56=ABROKER|11=ORD1|10=000|";
+        let mut codec = encoder_slash_no_verify();
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidStandardHeader));
+        let result = codec
+            .decode_with_version(msg.as_bytes(), Version::Fix44)
+            .unwrap();
+        assert_eq!(
+            result.get_field(8),
+            Some(&slr::FixFieldValue::String("FIX.4.4".to_string()))
+        );
+        assert_eq!(
+            result.get_field(11),
+            Some(&slr::FixFieldValue::String("ORD1".to_string()))
+        );
+    }
+
+    /// A minimal QuickFIX spec with a custom `Tenor` field, as no FIX version
+    /// bundled with QuickFIX has any.
+    const QUICKFIX_SPEC_WITH_TENOR: &str = r#"
+<fix type='FIX' major='4' minor='4' servicepack='0'>
+ <header>
+  <field name='BeginString' required='Y' />
+  <field name='BodyLength' required='Y' />
+  <field name='MsgType' required='Y' />
+ </header>
+ <trailer>
+  <field name='CheckSum' required='Y' />
+ </trailer>
+ <messages>
+  <message name='CustomTenor' msgtype='U1' msgcat='app'>
+   <field name='CustomSettlTenor' required='Y' />
+  </message>
+ </messages>
+ <components />
+ <fields>
+  <field number='8' name='BeginString' type='STRING' />
+  <field number='9' name='BodyLength' type='LENGTH' />
+  <field number='10' name='CheckSum' type='STRING' />
+  <field number='35' name='MsgType' type='STRING' />
+  <field number='5000' name='CustomSettlTenor' type='TENOR' />
+ </fields>
+</fix>
+"#;
+
+    fn dict_with_tenor() -> Dictionary {
+        Dictionary::save_definition_spec(QUICKFIX_SPEC_WITH_TENOR).unwrap()
+    }
+
+    #[test]
+    fn tenor_round_trip() {
+        let msg = "8=FIX.4.4|9=13|35=U1|5000=M3|10=000|";
+        let mut codec = Codec::<slr::Message, _>::with_dict(
+            dict_with_tenor(),
+            ConfigVerticalSlashValidateTypes,
+        );
+        let message = codec.decode_str(msg).unwrap().clone();
+        assert_eq!(
+            message.get_field(5000).unwrap().as_tenor(),
+            Some((dt::TenorUnit::Months, 3))
+        );
+        let mut buffer = Vec::new();
+        codec.encode(&mut buffer, &message).unwrap();
+        let message = codec.decode(&buffer[..]).unwrap();
+        assert_eq!(
+            message.get_field(5000).unwrap().as_tenor(),
+            Some((dt::TenorUnit::Months, 3))
+        );
+    }
+
+    #[test]
+    fn invalid_tenor() {
+        let msg = "8=FIX.4.4|9=13|35=U1|5000=X9|10=000|";
+        let mut codec = Codec::<slr::Message, _>::with_dict(
+            dict_with_tenor(),
+            ConfigVerticalSlashValidateTypes,
+        );
+        assert_eq!(codec.decode_str(msg), Err(Error::InvalidData));
+        let mut codec =
+            Codec::<slr::Message, _>::with_dict(dict_with_tenor(), ConfigVerticalSlashNoVerify);
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(message.get_field(5000).unwrap().as_tenor(), None);
+    }
+
+    #[test]
+    fn pretty_market_data_snapshot() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "W");
+        message.add_str(55, "ESU6");
+        let entries = ["0", "1"]
+            .iter()
+            .map(|entry_type| {
+                let mut entry = slr::GroupEntry::new();
+                entry.insert(269, slr::FixFieldValue::String(entry_type.to_string()));
+                entry.insert(270, slr::FixFieldValue::String("1.50".to_string()));
+                entry
+            })
+            .collect();
+        message.add_field(268, slr::FixFieldValue::Group(entries));
+        message.add_str(9999, "custom");
+        let expected = [
+            "8=FIX.4.4 (BeginString)",
+            "35=W (MsgType)",
+            "55=ESU6 (Symbol)",
+            "268=2 (NoMDEntries)",
+            "  269=0 (MDEntryType)",
+            "  270=1.50 (MDEntryPx)",
+            "  269=1 (MDEntryType)",
+            "  270=1.50 (MDEntryPx)",
+            "9999=custom",
+        ];
+        assert_eq!(pretty(&message, &dict), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn signature_is_last_and_length_prefixed() {
+        let mut codec = Codec::<slr::Message, _>::new(ConfigDefault);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(49, "AFUNDMGR");
+        message.add_str(56, "ABROKER");
+        message.add_str(11, "ORD1");
+        let signature = b"\x01sig=\x00nature".to_vec();
+        message.add_field(89, slr::FixFieldValue::Data(signature.clone()));
+        let mut buffer = Vec::new();
+        codec.encode(&mut buffer, &message).unwrap();
+        let trailer = b"\x0193=12\x0189=\x01sig=\x00nature\x0110=";
+        let trailer_start = buffer
+            .windows(trailer.len())
+            .position(|window| window == &trailer[..])
+            .unwrap();
+        // Only the separator of `CheckSum <10>` is left.
+        let rest = &buffer[trailer_start + trailer.len()..];
+        assert_eq!(rest.iter().filter(|byte| **byte == SOH).count(), 1);
+        let decoded = codec.decode(&buffer[..]).unwrap();
+        assert_eq!(
+            decoded.get_field(89),
+            Some(&slr::FixFieldValue::Data(signature))
+        );
+        assert_eq!(
+            decoded.get_field(11),
+            Some(&slr::FixFieldValue::String("ORD1".to_string()))
+        );
+    }
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashStrictExtensionFields;
+
+    impl Config for ConfigVerticalSlashStrictExtensionFields {
+        type ChecksumAlgo = ChecksumAlgoLazy;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const STRICT_EXTENSION_FIELDS: bool = true;
+    }
+
+    #[test]
+    fn extension_field_with_fix44_dictionary() {
+        let msg = "8=FIX.4.4|9=40|35=D|49=AFUNDMGR|56=ABROKER|1156=1|15=USD|10=000|";
+        let mut codec = encoder_slash_no_verify();
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(
+            message.get_field(1156),
+            Some(&slr::FixFieldValue::String("1".to_string()))
+        );
+        assert_eq!(
+            message.get_field(15),
+            Some(&slr::FixFieldValue::String("USD".to_string()))
+        );
+        let mut codec: Codec<slr::Message, _> =
+            Codec::new(ConfigVerticalSlashStrictExtensionFields);
+        assert_eq!(codec.decode_str(msg), Err(Error::ExtensionField(1156)));
+    }
+
+    #[test]
+    fn extension_field_defined_by_fixt_dictionary() {
+        let msg =
+            "8=FIXT.1.1|9=55|35=A|49=A|56=B|34=1|52=20100304-07:59:30|98=0|108=30|1137=9|10=000|";
+        let mut codec: Codec<slr::Message, _> = Codec::with_dict(
+            Dictionary::from_version(Version::Fixt11),
+            ConfigVerticalSlashStrictExtensionFields,
+        );
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(
+            message.get_field(1137),
+            Some(&slr::FixFieldValue::String("9".to_string()))
+        );
+    }
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashBestEffortData;
+
+    impl Config for ConfigVerticalSlashBestEffortData {
+        type ChecksumAlgo = ChecksumAlgoLazy;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+        const BEST_EFFORT_DATA: bool = true;
+    }
+
+    #[test]
+    fn data_field_without_length() {
+        // `RawData <96>` without `RawDataLength <95>`.
+        let msg = "8=FIX.4.4|9=40|35=D|49=AFUNDMGR|96=foo|bar=|56=ABROKER|10=000|";
+        let mut codec = encoder_slash_no_verify();
+        assert_eq!(codec.decode_str(msg), Err(Error::DataWithoutLength(96)));
+        let mut codec: Codec<slr::Message, _> = Codec::new(ConfigVerticalSlashBestEffortData);
+        let message = codec.decode_str(msg).unwrap();
+        assert_eq!(
+            message.get_field(96),
+            Some(&slr::FixFieldValue::Data(b"foo|bar=".to_vec()))
+        );
+        assert_eq!(
+            message.get_field(56),
+            Some(&slr::FixFieldValue::String("ABROKER".to_string()))
+        );
+    }
+
+    #[test]
+    fn verify_roundtrip_of_canonical_message() {
+        let msg = "8=FIX.4.4|9=66|35=D|11=ORD1|15=USD|38=100|49=AFUNDMGR|54=1|55=EUR/USD|56=ABROKER|10=163|";
+        let mut codec = encoder();
+        assert_eq!(codec.verify_roundtrip(msg.as_bytes()), Ok(()));
+        let mut codec = encoder_with_soh();
+        assert_eq!(codec.verify_roundtrip(with_soh(msg).as_bytes()), Ok(()));
+    }
+
+    #[test]
+    fn verify_roundtrip_of_reordered_message() {
+        // `SenderCompID <49>` comes before `ClOrdID <11>`, but the encoder
+        // writes fields in ascending tag order.
+        let msg = "8=FIX.4.4|9=66|35=D|49=AFUNDMGR|11=ORD1|15=USD|38=100|54=1|55=EUR/USD|56=ABROKER|10=163|";
+        let mut codec = encoder();
+        assert_eq!(
+            codec.verify_roundtrip(msg.as_bytes()),
+            Err(RoundtripError::Mismatch(20))
+        );
+    }
+
+    #[test]
+    fn verify_roundtrip_of_random_message() {
+        // `MsgSeqNum <34>` comes after `SenderCompID <49>` and
+        // `TargetCompID <56>`, but the encoder writes it first.
+        let mut codec = encoder();
+        assert_eq!(
+            codec.verify_roundtrip(RANDOM_MESSAGES[0].as_bytes()),
+            Err(RoundtripError::Mismatch(20))
+        );
+    }
+
+    #[test]
+    fn zero_body_length_is_recovered_in_lenient_mode() {
+        let msg = "8=FIX.4.4|9=0|35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|10=000|8=FIX.4.4|";
+        let mut codec = Codec::<slr::Message, _>::new(ConfigVerticalSlashLenientBodyLength);
+        assert_eq!(codec.frame_len(msg.as_bytes()), Ok(Some(msg.len() - 10)));
+        let message = codec.decode(msg.as_bytes()).unwrap();
+        assert_eq!(
+            message.get_field(56),
+            Some(&slr::FixFieldValue::String("ABROKER".to_string()))
+        );
+        let msg = "8=FIX.4.4|35=D|49=AFUNDMGR|56=ABROKER|10=000|";
+        assert!(codec.decode(msg.as_bytes()).is_ok());
+        let mut codec = encoder_slash_no_verify();
+        assert_eq!(
+            codec.frame_len(msg.as_bytes()),
+            Err(Error::InvalidStandardHeader)
+        );
+        assert_eq!(
+            codec.decode(msg.as_bytes()).err(),
+            Some(Error::InvalidStandardHeader)
+        );
+    }
+
+    #[test]
+    fn streaming_decoder_without_body_length() {
+        let first = "8=FIX.4.4|35=D|49=AFUNDMGR|56=ABROKER|10=000|";
+        let second = "8=FIX.4.4|35=0|49=ABROKER|56=AFUNDMGR|34=2|10=000|";
+        let stream = format!("{}{}", first, second);
+        let codec = Codec::<slr::Message, _>::new(ConfigVerticalSlashLenientBodyLength);
+        let mut frames = codec.frames_streamiter(io::Cursor::new(stream));
+        assert_eq!(frames.next().unwrap().unwrap().len, first.len());
+        assert_eq!(frames.next().unwrap().unwrap().len, second.len());
+        let codec = encoder_slash_no_verify();
+        let mut frames = codec.frames_streamiter(io::Cursor::new(first));
+        assert!(matches!(
+            frames.next(),
+            Err(FramelessError::Decoder(Error::InvalidStandardHeader))
+        ));
+    }
+
+    #[test]
+    fn streaming_decoder_with_nul_in_data_field() {
+        let msg = "8=FIX.4.4|9=27|35=D|49=A|56=B|95=3|96=a\0b|10=000|";
+        let stream = format!("{}{}", msg, RANDOM_MESSAGES[5]);
+        let codec = encoder_slash_no_verify();
+        let mut frames = codec.frames_streamiter(io::Cursor::new(stream));
+        assert_eq!(frames.next().unwrap().unwrap().len, msg.len());
+        assert_eq!(
+            frames.next().unwrap().unwrap().len,
+            RANDOM_MESSAGES[5].len()
+        );
+    }
+
+    #[test]
+    fn frame_len_follows_body_length() {
+        let msg = RANDOM_MESSAGES[0];
+        let codec = encoder_slash_no_verify();
+        assert_eq!(codec.frame_len(msg.as_bytes()), Ok(Some(msg.len())));
+        assert_eq!(codec.frame_len(&msg.as_bytes()[..40]), Ok(None));
+        let stream = format!("{}{}", msg, RANDOM_MESSAGES[1]);
+        assert_eq!(codec.frame_len(stream.as_bytes()), Ok(Some(msg.len())));
+    }
+
+    #[test]
+    fn frame_reader_with_repeated_begin_string() {
+        let first = RANDOM_MESSAGES[0].trim_end_matches("10=185|");
+        let stream = format!("{}{}", first, RANDOM_MESSAGES[5]);
+        let mut reader = FrameReader::new(io::Cursor::new(&stream), ConfigVerticalSlash);
+        assert!(matches!(
+            reader.next_frame(),
+            Err(FramelessError::Decoder(Error::RepeatedBeginString))
+        ));
+        let mut reader = FrameReader::new(
+            io::Cursor::new(&stream),
+            ConfigVerticalSlashSplitOnRepeatedBeginString,
+        );
+        assert_eq!(reader.next_frame().unwrap().unwrap(), first.as_bytes());
+        assert_eq!(
+            reader.next_frame().unwrap().unwrap(),
+            RANDOM_MESSAGES[5].as_bytes()
+        );
+        assert!(reader.next_frame().unwrap().is_none());
+        // Well-formed messages are never split.
+        let stream = format!("{}{}", RANDOM_MESSAGES[0], RANDOM_MESSAGES[5]);
+        let mut reader = FrameReader::new(
+            io::Cursor::new(&stream),
+            ConfigVerticalSlashSplitOnRepeatedBeginString,
+        );
+        assert_eq!(
+            reader.next_frame().unwrap().unwrap(),
+            RANDOM_MESSAGES[0].as_bytes()
+        );
+    }
+
+    /// Hands out a single byte per read.
+    struct ByteByByte<R>(R);
+
+    impl<R: io::Read> io::Read for ByteByByte<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn frame_reader_with_repeated_begin_string_read_byte_by_byte() {
+        let first = RANDOM_MESSAGES[0].trim_end_matches("10=185|");
+        let stream = format!("{}{}", first, RANDOM_MESSAGES[5]);
+        let reader = ByteByByte(io::Cursor::new(&stream));
+        let mut reader = FrameReader::new(reader, ConfigVerticalSlash);
+        assert!(matches!(
+            reader.next_frame(),
+            Err(FramelessError::Decoder(Error::RepeatedBeginString))
+        ));
+        let stream = format!("{}{}{}", first, RANDOM_MESSAGES[5], RANDOM_MESSAGES[1]);
+        let reader = ByteByByte(io::Cursor::new(&stream));
+        let mut reader = FrameReader::new(reader, ConfigVerticalSlashSplitOnRepeatedBeginString);
+        assert_eq!(reader.next_frame().unwrap().unwrap(), first.as_bytes());
+        assert_eq!(
+            reader.next_frame().unwrap().unwrap(),
+            RANDOM_MESSAGES[5].as_bytes()
+        );
+        assert_eq!(
+            reader.next_frame().unwrap().unwrap(),
+            RANDOM_MESSAGES[1].as_bytes()
+        );
+        assert!(reader.next_frame().unwrap().is_none());
+    }
+
+    fn length_prefixed_reader(prefix_len: usize) -> FrameReader<io::Cursor<Vec<u8>>, impl Config> {
+        let msg = RANDOM_MESSAGES[0];
+        let mut stream = (msg.len() as u64).to_be_bytes()[8 - prefix_len.min(8)..].to_vec();
+        stream.extend_from_slice(msg.as_bytes());
+        FrameReader::with_outer_framing(
+            io::Cursor::new(stream),
+            ConfigVerticalSlash,
+            OuterFraming::LengthPrefix(prefix_len),
+        )
+    }
+
+    #[test]
+    fn frame_reader_accepts_length_prefixes_of_1_to_8_bytes() {
+        for prefix_len in &[1, 8] {
+            let mut reader = length_prefixed_reader(*prefix_len);
+            assert_eq!(
+                reader.next_frame().unwrap().unwrap(),
+                RANDOM_MESSAGES[0].as_bytes()
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn frame_reader_rejects_empty_length_prefix() {
+        length_prefixed_reader(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn frame_reader_rejects_length_prefix_longer_than_8_bytes() {
+        length_prefixed_reader(9);
+    }
+
+    #[test]
+    fn frame_reader_strips_length_prefix() {
+        let mut stream = Vec::new();
+        for msg in &RANDOM_MESSAGES[..2] {
+            stream.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+            stream.extend_from_slice(msg.as_bytes());
+        }
+        let mut reader = FrameReader::with_outer_framing(
+            io::Cursor::new(&stream),
+            ConfigVerticalSlash,
+            OuterFraming::LengthPrefix(4),
+        );
+        let mut codec = encoder();
+        for msg in &RANDOM_MESSAGES[..2] {
+            let frame = reader.next_frame().unwrap().unwrap();
+            assert_eq!(frame, msg.as_bytes());
+            assert!(codec.decode(frame).is_ok());
+        }
+        assert!(reader.next_frame().unwrap().is_none());
+        // A message that is cut short.
+        let mut reader = FrameReader::with_outer_framing(
+            io::Cursor::new(&stream[..stream.len() - 1]),
+            ConfigVerticalSlash,
+            OuterFraming::LengthPrefix(4),
+        );
+        assert!(reader.next_frame().unwrap().is_some());
+        assert!(matches!(
+            reader.next_frame(),
+            Err(FramelessError::Decoder(Error::Eof))
+        ));
+    }
+
+    #[test]
+    fn msg_type_histogram_of_log() {
+        let log = format!(
+            "{}\n{}\n{}\n",
+            RANDOM_MESSAGES[0], RANDOM_MESSAGES[5], RANDOM_MESSAGES[0]
+        );
+        let histogram = msg_type_histogram(log.as_bytes(), b'|').unwrap();
+        assert_eq!(histogram.len(), 2);
+        assert_eq!(histogram.get("0"), Some(&2));
+        assert_eq!(histogram.get("D"), Some(&1));
+    }
+
+    #[test]
+    fn scan_matches_reference_parse() {
+        for msg in RANDOM_MESSAGES {
+            let expected: Vec<(u32, &[u8])> = msg
+                .as_bytes()
+                .split(|byte| *byte == b'|')
+                .filter(|field| !field.is_empty())
+                .map(|field| {
+                    let eq = field.iter().position(|byte| *byte == b'=').unwrap();
+                    let tag = str::from_utf8(&field[..eq]).unwrap().parse().unwrap();
+                    (tag, &field[eq + 1..])
+                })
+                .collect();
+            let scanned: Vec<(u32, &[u8])> = scan(msg.as_bytes(), b'|').collect();
+            assert_eq!(scanned, expected);
+        }
+    }
+
+    #[test]
+    fn scan_stops_at_checksum_or_malformed_field() {
+        let msg = format!("{}8=FIX.4.2|", RANDOM_MESSAGES[0]);
+        assert_eq!(scan(msg.as_bytes(), b'|').count(), 8);
+        let without_final_separator = &RANDOM_MESSAGES[0][..RANDOM_MESSAGES[0].len() - 1];
+        assert_eq!(
+            scan(without_final_separator.as_bytes(), b'|').last(),
+            Some((10, &b"185"[..]))
+        );
+        let tags: Vec<u32> = scan(b"8=FIX.4.2|9=42|x=0|35=0|", b'|')
+            .map(|(tag, _)| tag)
+            .collect();
+        assert_eq!(tags, vec![8, 9]);
+        assert_eq!(scan(b"99999999999=1|", b'|').next(), None);
+        assert_eq!(scan(b"", b'|').next(), None);
+    }
+
+    #[test]
+    fn count_fields_in_heartbeat() {
+        let heartbeat = RANDOM_MESSAGES[0];
+        assert_eq!(count_fields(heartbeat.as_bytes(), b'|'), 8);
+        assert_eq!(count_fields(with_soh(heartbeat).as_bytes(), SOH), 8);
+        let without_final_separator = &heartbeat[..heartbeat.len() - 1];
+        assert_eq!(count_fields(without_final_separator.as_bytes(), b'|'), 8);
+        // Group fields count, too.
+        assert_eq!(count_fields(RANDOM_MESSAGES[6].as_bytes(), b'|'), 26);
+        assert_eq!(count_fields(b"", b'|'), 0);
+    }
+
+    #[test]
+    fn validate_framing_of_heartbeat() {
+        let heartbeat = RANDOM_MESSAGES[0];
+        assert!(validate_framing(heartbeat.as_bytes(), b'|').is_ok());
+        assert!(validate_framing(with_soh(heartbeat).as_bytes(), SOH).is_ok());
+        let bad_checksum = heartbeat.replace("10=185", "10=186");
+        assert!(matches!(
+            validate_framing(bad_checksum.as_bytes(), b'|'),
+            Err(Error::InvalidChecksum(InvalidChecksum {
+                expected: 185,
+                actual: 186
+            }))
+        ));
+        let bad_body_length = heartbeat.replace("9=42", "9=41");
+        assert!(matches!(
+            validate_framing(bad_body_length.as_bytes(), b'|'),
+            Err(Error::InvalidStandardTrailer)
+        ));
+    }
+
+    #[test]
+    fn heartbeat_to_csv_row() {
+        let mut codec = encoder_slash_no_verify();
+        let message = codec.decode(RANDOM_MESSAGES[0].as_bytes()).unwrap().clone();
+        let dict = Dictionary::from_version(Version::Fix42);
+        assert_eq!(
+            to_csv_row(&message, &[35, 34, 52], &dict),
+            "0,12,20100304-07:59:30"
+        );
+    }
+
+    #[test]
+    fn csv_row_with_quotes_and_groups() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = slr::Message::new();
+        message.add_str(58, r#"Say "hi""#);
+        let mut bid = slr::GroupEntry::new();
+        bid.insert(269, slr::FixFieldValue::String("0".to_string()));
+        bid.insert(270, slr::FixFieldValue::String("1.5".to_string()));
+        let mut offer = slr::GroupEntry::new();
+        offer.insert(269, slr::FixFieldValue::String("1".to_string()));
+        offer.insert(270, slr::FixFieldValue::String("1.75".to_string()));
+        message.add_field(268, slr::FixFieldValue::Group(vec![bid, offer]));
+        assert_eq!(
+            to_csv_row(&message, &[58, 268], &dict),
+            r#""Say ""hi""",MDEntryType=0 MDEntryPx=1.5;MDEntryType=1 MDEntryPx=1.75"#
+        );
+    }
+
+    #[test]
+    fn encode_to_writer_matches_encode() {
+        let mut codec = encoder_slash_no_verify();
+        for msg in RANDOM_MESSAGES {
+            let message = codec.decode(msg.as_bytes()).unwrap().clone();
+            let mut buffer = Vec::<u8>::new();
+            codec.encode(&mut buffer, &message).unwrap();
+            let mut writer = Vec::<u8>::new();
+            codec.encode_to_writer(&mut writer, &message).unwrap();
+            assert_eq!(writer, buffer);
+        }
+    }
+
+    #[test]
+    fn encode_with_custom_field_order() {
+        let mut codec = encoder();
+        let message = codec.decode(RANDOM_MESSAGES[5].as_bytes()).unwrap().clone();
+        codec.set_field_order(vec![55, 44, 11, 54, 44]);
+        let output = codec.encode_to_vec(&message).unwrap();
+        let output = str::from_utf8(&output[..]).unwrap();
+        assert!(output.starts_with("8=FIX.4.4|9=122|35=D|44=5|11=13346|54=1|1=Marcel|21=1|34=215|"));
+        // 55 is not in the message, so it's skipped.
+        assert!(!output.contains("|55="));
+        assert_eq!(codec.decode(output.as_bytes()).unwrap(), &message);
+    }
+
+    #[test]
+    fn stale_data_length_is_corrected() {
+        let mut codec = encoder_slash_no_verify();
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "A");
+        message.add_int(95, 10);
+        message.add_field(96, slr::FixFieldValue::Data(b"abc".to_vec()));
+        let mut buffer = Vec::<u8>::new();
+        codec.encode(&mut buffer, &message).unwrap();
+        let output = str::from_utf8(&buffer[..]).unwrap();
+        assert!(output.contains("|95=3|96=abc|"));
+        let decoded = codec.decode(&buffer[..]).unwrap();
+        assert_eq!(
+            decoded.get_field(96),
+            Some(&slr::FixFieldValue::Data(b"abc".to_vec()))
+        );
+    }
+
+    #[test]
+    fn custom_checksum_fn_replaces_checksum() {
+        let mut codec = encoder_slash_no_verify();
+        let message = codec.decode(RANDOM_MESSAGES[0].as_bytes()).unwrap().clone();
+        codec.set_checksum_fn(Box::new(|_| 0));
+        let mut buffer = Vec::<u8>::new();
+        codec.encode(&mut buffer, &message).unwrap();
+        assert!(buffer.ends_with(b"|10=000|"));
+    }
+
+    /// Sums all integer fields and logs group boundaries.
+    #[derive(Default)]
+    struct SumVisitor {
+        sum: i64,
+        events: Vec<String>,
+    }
+
+    impl FieldVisitor for SumVisitor {
+        fn on_field(&mut self, tag: u32, value: &slr::FixFieldValue) {
+            if let slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(n))) = value {
+                self.sum += *n as i64;
+                self.events.push(format!("{}={}", tag, n));
+            }
+        }
+
+        fn on_group_start(&mut self, count_tag: u32, n: usize) {
+            self.events.push(format!("start {} x{}", count_tag, n));
+        }
+
+        fn on_group_end(&mut self) {
+            self.events.push("end".to_string());
+        }
+    }
+
+    #[test]
+    fn decode_visit_sums_integer_fields() {
+        let msg = "8=FIX.4.4|9=40|35=W|34=12|262=789|268=2|269=0|270=1.50|290=1|269=1|270=1.75|290=2|22=8|10=000|";
+        let mut codec = encoder_slash_no_verify();
+        let mut visitor = SumVisitor::default();
+        codec.decode_visit(msg.as_bytes(), &mut visitor).unwrap();
+        assert_eq!(visitor.sum, 40 + 12 + 2 + 1 + 2);
+        assert_eq!(
+            visitor.events,
+            vec![
+                "9=40",
+                "34=12",
+                "268=2",
+                "start 268 x2",
+                "290=1",
+                "290=2",
+                "end"
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_with_and_without_trailing_soh() {
+        let msg = RANDOM_MESSAGES[0];
+        let mut codec = encoder();
+        let message = codec.decode(msg.as_bytes()).unwrap().clone();
+        let mut with_soh = Vec::<u8>::new();
+        codec.encode(&mut with_soh, &message).unwrap();
+        assert!(with_soh.ends_with(b"|10=185|"));
+        let mut codec = Codec::<slr::Message, _>::new(ConfigVerticalSlashNoTrailingSoh);
+        let mut without_soh = Vec::<u8>::new();
+        codec.encode(&mut without_soh, &message).unwrap();
+        assert!(without_soh.ends_with(b"|10=185"));
+        assert_eq!(&without_soh[..], &with_soh[..with_soh.len() - 1]);
+    }
 }
@@ -0,0 +1,189 @@
+//! Test utilities for codecs. Outside of this crate, they're only available
+//! with the `test-util` feature.
+
+use crate::app::slr;
+use crate::codec::{fast, json, tagvalue, Decoder, Encoder};
+use crate::Dictionary;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// Encodes and decodes `message` through all codecs that support it and
+/// asserts that each decoded message is semantically equal to `message` (see
+/// [`slr::Message::normalized`]):
+///
+/// - JSON.
+/// - FIX tag-value, unless `message` has repeating groups, which the
+///   tag-value encoder doesn't support yet.
+/// - JSON and then FIX tag-value, with the same restriction.
+///
+/// FAST is skipped, as its templates can't be derived from `dict`; see
+/// [`assert_roundtrip_all_with_templates`].
+/// `BodyLength <9>` and `CheckSum <10>` are ignored, since they're
+/// recalculated by the tag-value encoder.
+///
+/// # Panics
+///
+/// Panics if any encoding or decoding step fails, or if any round-trip
+/// changes `message`.
+pub fn assert_roundtrip_all(message: &slr::Message, dict: &Dictionary) {
+    let expected = canonical(message, dict);
+    let mut config = json::ConfigSettable::new();
+    config.set_allow_unknown_fields(true);
+    let mut codec_json = json::Codec::<slr::Message, _>::new(dict.clone(), config);
+    let mut codec_tagvalue =
+        tagvalue::Codec::<slr::Message, _>::with_dict(dict.clone(), tagvalue::ConfigDefault);
+    let message_json = roundtrip(&mut codec_json, message, "JSON");
+    assert_eq!(canonical(&message_json, dict), expected, "JSON round-trip");
+    if message.groups().next().is_some() {
+        return;
+    }
+    let message_tagvalue = roundtrip(&mut codec_tagvalue, message, "tag-value");
+    assert_eq!(
+        canonical(&message_tagvalue, dict),
+        expected,
+        "tag-value round-trip"
+    );
+    let message_both = roundtrip(&mut codec_tagvalue, &message_json, "tag-value");
+    assert_eq!(
+        canonical(&message_both, dict),
+        expected,
+        "JSON to tag-value round-trip"
+    );
+}
+
+/// Like [`assert_roundtrip_all`], but also encodes and decodes `message`
+/// through FAST with the template with ID `template_id`, unless `message` has
+/// repeating groups. The template must have an instruction for each field of
+/// `message`, as FAST leaves out all other fields.
+///
+/// # Panics
+///
+/// Panics if any encoding or decoding step fails, or if any round-trip
+/// changes `message`.
+pub fn assert_roundtrip_all_with_templates(
+    message: &slr::Message,
+    dict: &Dictionary,
+    templates: &fast::Templates,
+    template_id: u32,
+) {
+    assert_roundtrip_all(message, dict);
+    if message.groups().next().is_some() {
+        return;
+    }
+    let mut codec_fast = fast::Fast::new()
+        .with_templates(templates.clone())
+        .with_encoding_template(template_id);
+    // The FAST encoder only takes textual values for string fields.
+    let message_text = slr::Message {
+        fields: stringified(&message.fields),
+    };
+    let message_fast = roundtrip(&mut codec_fast, &message_text, "FAST");
+    assert_eq!(
+        canonical(&message_fast, dict),
+        canonical(message, dict),
+        "FAST round-trip"
+    );
+}
+
+fn roundtrip<C>(codec: &mut C, message: &slr::Message, name: &str) -> slr::Message
+where
+    C: Encoder<slr::Message> + Decoder<slr::Message>,
+    <C as Encoder<slr::Message>>::Error: Debug,
+    <C as Decoder<slr::Message>>::Error: Debug,
+{
+    let mut buffer = Vec::new();
+    if let Err(err) = codec.encode(&mut buffer, message) {
+        panic!("{} encoding failed: {:?}", name, err);
+    }
+    match codec.decode(&buffer[..]) {
+        Ok(decoded) => decoded.clone(),
+        Err(err) => panic!("{} decoding failed: {:?}", name, err),
+    }
+}
+
+/// Decoders don't agree on which fields get a typed value, so values are
+/// compared by their textual representation.
+fn canonical(message: &slr::Message, dict: &Dictionary) -> slr::Message {
+    let mut message = slr::Message {
+        fields: stringified(&message.fields),
+    };
+    message.fields.remove(&9);
+    message.fields.remove(&10);
+    message.normalized(dict)
+}
+
+fn stringified(fields: &BTreeMap<i64, slr::FixFieldValue>) -> BTreeMap<i64, slr::FixFieldValue> {
+    fields
+        .iter()
+        .map(|(tag, value)| {
+            let value = match value {
+                slr::FixFieldValue::Value(v) => slr::FixFieldValue::String(v.to_string()),
+                slr::FixFieldValue::MultiValue(values) => {
+                    slr::FixFieldValue::String(values.join(" "))
+                }
+                slr::FixFieldValue::Group(entries) => {
+                    slr::FixFieldValue::Group(entries.iter().map(stringified).collect())
+                }
+                v => v.clone(),
+            };
+            (*tag, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::app::Version;
+    use crate::codec::tagvalue::{ChecksumAlgoLazy, Codec, Config, TagLookupPredetermined};
+
+    #[derive(Clone, Debug)]
+    struct ConfigVerticalSlashNoVerify;
+
+    impl Config for ConfigVerticalSlashNoVerify {
+        type ChecksumAlgo = ChecksumAlgoLazy;
+        type TagLookup = TagLookupPredetermined;
+
+        const SOH_SEPARATOR: u8 = '|' as u8;
+    }
+
+    const SAMPLE_MESSAGES: &[&str] = &[
+        "8=FIX.4.4|9=122|35=D|34=215|49=CLIENT12|52=20100225-19:41:57.316|56=B|1=Marcel|11=13346|21=1|40=2|44=5|54=1|59=0|60=20100225-19:39:52.020|10=072|",
+        "8=FIX.4.4|9=40|35=D|49=AFUNDMGR|56=ABROKER|15=USD|59=0|10=000|",
+        "8=FIX.4.4|9=34|35=D|49=AFUNDMGR|56=ABROKER|18=1 6|10=000|",
+    ];
+
+    /// Has an instruction for each field of `SAMPLE_MESSAGES`.
+    const TEMPLATE_SAMPLE_MESSAGES: &str = r#"
+<templates>
+  <template name="NewOrderSingle" id="1">
+    <string name="BeginString" id="8"/>
+    <string name="MsgType" id="35"/>
+    <uInt32 name="MsgSeqNum" id="34" presence="optional"><copy/></uInt32>
+    <string name="SenderCompID" id="49"/>
+    <string name="SendingTime" id="52" presence="optional"/>
+    <string name="TargetCompID" id="56"/>
+    <string name="Account" id="1" presence="optional"/>
+    <string name="ClOrdID" id="11" presence="optional"/>
+    <string name="Currency" id="15" presence="optional"/>
+    <string name="ExecInst" id="18" presence="optional"/>
+    <string name="HandlInst" id="21" presence="optional"/>
+    <string name="OrdType" id="40" presence="optional"/>
+    <decimal name="Price" id="44" presence="optional"/>
+    <string name="Side" id="54" presence="optional"/>
+    <string name="TimeInForce" id="59" presence="optional"/>
+    <string name="TransactTime" id="60" presence="optional"/>
+  </template>
+</templates>"#;
+
+    #[test]
+    fn sample_messages_roundtrip_through_all_codecs() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let templates = fast::Templates::from_xml(TEMPLATE_SAMPLE_MESSAGES).unwrap();
+        let mut codec = Codec::<slr::Message, _>::new(ConfigVerticalSlashNoVerify);
+        for sample in SAMPLE_MESSAGES {
+            let message = codec.decode_str(sample).unwrap();
+            assert_roundtrip_all_with_templates(message, &dict, &templates, 1);
+        }
+    }
+}
@@ -1,11 +1,15 @@
 //! Access to FIX Dictionary reference and message specifications.
 
-use crate::app::Version;
+use crate::app::{slr, Version};
 use crate::dt;
+use chrono::NaiveDateTime;
 use quickfix::{ParseDictionaryError, QuickFixReader};
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::io;
 use std::ops::Range;
+use std::sync::Arc;
 
 /// Value for the field `MsgType (35)`.
 #[derive(Copy, Debug, Clone, PartialEq, Eq, Hash)]
@@ -129,6 +133,7 @@ impl<'a> PartialEq for dyn SymbolTableIndex + 'a {
 #[derive(Clone, Debug)]
 pub struct Dictionary {
     version: String,
+    service_pack: u32,
     symbol_table: HashMap<Key, InternalId>,
     abbreviations: Vec<AbbreviatonData>,
     data_types: Vec<DatatypeData>,
@@ -138,6 +143,8 @@ pub struct Dictionary {
     layout_items: Vec<LayoutItemData>,
     categories: Vec<CategoryData>,
     header: Vec<FieldData>,
+    validators: Validators,
+    dangling_references: Vec<DictConsistencyWarning>,
 }
 
 impl Dictionary {
@@ -145,6 +152,7 @@ impl Dictionary {
     fn new<S: ToString>(version: S) -> Self {
         Dictionary {
             version: version.to_string(),
+            service_pack: 0,
             symbol_table: HashMap::new(),
             abbreviations: Vec::new(),
             data_types: Vec::new(),
@@ -154,6 +162,8 @@ impl Dictionary {
             layout_items: Vec::new(),
             categories: Vec::new(),
             header: Vec::new(),
+            validators: Validators::default(),
+            dangling_references: Vec::new(),
         }
     }
 
@@ -182,6 +192,32 @@ impl Dictionary {
         self.version.as_str()
     }
 
+    /// Returns the [`Version`] of `self`, if it's a known FIX version. Unlike
+    /// [`Dictionary::get_version`], this tells service packs apart.
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix50SP2);
+    /// assert_eq!(dict.get_version(), "FIX.5.0");
+    /// assert_eq!(dict.fix_version(), Some(Version::Fix50SP2));
+    /// ```
+    pub fn fix_version(&self) -> Option<Version> {
+        Some(match (self.version.as_str(), self.service_pack) {
+            ("FIX.4.0", _) => Version::Fix40,
+            ("FIX.4.1", _) => Version::Fix41,
+            ("FIX.4.2", _) => Version::Fix42,
+            ("FIX.4.3", _) => Version::Fix43,
+            ("FIX.4.4", _) => Version::Fix44,
+            ("FIX.5.0", 0) => Version::Fix50,
+            ("FIX.5.0", 1) => Version::Fix50SP1,
+            ("FIX.5.0", 2) => Version::Fix50SP2,
+            ("FIXT.1.1", _) => Version::Fixt11,
+            _ => return None,
+        })
+    }
+
     fn symbol(&self, pkey: KeyRef) -> Option<&u32> {
         self.symbol_table.get(&pkey as &dyn SymbolTableIndex)
     }
@@ -277,6 +313,387 @@ impl Dictionary {
             .map(|data| Field(self, data))
     }
 
+    /// Returns the tags of all fields in `message` (including those inside
+    /// repeating groups) that are not defined in `self`, in ascending order.
+    /// This is useful to spot custom, user-defined fields.
+    pub fn unknown_tags(&self, message: &slr::Message) -> Vec<u32> {
+        let mut tags = BTreeSet::new();
+        self.collect_unknown_tags(&message.fields, false, &mut tags);
+        tags.into_iter().collect()
+    }
+
+    /// Returns the tags of all fields in `message` (including those inside
+    /// repeating groups) that don't belong in the FIX version of `self`, in
+    /// ascending order: fields that the version doesn't define, which would
+    /// be dropped when relaying `message` to a counterparty on that version
+    /// (e.g. from FIX 4.4 to FIX 4.2), and fields that the version deprecates
+    /// (see [`Field::is_deprecated`]), which the counterparty may reject.
+    pub fn validate_for_version(&self, message: &slr::Message) -> Vec<u32> {
+        let mut tags = BTreeSet::new();
+        self.collect_unknown_tags(&message.fields, true, &mut tags);
+        tags.into_iter().collect()
+    }
+
+    fn collect_unknown_tags(
+        &self,
+        fields: &BTreeMap<i64, slr::FixFieldValue>,
+        include_deprecated: bool,
+        tags: &mut BTreeSet<u32>,
+    ) {
+        for (tag, value) in fields {
+            let tag = *tag as u32;
+            let is_unknown = match self.field_by_tag(tag) {
+                Some(field) => include_deprecated && field.is_deprecated(),
+                None => true,
+            };
+            if is_unknown {
+                tags.insert(tag);
+            }
+            if let slr::FixFieldValue::Group(entries) = value {
+                for entry in entries {
+                    self.collect_unknown_tags(entry, include_deprecated, tags);
+                }
+            }
+        }
+    }
+
+    /// Checks that all fields required by the definition of `message` in
+    /// `self` are present, including those required within each entry of
+    /// repeating groups. Fields of optional components are not checked.
+    ///
+    /// Only fields in the message body are checked, `StandardHeader` and
+    /// `StandardTrailer` are not. Validators registered with
+    /// [`Dictionary::add_validator`] run afterwards, in registration order.
+    pub fn validate_message(&self, message: &slr::Message) -> Result<(), ValidationError> {
+        let msg_type = match message.get_field(35) {
+            Some(slr::FixFieldValue::String(msg_type)) => msg_type,
+            _ => return Err(ValidationError::InvalidMsgType),
+        };
+        let definition = self
+            .message_by_msgtype(msg_type)
+            .ok_or(ValidationError::InvalidMsgType)?;
+        let mut errors = Vec::new();
+        validate_layout(definition.layout(), &message.fields, &mut errors);
+        if let Some(err) = errors.into_iter().next() {
+            return Err(err);
+        }
+        for validator in self.validators.0.get(msg_type).into_iter().flatten() {
+            validator(message)?;
+        }
+        Ok(())
+    }
+
+    /// Like [`Dictionary::validate_message`], but returns all errors rather
+    /// than just the first one. Validators only run if no required fields are
+    /// missing.
+    pub(crate) fn validation_errors(&self, message: &slr::Message) -> Vec<ValidationError> {
+        let definition = match message.get_field(35) {
+            Some(slr::FixFieldValue::String(msg_type)) => self.message_by_msgtype(msg_type),
+            _ => None,
+        };
+        let definition = match definition {
+            Some(definition) => definition,
+            None => return vec![ValidationError::InvalidMsgType],
+        };
+        let mut errors = Vec::new();
+        validate_layout(definition.layout(), &message.fields, &mut errors);
+        let validators = self.validators.0.get(definition.msg_type());
+        if errors.is_empty() {
+            for validator in validators.into_iter().flatten() {
+                if let Err(err) = validator(message) {
+                    errors.push(err);
+                }
+            }
+        }
+        errors
+    }
+
+    /// Returns the optional fields of the body of `msg_type` which `message`
+    /// doesn't carry, in definition order. Fields of optional components count
+    /// as optional. Repeating groups are only represented by their counter
+    /// field, i.e. entries are not inspected.
+    ///
+    /// This is meant for monitoring field fill rates; `StandardHeader` and
+    /// `StandardTrailer` are not considered. An unknown `msg_type` results in
+    /// no fields at all.
+    pub fn absent_optional_fields(&self, message: &slr::Message, msg_type: &str) -> Vec<Field> {
+        let mut absent = Vec::new();
+        if let Some(definition) = self.message_by_msgtype(msg_type) {
+            collect_absent_optional_fields(definition.layout(), true, &message.fields, &mut absent);
+        }
+        let mut tags = BTreeSet::new();
+        absent.retain(|tag| tags.insert(*tag));
+        absent
+            .into_iter()
+            .filter_map(|tag| self.field_by_tag(tag))
+            .collect()
+    }
+
+    /// Registers `validator` to be run by [`Dictionary::validate_message`] on
+    /// all messages with `MsgType <35>` equal to `msg_type`, after they pass
+    /// structural validation. This allows checking business rules that are not
+    /// part of the FIX specification.
+    ///
+    /// ```
+    /// use fefix::app::{slr, Version};
+    /// use fefix::{Dictionary, ValidationError};
+    ///
+    /// let mut dict = Dictionary::from_version(Version::Fix44);
+    /// dict.add_validator(
+    ///     "0",
+    ///     Box::new(|_: &slr::Message| Err(ValidationError::Custom("No heartbeats".to_string()))),
+    /// );
+    /// let mut message = slr::Message::new();
+    /// message.add_str(35, "0");
+    /// assert!(dict.validate_message(&message).is_err());
+    /// ```
+    pub fn add_validator<S: AsRef<str>>(&mut self, msg_type: S, validator: Validator) {
+        self.validators
+            .0
+            .entry(msg_type.as_ref().to_string())
+            .or_default()
+            .push(Arc::from(validator));
+    }
+
+    /// Returns a [JSON Schema](https://json-schema.org/) of messages with
+    /// `MsgType <35>` equal to `msg_type`, as represented by
+    /// [`json::Codec`](crate::codec::json::Codec): fields are strings,
+    /// repeating groups are arrays of objects and multiple values are arrays
+    /// of strings. `None` if `self` doesn't define `msg_type`.
+    ///
+    /// `BodyLength <9>` and `CheckSum <10>` are never required, as the JSON
+    /// codec doesn't need them. FIX 5.0+ messages take their header and trailer
+    /// from FIXT.1.1, which is not available in `self`.
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix44);
+    /// let schema = dict.json_schema("0").unwrap();
+    /// assert_eq!(schema["title"], "Heartbeat");
+    /// ```
+    pub fn json_schema(&self, msg_type: &str) -> Option<serde_json::Value> {
+        let message = self.message_by_msgtype(msg_type)?;
+        let component_schema = |name: &str| match self.component_by_name(name) {
+            Some(component) => json_schema_of_layout(component.items()),
+            None => json!({ "type": "object" }),
+        };
+        let mut header = component_schema("StandardHeader");
+        header["properties"]["MsgType"] = json!({ "const": msg_type });
+        Some(json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": message.name(),
+            "type": "object",
+            "properties": {
+                "Header": header,
+                "Body": json_schema_of_layout(message.layout()),
+                "Trailer": component_schema("StandardTrailer"),
+            },
+            "required": ["Header", "Body", "Trailer"],
+        }))
+    }
+
+    /// Compares `self` against `other`, e.g. an older FIX version against a
+    /// newer one. Fields are matched by tag and messages by `MsgType <35>`;
+    /// "added" items are those which `other` defines and `self` doesn't.
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    ///
+    /// let fix42 = Dictionary::from_version(Version::Fix42);
+    /// let fix44 = Dictionary::from_version(Version::Fix44);
+    /// let diff = fix42.diff(&fix44);
+    /// // `TradeCaptureReport <AE>` was introduced in FIX 4.3.
+    /// assert!(diff.messages_added.contains(&"AE".to_string()));
+    /// ```
+    pub fn diff(&self, other: &Dictionary) -> DictDiff {
+        let mut diff = DictDiff::default();
+        for field in other.iter_fields() {
+            match self.field_by_tag(field.tag()) {
+                None => diff.fields_added.push(field.tag()),
+                Some(old) if !old.same_definition(&field) => diff.fields_changed.push(field.tag()),
+                Some(_) => {}
+            }
+        }
+        for field in self.iter_fields() {
+            if other.field_by_tag(field.tag()).is_none() {
+                diff.fields_removed.push(field.tag());
+            }
+        }
+        for message in other.iter_messages() {
+            let msg_type = message.msg_type().to_string();
+            match self.message_by_msgtype(&msg_type) {
+                None => diff.messages_added.push(msg_type),
+                Some(old) if !old.same_definition(&message) => diff.messages_changed.push(msg_type),
+                Some(_) => {}
+            }
+        }
+        for message in self.iter_messages() {
+            if other.message_by_msgtype(message.msg_type()).is_none() {
+                diff.messages_removed.push(message.msg_type().to_string());
+            }
+        }
+        diff.fields_added.sort_unstable();
+        diff.fields_removed.sort_unstable();
+        diff.fields_changed.sort_unstable();
+        diff.messages_added.sort();
+        diff.messages_removed.sort();
+        diff.messages_changed.sort();
+        diff
+    }
+
+    /// Checks that repeating groups and their counter fields agree with each
+    /// other, i.e. that every group is counted by a `NumInGroup` field and that
+    /// every `NumInGroup` field counts some group. Otherwise, decoding is
+    /// ambiguous. Call this after loading a custom definition file. FIX 4.2 and
+    /// earlier have no `NumInGroup` data type, so plain `int` counters are fine
+    /// in dictionaries without any `NumInGroup` field.
+    ///
+    /// Components and fields which are referenced by some layout but never
+    /// defined are reported as well. Fields are left out of the layouts that
+    /// reference them, and components are empty.
+    ///
+    /// Dangling references come first and are sorted by name, all other
+    /// warnings are sorted by tag.
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix44);
+    /// assert!(dict.validate_self().is_empty());
+    /// ```
+    pub fn validate_self(&self) -> Vec<DictConsistencyWarning> {
+        let mut counters = BTreeSet::new();
+        for item in self.layout_items.iter() {
+            if let LayoutItemKindData::Group(counter, _) = item.kind {
+                counters.insert(counter);
+            }
+        }
+        let has_num_in_group = self
+            .fields
+            .iter()
+            .any(|field| field.exact_data_type == dt::DataType::NumInGroup);
+        let mut warnings = Vec::new();
+        for counter in counters.iter() {
+            let field = &self.fields[*counter as usize];
+            let is_valid_counter = match field.exact_data_type {
+                dt::DataType::NumInGroup => true,
+                dt::DataType::Int => !has_num_in_group,
+                _ => false,
+            };
+            if !is_valid_counter {
+                warnings.push(DictConsistencyWarning::GroupCounterNotNumInGroup(field.tag));
+            }
+        }
+        for (iid, field) in self.fields.iter().enumerate() {
+            if field.exact_data_type == dt::DataType::NumInGroup
+                && !counters.contains(&(iid as u32))
+            {
+                warnings.push(DictConsistencyWarning::NumInGroupWithoutGroup(field.tag));
+            }
+        }
+        warnings.sort_by_key(|warning| match warning {
+            DictConsistencyWarning::GroupCounterNotNumInGroup(tag)
+            | DictConsistencyWarning::NumInGroupWithoutGroup(tag) => *tag,
+            _ => 0,
+        });
+        let mut dangling_references = self.dangling_references.clone();
+        dangling_references.extend(warnings);
+        dangling_references
+    }
+
+    /// Summarizes `message` for compliance logs: who sent it to whom, when and
+    /// what it is, along with all of its business fields (i.e. neither in
+    /// `StandardHeader` nor in `StandardTrailer`) by name. Anything that is
+    /// missing or malformed is left out of the [`AuditRecord`].
+    pub fn audit_record(&self, message: &slr::Message) -> AuditRecord {
+        let string = |tag: i64| match message.fields.get(&tag) {
+            Some(slr::FixFieldValue::String(s)) => Some(s.clone()),
+            Some(slr::FixFieldValue::Value(value)) => Some(value.to_string()),
+            _ => None,
+        };
+        let msg_type = message
+            .msg_type()
+            .and_then(|msg_type| self.message_by_msgtype(msg_type));
+        let header = self.component_by_name("StandardHeader");
+        let trailer = self.component_by_name("StandardTrailer");
+        let is_business_field = |field: &Field| {
+            !header.as_ref().is_some_and(|c| c.contains_field(field))
+                && !trailer.as_ref().is_some_and(|c| c.contains_field(field))
+        };
+        let mut fields = Vec::new();
+        for (tag, value) in message.fields.iter() {
+            let field = match self.field_by_tag(*tag as u32) {
+                Some(field) if is_business_field(&field) => field,
+                Some(_) => continue,
+                None => {
+                    if let 8 | 9 | 10 | 35 = *tag {
+                        continue;
+                    }
+                    fields.push(AuditField {
+                        tag: *tag as u32,
+                        name: None,
+                        value: audit_value(value),
+                        description: None,
+                    });
+                    continue;
+                }
+            };
+            let value = audit_value(value);
+            let description = field.enums().and_then(|mut enums| {
+                enums
+                    .find(|e| e.value() == value)
+                    .map(|e| e.description().to_string())
+            });
+            fields.push(AuditField {
+                tag: *tag as u32,
+                name: Some(field.name().to_string()),
+                value,
+                description,
+            });
+        }
+        AuditRecord {
+            sender_comp_id: string(49),
+            target_comp_id: string(56),
+            msg_type: msg_type.map(|msg_type| msg_type.name().to_string()),
+            msg_seq_num: message.seq_num(),
+            sending_time: message
+                .fields
+                .get(&52)
+                .and_then(|value| slr::utc_timestamp(52, value).ok()),
+            fields,
+        }
+    }
+
+    /// Returns the tags of the fields that can appear in entries of the
+    /// repeating groups counted by the field with tag `counter_tag`, across all
+    /// messages and components. Only top-level entry fields are included, i.e.
+    /// nested groups are represented by their counter field. `None` if no group
+    /// is counted by `counter_tag`.
+    pub(crate) fn group_entry_tags(&self, counter_tag: u32) -> Option<BTreeSet<u32>> {
+        let mut tags = BTreeSet::new();
+        let mut is_counter = false;
+        for data in self.layout_items.iter() {
+            if let LayoutItemKindData::Group(counter, range) = &data.kind {
+                if self.fields[*counter as usize].tag == counter_tag {
+                    is_counter = true;
+                    let items = self.layout_items[range.start as usize..range.end as usize]
+                        .iter()
+                        .map(|data| LayoutItem(self, data));
+                    slr::collect_layout_tags(items, &mut tags);
+                }
+            }
+        }
+        if is_counter {
+            Some(tags.into_iter().map(|tag| tag as u32).collect())
+        } else {
+            None
+        }
+    }
+
     /// Returns an [`Iterator`](Iterator) over all [`DataType`](DataType) defined
     /// in `self`. Items are in no particular order.
     ///
@@ -323,6 +740,54 @@ impl Dictionary {
             .map(move |data| Field(&self, data))
     }
 
+    /// Returns all fields of `self` with the given data type, as declared in
+    /// the definition file, in no particular order. Unlike
+    /// [`Field::basetype`], this tells apart data types that are encoded the
+    /// same way (e.g. `UTCTimestamp` and `String`).
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    /// use fefix::dt::DataType;
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix44);
+    /// let prices = dict.fields_of_type(DataType::Price);
+    /// assert!(prices.iter().any(|field| field.name() == "Price"));
+    /// ```
+    pub fn fields_of_type(&self, data_type: dt::DataType) -> Vec<Field> {
+        self.iter_fields()
+            .filter(|field| field.1.exact_data_type == data_type)
+            .collect()
+    }
+
+    /// Returns the field that holds the length of the data field with tag
+    /// `data_tag`, e.g. `RawDataLength <95>` for `RawData <96>`. `None` if
+    /// `data_tag` is not a data field or has no length field.
+    ///
+    /// Definition files don't link the two, so length fields are found by
+    /// name: they are named after their data field, followed by either
+    /// `Length` or `Len`.
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix44);
+    /// assert_eq!(dict.length_field_for(96).unwrap().name(), "RawDataLength");
+    /// assert_eq!(dict.length_field_for(355).unwrap().name(), "EncodedTextLen");
+    /// assert!(dict.length_field_for(95).is_none());
+    /// ```
+    pub fn length_field_for(&self, data_tag: u32) -> Option<Field> {
+        let data_field = self.field_by_tag(data_tag)?;
+        if data_field.1.exact_data_type != dt::DataType::Data {
+            return None;
+        }
+        ["Length", "Len"]
+            .iter()
+            .filter_map(|suffix| self.field_by_name(format!("{}{}", data_field.name(), suffix)))
+            .find(|field| field.1.exact_data_type == dt::DataType::Length)
+    }
+
     /// Returns an [`Iterator`] over this [`Dictionary`]'s components. Items are in
     /// no particular order.
     pub fn iter_components(&self) -> impl Iterator<Item = Component> {
@@ -480,6 +945,10 @@ struct FieldData {
     tag: u32,
     /// The datatype of the field.
     data_type_iid: InternalId,
+    /// The datatype of the field as declared in the definition file, which
+    /// may be more specific than `data_type_iid` (e.g. `UTCTimestamp` rather
+    /// than `String`).
+    exact_data_type: dt::DataType,
     /// The associated data field. If given, this field represents the length of
     /// the referenced data field
     associated_data_tag: Option<usize>,
@@ -551,9 +1020,15 @@ impl<'a> Field<'a> {
         self.data_type().basetype()
     }
 
+    /// Returns the datatype of `self` exactly as declared in the definition
+    /// file, e.g. [`dt::DataType::SeqNum`] rather than [`dt::DataType::Int`].
+    pub(crate) fn exact_data_type(&self) -> dt::DataType {
+        self.1.exact_data_type
+    }
+
     /// Returns the name of `self`. Field names are unique across each FIX
     /// [`Dictionary`].
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &'a str {
         self.1.name.as_str()
     }
 
@@ -563,6 +1038,28 @@ impl<'a> Field<'a> {
         self.1.tag
     }
 
+    /// Returns `true` if `self` is deprecated in the FIX version of its
+    /// [`Dictionary`], `false` otherwise.
+    ///
+    /// QuickFIX definition files don't carry this information, so it comes
+    /// from the `deprecated` attributes of the FIX Repository (2010 Edition),
+    /// which is shipped in `resources/repositories`. Dictionaries of unknown
+    /// FIX versions have no deprecated fields.
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix44);
+    /// assert!(dict.field_by_tag(239).unwrap().is_deprecated());
+    /// assert!(!dict.field_by_tag(35).unwrap().is_deprecated());
+    /// ```
+    pub fn is_deprecated(&self) -> bool {
+        self.0
+            .fix_version()
+            .is_some_and(|version| deprecated_tags(version).contains(&self.1.tag))
+    }
+
     pub fn enums(&self) -> Option<impl Iterator<Item = FieldEnum>> {
         self.1
             .value_restrictions
@@ -570,6 +1067,11 @@ impl<'a> Field<'a> {
             .map(move |v| v.iter().map(move |f| FieldEnum(self.0, f)))
     }
 
+    /// Returns `true` if `self` and `other` have the same name and data type.
+    fn same_definition(&self, other: &Field) -> bool {
+        self.name() == other.name() && self.data_type().name() == other.data_type().name()
+    }
+
     /// Returns the [`Datatype`] of `self`.
     pub fn data_type(&self) -> Datatype {
         let data = self
@@ -581,6 +1083,360 @@ impl<'a> Field<'a> {
     }
 }
 
+/// Returns the JSON Schema of an object with the fields in `items`. Fields
+/// of components are flattened into the object itself.
+fn json_schema_of_layout<'a>(items: impl Iterator<Item = LayoutItem<'a>>) -> serde_json::Value {
+    let mut properties = serde_json::Map::new();
+    let mut required = Vec::new();
+    collect_json_schema_properties(items, true, &mut properties, &mut required);
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn collect_json_schema_properties<'a>(
+    items: impl Iterator<Item = LayoutItem<'a>>,
+    is_parent_required: bool,
+    properties: &mut serde_json::Map<String, serde_json::Value>,
+    required: &mut Vec<String>,
+) {
+    for item in items {
+        let is_required = is_parent_required && item.required();
+        let (name, schema) = match item.kind() {
+            LayoutItemKind::Field(field) => {
+                (field.name().to_string(), json_schema_of_field(&field))
+            }
+            LayoutItemKind::Component(component) => {
+                collect_json_schema_properties(
+                    component.items(),
+                    is_required,
+                    properties,
+                    required,
+                );
+                continue;
+            }
+            LayoutItemKind::Group(counter, entry_layout) => (
+                counter.name().to_string(),
+                json!({
+                    "type": "array",
+                    "items": json_schema_of_layout(entry_layout.into_iter()),
+                }),
+            ),
+        };
+        // The JSON codec doesn't need framing fields.
+        if is_required && name != "BodyLength" && name != "CheckSum" {
+            required.push(name.clone());
+        }
+        properties.insert(name, schema);
+    }
+}
+
+fn json_schema_of_field(field: &Field) -> serde_json::Value {
+    let enum_values: Option<Vec<String>> = field
+        .enums()
+        .map(|enums| enums.map(|e| e.value().to_string()).collect());
+    let mut schema = match enum_values {
+        Some(values) => json!({ "type": "string", "enum": values }),
+        None => json!({ "type": "string" }),
+    };
+    match field.basetype() {
+        dt::DataType::MultipleStringValue | dt::DataType::MultipleCharValue => {
+            json!({ "type": "array", "items": schema })
+        }
+        dt::DataType::Data => {
+            schema["contentEncoding"] = json!("base64");
+            schema
+        }
+        _ => schema,
+    }
+}
+
+fn collect_absent_optional_fields<'a>(
+    items: impl Iterator<Item = LayoutItem<'a>>,
+    is_parent_required: bool,
+    fields: &BTreeMap<i64, slr::FixFieldValue>,
+    absent: &mut Vec<u32>,
+) {
+    for item in items {
+        let is_required = is_parent_required && item.required();
+        let tag = match item.kind() {
+            LayoutItemKind::Field(field) => field.tag(),
+            LayoutItemKind::Group(counter, _) => counter.tag(),
+            LayoutItemKind::Component(component) => {
+                collect_absent_optional_fields(component.items(), is_required, fields, absent);
+                continue;
+            }
+        };
+        if !is_required && !fields.contains_key(&(tag as i64)) {
+            absent.push(tag);
+        }
+    }
+}
+
+/// Pushes an error to `errors` for each required field of `items` which is
+/// missing from `fields`, in definition order.
+fn validate_layout<'a>(
+    items: impl Iterator<Item = LayoutItem<'a>>,
+    fields: &BTreeMap<i64, slr::FixFieldValue>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for item in items {
+        match item.kind() {
+            LayoutItemKind::Field(field) => {
+                if item.required() && !fields.contains_key(&(field.tag() as i64)) {
+                    errors.push(ValidationError::MissingField(field.tag()));
+                }
+            }
+            LayoutItemKind::Component(component) => {
+                if item.required() {
+                    validate_layout(component.items(), fields, errors);
+                }
+            }
+            LayoutItemKind::Group(counter, entry_layout) => {
+                match fields.get(&(counter.tag() as i64)) {
+                    Some(slr::FixFieldValue::Group(entries)) => {
+                        for (index, entry) in entries.iter().enumerate() {
+                            let mut entry_errors = Vec::new();
+                            validate_layout(entry_layout.iter().cloned(), entry, &mut entry_errors);
+                            errors.extend(entry_errors.into_iter().map(|err| match err {
+                                ValidationError::MissingField(tag) => {
+                                    ValidationError::MissingGroupField {
+                                        group: counter.tag(),
+                                        index,
+                                        tag,
+                                    }
+                                }
+                                err => err,
+                            }));
+                        }
+                    }
+                    None if item.required() => {
+                        errors.push(ValidationError::MissingField(counter.tag()));
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// A business rule that can be registered with [`Dictionary::add_validator`].
+pub type Validator = Box<dyn Fn(&slr::Message) -> Result<(), ValidationError> + Send + Sync>;
+
+/// A [`Validator`] that is shared between clones of a [`Dictionary`].
+type SharedValidator = Arc<dyn Fn(&slr::Message) -> Result<(), ValidationError> + Send + Sync>;
+
+/// User-defined [`Validator`]s, by `MsgType <35>`. They are shared between
+/// clones of a [`Dictionary`].
+#[derive(Clone, Default)]
+struct Validators(HashMap<String, Vec<SharedValidator>>);
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(msg_type, v)| (msg_type, v.len())))
+            .finish()
+    }
+}
+
+/// The differences between two [`Dictionary`]s, as returned by
+/// [`Dictionary::diff`]. Fields are identified by tag and messages by
+/// `MsgType <35>`, all in ascending order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DictDiff {
+    /// Fields which are only defined in the newer [`Dictionary`].
+    pub fields_added: Vec<u32>,
+    /// Fields which are only defined in the older [`Dictionary`].
+    pub fields_removed: Vec<u32>,
+    /// Fields whose name or data type have changed.
+    pub fields_changed: Vec<u32>,
+    /// Messages which are only defined in the newer [`Dictionary`].
+    pub messages_added: Vec<String>,
+    /// Messages which are only defined in the older [`Dictionary`].
+    pub messages_removed: Vec<String>,
+    /// Messages whose name or layout have changed.
+    pub messages_changed: Vec<String>,
+}
+
+/// A summary of a message, as returned by [`Dictionary::audit_record`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    /// `SenderCompID <49>`.
+    pub sender_comp_id: Option<String>,
+    /// `TargetCompID <56>`.
+    pub target_comp_id: Option<String>,
+    /// The name of the message type, e.g. "Heartbeat".
+    pub msg_type: Option<String>,
+    /// `MsgSeqNum <34>`.
+    pub msg_seq_num: Option<u64>,
+    /// `SendingTime <52>`.
+    pub sending_time: Option<NaiveDateTime>,
+    /// All business fields, in tag order.
+    pub fields: Vec<AuditField>,
+}
+
+/// A business field of an [`AuditRecord`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditField {
+    /// The tag number of the field.
+    pub tag: u32,
+    /// The name of the field, unless it's unknown to the [`Dictionary`].
+    pub name: Option<String>,
+    /// The value of the field. Repeating groups are summarized by their number
+    /// of entries.
+    pub value: String,
+    /// The description of `value`, if the field has a code set, e.g. "NONE"
+    /// for `EncryptMethod <98>` equal to 0.
+    pub description: Option<String>,
+}
+
+fn audit_value(value: &slr::FixFieldValue) -> String {
+    match value {
+        slr::FixFieldValue::String(s) => s.clone(),
+        slr::FixFieldValue::Data(data) => String::from_utf8_lossy(data).into_owned(),
+        slr::FixFieldValue::Value(value) => value.to_string(),
+        slr::FixFieldValue::Group(entries) => entries.len().to_string(),
+        slr::FixFieldValue::MultiValue(values) => values.join(" "),
+    }
+}
+
+/// An inconsistency within a [`Dictionary`], as returned by
+/// [`Dictionary::validate_self`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DictConsistencyWarning {
+    /// A component with this name is referenced but never defined.
+    UndefinedComponent(String),
+    /// A field with this name is referenced but never defined.
+    UndefinedField(String),
+    /// The field with this tag counts the entries of a repeating group, but its
+    /// data type isn't `NumInGroup` (or `int`, for FIX 4.2 and earlier).
+    GroupCounterNotNumInGroup(u32),
+    /// The field with this tag is a `NumInGroup` but no repeating group is
+    /// counted by it.
+    NumInGroupWithoutGroup(u32),
+}
+
+/// The error type that can be returned by [`Dictionary::validate_message`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// `MsgType <35>` is missing or not defined in the [`Dictionary`].
+    InvalidMsgType,
+    /// A required field is missing.
+    MissingField(u32),
+    /// A required field is missing from the entry at `index` of the repeating
+    /// group with counter field `group`.
+    MissingGroupField { group: u32, index: usize, tag: u32 },
+    /// A [`Validator`] rejected the message for the given reason.
+    Custom(String),
+}
+
+impl ValidationError {
+    /// Describes `self` in plain English, by looking up in `dict` the names of
+    /// the fields involved and of the type of `message`, i.e. the message that
+    /// failed validation. For enumerated fields, allowed values are listed
+    /// too.
+    ///
+    /// ```
+    /// use fefix::app::{slr, Version};
+    /// use fefix::{Dictionary, ValidationError};
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix44);
+    /// let mut message = slr::Message::new();
+    /// message.add_str(35, "0");
+    /// let error = ValidationError::MissingField(112);
+    /// assert_eq!(
+    ///     error.explain(&dict, &message),
+    ///     "TestReqID <112> is required for Heartbeat <0>, but it's missing."
+    /// );
+    /// ```
+    pub fn explain(&self, dict: &Dictionary, message: &slr::Message) -> String {
+        let msg_type = match message.get_field(35) {
+            Some(slr::FixFieldValue::String(msg_type)) => Some(msg_type.as_str()),
+            _ => None,
+        };
+        match self {
+            ValidationError::InvalidMsgType => match msg_type {
+                Some(msg_type) => format!(
+                    "MsgType <35> is \"{}\", which is not defined in {}.",
+                    msg_type,
+                    dict.get_version()
+                ),
+                None => "MsgType <35> is missing.".to_string(),
+            },
+            ValidationError::MissingField(tag) => {
+                let message_name = msg_type
+                    .and_then(|msg_type| dict.message_by_msgtype(msg_type))
+                    .map(|message| format!("{} <{}>", message.name(), message.msg_type()))
+                    .unwrap_or_else(|| "this message".to_string());
+                format!(
+                    "{} is required for {}, but it's missing.{}",
+                    field_label(dict, *tag),
+                    message_name,
+                    allowed_values(dict, *tag)
+                )
+            }
+            ValidationError::MissingGroupField { group, index, tag } => format!(
+                "{} is required in each entry of the {} repeating group, but it's missing from entry #{}.{}",
+                field_label(dict, *tag),
+                field_label(dict, *group),
+                index,
+                allowed_values(dict, *tag)
+            ),
+            ValidationError::Custom(reason) => reason.clone(),
+        }
+    }
+}
+
+fn field_label(dict: &Dictionary, tag: u32) -> String {
+    match dict.field_by_tag(tag) {
+        Some(field) => format!("{} <{}>", field.name(), tag),
+        None => format!("<{}>", tag),
+    }
+}
+
+/// Returns a sentence listing the allowed values of the field `tag`, with a
+/// leading space, or an empty string if it's not an enumerated field.
+fn allowed_values(dict: &Dictionary, tag: u32) -> String {
+    let field = match dict.field_by_tag(tag) {
+        Some(field) => field,
+        None => return String::new(),
+    };
+    let enums = match field.enums() {
+        Some(enums) => enums,
+        None => return String::new(),
+    };
+    let values: Vec<String> = enums
+        .map(|e| format!("{} ({})", e.value(), e.description()))
+        .collect();
+    format!(" Allowed values are: {}.", values.join(", "))
+}
+
+/// Returns the tags of all fields that are deprecated in `version`, i.e. the
+/// fields with a `deprecated` attribute in the `Base/Fields.xml` file of
+/// `version` in the FIX Repository (2010 Edition).
+fn deprecated_tags(version: Version) -> &'static [u32] {
+    match version {
+        Version::Fix40 => &[125],
+        // The repository leaves `Fields.xml` of FIX.4.1 without any
+        // deprecation, although FIX.4.0 and FIX.4.2 agree that `CxlType <125>`
+        // was deprecated in FIX.4.1.
+        Version::Fix41 => &[125],
+        Version::Fix42 | Version::Fix43 => &[24, 51, 85, 86, 125],
+        Version::Fix44 => &[239, 243, 250],
+        Version::Fix50 => &[
+            111, 192, 193, 210, 226, 227, 239, 240, 243, 244, 245, 247, 250, 251, 252, 254, 275,
+            282, 540, 575, 640, 641, 642, 643, 818, 848, 849, 881,
+        ],
+        Version::Fix50SP1 | Version::Fix50SP2 => &[
+            89, 90, 91, 93, 111, 192, 193, 210, 226, 227, 239, 240, 243, 244, 245, 247, 250, 251,
+            252, 254, 275, 282, 540, 575, 640, 641, 642, 643, 687, 818, 848, 849, 852, 881,
+        ],
+        Version::Fixt11 => &[89, 90, 91, 93],
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct FieldRef {
     pub name: String,
@@ -590,7 +1446,9 @@ pub struct FieldRef {
 #[derive(Clone, Debug)]
 enum LayoutItemKindData {
     Component(u32),
-    Group(Range<u32>),
+    /// The counter field (e.g. `NoMDEntries <268>`) and the layout of each
+    /// group entry.
+    Group(u32, Range<u32>),
     Field(u32),
 }
 
@@ -607,7 +1465,9 @@ pub struct LayoutItem<'a>(&'a Dictionary, &'a LayoutItemData);
 #[derive(Debug)]
 pub enum LayoutItemKind<'a> {
     Component(Component<'a>),
-    Group(),
+    /// A repeating group, with its counter field and the layout of each of its
+    /// entries.
+    Group(Field<'a>, Vec<LayoutItem<'a>>),
     Field(Field<'a>),
 }
 
@@ -624,8 +1484,15 @@ impl<'a> LayoutItem<'a> {
                 self.0,
                 self.0.components.get(*n as usize).unwrap(),
             )),
-            LayoutItemKindData::Group(_range) => {
-                LayoutItemKind::Group() // FIXME
+            LayoutItemKindData::Group(counter, range) => {
+                let items = self.0.layout_items[range.start as usize..range.end as usize]
+                    .iter()
+                    .map(|data| LayoutItem(self.0, data))
+                    .collect();
+                LayoutItemKind::Group(
+                    Field(self.0, self.0.fields.get(*counter as usize).unwrap()),
+                    items,
+                )
             }
             LayoutItemKindData::Field(n) => {
                 LayoutItemKind::Field(Field(self.0, self.0.fields.get(*n as usize).unwrap()))
@@ -638,7 +1505,9 @@ impl<'a> LayoutItem<'a> {
             LayoutItemKindData::Component(n) => {
                 self.0.components.get(*n as usize).unwrap().name.as_str()
             }
-            LayoutItemKindData::Group(_range) => "",
+            LayoutItemKindData::Group(counter, _range) => {
+                self.0.fields.get(*counter as usize).unwrap().name.as_str()
+            }
             LayoutItemKindData::Field(n) => self.0.fields.get(*n as usize).unwrap().name.as_str(),
         }
     }
@@ -700,6 +1569,45 @@ impl<'a> Message<'a> {
             .iter()
             .map(move |data| LayoutItem(self.0, data))
     }
+
+    /// Returns `true` if `self` and `other` have the same name and their
+    /// layouts reference the same items, with the same requiredness.
+    fn same_definition(&self, other: &Message) -> bool {
+        self.name() == other.name()
+            && self
+                .layout()
+                .map(|item| (item.tag_text().to_string(), item.required()))
+                .eq(other
+                    .layout()
+                    .map(|item| (item.tag_text().to_string(), item.required())))
+    }
+
+    /// Returns all [`Component`]s directly referenced by `self`, in layout
+    /// order. Components nested inside other components or repeating groups
+    /// are not included.
+    ///
+    /// ```
+    /// use fefix::Dictionary;
+    /// use fefix::app::Version;
+    ///
+    /// let dict = Dictionary::from_version(Version::Fix44);
+    /// let new_order_single = dict.message_by_msgtype("D").unwrap();
+    /// let components = new_order_single.components();
+    /// assert!(components.iter().any(|c| c.name() == "Instrument"));
+    /// ```
+    pub fn components(&self) -> Vec<Component<'a>> {
+        let start = self.1.layout_items.start as usize;
+        let end = self.1.layout_items.end as usize;
+        self.0.layout_items[start..end]
+            .iter()
+            .filter_map(|data| match data.kind {
+                LayoutItemKindData::Component(n) => {
+                    Some(Component(self.0, self.0.components.get(n as usize).unwrap()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -739,14 +1647,48 @@ mod quickfix {
                 "STRING" => DataType::String,
                 "UTCTIMESTAMP" => DataType::String,
                 "CHAR" => DataType::Char,
+                "BOOLEAN" => DataType::Boolean,
                 "INT" => DataType::Int,
                 "LENGTH" => DataType::Int,
                 "SEQNUM" => DataType::Int,
                 "FLOAT" => DataType::Float,
+                "PRICE" => DataType::Price,
+                "QTY" => DataType::Qty,
                 "DATA" => DataType::Data,
+                "MULTIPLEVALUESTRING" => DataType::MultipleStringValue,
+                "MULTIPLESTRINGVALUE" => DataType::MultipleStringValue,
+                "MULTIPLECHARVALUE" => DataType::MultipleCharValue,
+                "NUMINGROUP" => DataType::NumInGroup,
+                "TENOR" => DataType::Tenor,
                 _ => DataType::String, // FIXME
             })
         }
+
+        /// Just like [`DataType::from_quickfix_name`], but data types that
+        /// are encoded the same way are kept apart.
+        fn from_quickfix_name_exact(name: &str) -> Self {
+            use dt::DataType;
+            match name {
+                "LENGTH" => DataType::Length,
+                "SEQNUM" => DataType::SeqNum,
+                "TAGNUM" => DataType::TagNum,
+                "DAYOFMONTH" => DataType::DayOfMonth,
+                "AMT" => DataType::Amt,
+                "PRICEOFFSET" => DataType::PriceOffset,
+                "PERCENTAGE" => DataType::Percentage,
+                "CURRENCY" => DataType::Currency,
+                "EXCHANGE" => DataType::Exchange,
+                "COUNTRY" => DataType::Country,
+                "MONTHYEAR" => DataType::MonthYear,
+                "LOCALMKTDATE" => DataType::LocalMktDate,
+                // Older FIX versions use different names for some data types.
+                "UTCTIMESTAMP" | "TIME" => DataType::UtcTimestamp,
+                "UTCTIMEONLY" => DataType::UtcTimeOnly,
+                "UTCDATEONLY" | "UTCDATE" => DataType::UtcDateOnly,
+                "XMLDATA" => DataType::XmlData,
+                name => Self::from_quickfix_name(name).unwrap(),
+            }
+        }
     }
 
     /// Adds all FIX datatypes to `dict`. This is necessary because QuickFIX
@@ -813,6 +1755,7 @@ mod quickfix {
             // potentially empty (FIX 5.0+).
             reader.add_component_with_name(reader.node_with_header, "StandardHeader");
             reader.add_component_with_name(reader.node_with_trailer, "StandardTrailer");
+            reader.add_undefined_components();
             Ok(reader.dict)
         }
 
@@ -841,13 +1784,20 @@ mod quickfix {
                         "No minor version attribute.".to_string(),
                     ))?;
             let version = format!("{}.{}.{}", version_type, version_major, version_minor);
+            let mut dict = Dictionary::new(version);
+            dict.service_pack = match root.attribute("servicepack") {
+                Some(service_pack) => service_pack.parse().map_err(|_| {
+                    ParseDictionaryError::InvalidData("Invalid servicepack attribute.".to_string())
+                })?,
+                None => 0,
+            };
             Ok(QuickFixReader {
                 node_with_header: find_tagged_child("header")?,
                 node_with_trailer: find_tagged_child("trailer")?,
                 node_with_messages: find_tagged_child("messages")?,
                 node_with_components: find_tagged_child("components")?,
                 node_with_fields: find_tagged_child("fields")?,
-                dict: Dictionary::new(version),
+                dict,
             })
         }
 
@@ -888,35 +1838,60 @@ mod quickfix {
         }
 
         fn add_component_with_name<S: AsRef<str>>(&mut self, node: roxmltree::Node, name: S) {
-            let iid = self.dict.components.len();
             let component =
                 ComponentData::definition_from_node_with_name(&mut self.dict, node, name.as_ref());
-            self.dict
-                .symbol_table
-                .insert(Key::ComponentByName(name.as_ref().to_string()), iid as u32);
-            self.dict.components.push(component);
+            // Components that were referenced before their definition already
+            // have a placeholder.
+            match self.dict.symbol(KeyRef::ComponentByName(name.as_ref())) {
+                Some(iid) => {
+                    let iid = *iid as usize;
+                    self.dict.components[iid] = component;
+                }
+                None => {
+                    let iid = self.dict.components.len();
+                    self.dict
+                        .symbol_table
+                        .insert(Key::ComponentByName(name.as_ref().to_string()), iid as u32);
+                    self.dict.components.push(component);
+                }
+            }
+        }
+
+        /// Takes note of all components which were referenced but not defined.
+        /// Their placeholders are left in place, so that lookups still work.
+        fn add_undefined_components(&mut self) {
+            let defined: HashSet<&str> = self
+                .node_with_components
+                .children()
+                .filter_map(|n| n.attribute("name"))
+                .chain(["StandardHeader", "StandardTrailer"].iter().copied())
+                .collect();
+            for component in self.dict.components.iter() {
+                if !defined.contains(component.name.as_str()) {
+                    self.dict
+                        .dangling_references
+                        .push(DictConsistencyWarning::UndefinedComponent(
+                            component.name.clone(),
+                        ));
+                }
+            }
+            self.dict.dangling_references.sort();
+            self.dict.dangling_references.dedup();
         }
 
         fn import_message(&mut self, node: roxmltree::Node) -> MessageData {
             debug_assert_eq!(node.tag_name().name(), "message");
             let category_iid = CategoryData::get_or_create_iid_from_ref(&mut self.dict, node);
-            let layout_start = self.dict.layout_items.len() as u32;
-            for child in node.children() {
-                if child.is_element() {
-                    // We don't need to generate new IID's because we're dealing
-                    // with ranges.
-                    let data = LayoutItemData::save_definition(&mut self.dict, child);
-                    self.dict.layout_items.push(data);
-                }
-            }
-            let layout_end = self.dict.layout_items.len() as u32;
+            // We don't need to generate new IID's because we're dealing
+            // with ranges.
+            let layout = save_layout(&mut self.dict, node);
             MessageData {
                 name: node.attribute("name").unwrap().to_string(),
                 msg_type: node.attribute("msgtype").unwrap().to_string(),
                 component_id: 0,
                 category_iid,
                 section_id: String::new(),
-                layout_items: layout_start..layout_end,
+                layout_items: layout,
                 abbr_name: None,
                 required: true,
                 elaboration: None,
@@ -928,10 +1903,13 @@ mod quickfix {
             debug_assert_eq!(node.tag_name().name(), "field");
             let data_type_iid = DatatypeData::get_or_create_iid_from_ref(&mut self.dict, node);
             let value_restrictions = value_restrictions_from_node(node, data_type_iid);
+            let exact_data_type =
+                dt::DataType::from_quickfix_name_exact(node.attribute("type").unwrap());
             FieldData {
                 name: node.attribute("name").unwrap().to_string(),
                 tag: node.attribute("number").unwrap().parse().unwrap(),
                 data_type_iid: data_type_iid,
+                exact_data_type,
                 associated_data_tag: None,
                 value_restrictions,
                 required: true,
@@ -949,19 +1927,12 @@ mod quickfix {
             node: roxmltree::Node,
             name: S,
         ) -> Self {
-            let layout_start = dict.layout_items.len() as u32;
-            for child in node.children() {
-                if child.is_element() {
-                    // We don't need IID's because we're dealing with ranges.
-                    let item = LayoutItemData::save_definition(dict, child);
-                    dict.layout_items.push(item);
-                }
-            }
-            let layout_end = dict.layout_items.len() as u32;
+            // We don't need IID's because we're dealing with ranges.
+            let layout = save_layout(dict, node);
             ComponentData {
                 id: 0,
                 component_type: ComponentType::Block,
-                layout_items_iid_range: layout_start..layout_end,
+                layout_items_iid_range: layout,
                 category_iid: 0, // FIXME
                 name: name.as_ref().to_string(),
                 abbr_name: None,
@@ -974,7 +1945,7 @@ mod quickfix {
             match dict.symbol(KeyRef::ComponentByName(name)) {
                 Some(x) => *x,
                 None => {
-                    let iid = dict.data_types.len() as u32;
+                    let iid = dict.components.len() as u32;
                     let data = ComponentData {
                         id: 0,
                         component_type: ComponentType::Block,
@@ -992,6 +1963,20 @@ mod quickfix {
         }
     }
 
+    /// Saves the layout items of all children of `node` and returns their
+    /// range. Children are saved before being pushed, so that the layouts of
+    /// nested groups don't end up in the middle of the range.
+    fn save_layout(dict: &mut Dictionary, node: roxmltree::Node) -> Range<InternalId> {
+        let items: Vec<LayoutItemData> = node
+            .children()
+            .filter(|n| n.is_element())
+            .filter_map(|child| LayoutItemData::save_definition(dict, child))
+            .collect();
+        let layout_start = dict.layout_items.len() as u32;
+        dict.layout_items.extend(items);
+        layout_start..dict.layout_items.len() as u32
+    }
+
     fn value_restrictions_from_node(
         node: roxmltree::Node,
         _datatype: InternalId,
@@ -1046,37 +2031,38 @@ mod quickfix {
     }
 
     impl LayoutItemData {
-        fn save_definition(dict: &mut Dictionary, node: roxmltree::Node) -> Self {
+        /// Returns [`None`] if `node` references a field which is not defined.
+        fn save_definition(dict: &mut Dictionary, node: roxmltree::Node) -> Option<Self> {
             // This processing step requires on fields being already present in
             // the dictionary.
             debug_assert_ne!(dict.fields.len(), 0);
             let name = node.attribute("name").unwrap();
             let required = node.attribute("required").unwrap() == "Y";
             let tag = node.tag_name().name();
-            let kind = match tag {
-                "field" => {
-                    let field_iid = dict.symbol(KeyRef::FieldByName(name)).unwrap();
-                    LayoutItemKindData::Field(*field_iid)
+            let field_iid = |dict: &mut Dictionary| match dict.symbol(KeyRef::FieldByName(name)) {
+                Some(iid) => Some(*iid),
+                None => {
+                    dict.dangling_references
+                        .push(DictConsistencyWarning::UndefinedField(name.to_string()));
+                    None
                 }
+            };
+            let kind = match tag {
+                "field" => LayoutItemKindData::Field(field_iid(dict)?),
                 "component" => {
                     // Components may *not* be already present.
                     let component_iid = ComponentData::get_or_create_iid_from_ref(dict, node);
                     LayoutItemKindData::Component(component_iid)
                 }
                 "group" => {
-                    let start_range = dict.layout_items.len() as u32;
-                    let items = node
-                        .children()
-                        .filter(|n| n.is_element())
-                        .map(|child| LayoutItemData::save_definition(dict, child))
-                        .count();
-                    LayoutItemKindData::Group(start_range..(start_range + items as u32))
+                    let counter_iid = field_iid(dict)?;
+                    LayoutItemKindData::Group(counter_iid, save_layout(dict, node))
                 }
                 _ => {
                     panic!("Invalid tag!")
                 }
             };
-            LayoutItemData { required, kind }
+            Some(LayoutItemData { required, kind })
         }
     }
 
@@ -1116,6 +2102,400 @@ mod test {
     use std::convert::TryInto;
     use std::collections::HashSet;
 
+    #[test]
+    fn service_packs_have_their_own_deprecated_fields() {
+        let is_deprecated = |version: Version, tag: u32| {
+            Dictionary::from_version(version)
+                .field_by_tag(tag)
+                .unwrap()
+                .is_deprecated()
+        };
+        // `RepoCollateralSecurityType <239>` is deprecated since FIX.4.4,
+        // `LegQty <687>` only since FIX.5.0 SP1.
+        assert!(is_deprecated(Version::Fix50, 239));
+        assert!(!is_deprecated(Version::Fix50, 687));
+        assert!(is_deprecated(Version::Fix50SP1, 687));
+        assert!(is_deprecated(Version::Fix50SP2, 687));
+        assert!(!is_deprecated(Version::Fix44, 687));
+    }
+
+    #[test]
+    fn audit_record_of_heartbeat() {
+        let dict = Dictionary::from_version(Version::Fix42);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.2");
+        message.add_str(35, "0");
+        message.add_str(49, "A");
+        message.add_str(56, "B");
+        message.add_int(34, 12);
+        message.add_str(52, "20100304-07:59:30");
+        message.add_str(112, "TEST");
+        message.add_str(10, "185");
+        let record = dict.audit_record(&message);
+        assert_eq!(record.sender_comp_id.as_deref(), Some("A"));
+        assert_eq!(record.target_comp_id.as_deref(), Some("B"));
+        assert_eq!(record.msg_type.as_deref(), Some("Heartbeat"));
+        assert_eq!(record.msg_seq_num, Some(12));
+        assert_eq!(
+            record.sending_time,
+            Some(NaiveDateTime::parse_from_str("20100304-07:59:30", "%Y%m%d-%H:%M:%S").unwrap())
+        );
+        assert_eq!(
+            record.fields,
+            vec![AuditField {
+                tag: 112,
+                name: Some("TestReqID".to_string()),
+                value: "TEST".to_string(),
+                description: None,
+            }]
+        );
+        message.add_str(35, "A");
+        message.add_str(98, "0");
+        let record = dict.audit_record(&message);
+        assert_eq!(record.msg_type.as_deref(), Some("Logon"));
+        assert_eq!(record.fields[0].description.as_deref(), Some("NONE"));
+    }
+
+    #[test]
+    fn embedded_dictionaries_are_consistent() {
+        // The QuickFIX definition of FIX 4.3 has a couple of known mistakes.
+        let fix43 = Dictionary::from_version(Version::Fix43);
+        assert_eq!(
+            fix43.validate_self(),
+            vec![
+                DictConsistencyWarning::NumInGroupWithoutGroup(82),
+                DictConsistencyWarning::GroupCounterNotNumInGroup(576),
+            ]
+        );
+        for version in Version::all() {
+            let dict = Dictionary::from_version(version);
+            if let Version::Fix43 = version {
+                continue;
+            }
+            assert_eq!(dict.validate_self(), vec![], "{}", version);
+        }
+    }
+
+    #[test]
+    fn inconsistent_groups_are_flagged() {
+        let spec = r#"
+<fix type='FIX' major='4' minor='4' servicepack='0'>
+ <header>
+  <field name='BeginString' required='Y' />
+  <field name='BodyLength' required='Y' />
+  <field name='MsgType' required='Y' />
+ </header>
+ <trailer>
+  <field name='CheckSum' required='Y' />
+ </trailer>
+ <messages>
+  <message name='Legs' msgtype='U1' msgcat='app'>
+   <group name='NoLegs' required='Y'>
+    <field name='LegSymbol' required='Y' />
+   </group>
+   <field name='NoOrders' required='N' />
+  </message>
+ </messages>
+ <components />
+ <fields>
+  <field number='8' name='BeginString' type='STRING' />
+  <field number='9' name='BodyLength' type='LENGTH' />
+  <field number='10' name='CheckSum' type='STRING' />
+  <field number='35' name='MsgType' type='STRING' />
+  <field number='73' name='NoOrders' type='NUMINGROUP' />
+  <field number='555' name='NoLegs' type='STRING' />
+  <field number='600' name='LegSymbol' type='STRING' />
+ </fields>
+</fix>
+"#;
+        let dict = Dictionary::save_definition_spec(spec).unwrap();
+        assert_eq!(
+            dict.validate_self(),
+            vec![
+                DictConsistencyWarning::NumInGroupWithoutGroup(73),
+                DictConsistencyWarning::GroupCounterNotNumInGroup(555),
+            ]
+        );
+    }
+
+    #[test]
+    fn dangling_references_are_flagged() {
+        let spec = r#"
+<fix type='FIX' major='4' minor='4' servicepack='0'>
+ <header>
+  <field name='BeginString' required='Y' />
+  <field name='BodyLength' required='Y' />
+  <field name='MsgType' required='Y' />
+ </header>
+ <trailer>
+  <field name='CheckSum' required='Y' />
+ </trailer>
+ <messages>
+  <message name='Custom' msgtype='U1' msgcat='app'>
+   <component name='Instrument' required='Y' />
+   <component name='Parties' required='N' />
+   <field name='Text' required='N' />
+  </message>
+  <message name='Other' msgtype='U2' msgcat='app'>
+   <component name='Instrument' required='Y' />
+  </message>
+ </messages>
+ <components>
+  <component name='Parties'>
+   <field name='PartyID' required='N' />
+  </component>
+ </components>
+ <fields>
+  <field number='8' name='BeginString' type='STRING' />
+  <field number='9' name='BodyLength' type='LENGTH' />
+  <field number='10' name='CheckSum' type='STRING' />
+  <field number='35' name='MsgType' type='STRING' />
+  <field number='58' name='Text' type='STRING' />
+ </fields>
+</fix>
+"#;
+        let dict = Dictionary::save_definition_spec(spec).unwrap();
+        assert_eq!(
+            dict.validate_self(),
+            vec![
+                DictConsistencyWarning::UndefinedComponent("Instrument".to_string()),
+                DictConsistencyWarning::UndefinedField("PartyID".to_string()),
+            ]
+        );
+        let message = dict.message_by_msgtype("U1").unwrap();
+        assert_eq!(message.layout().count(), 3);
+    }
+
+    #[test]
+    fn explain_missing_side() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = slr::Message::new();
+        message.add_str(35, "D");
+        assert_eq!(
+            ValidationError::MissingField(54).explain(&dict, &message),
+            "Side <54> is required for NewOrderSingle <D>, but it's missing. \
+             Allowed values are: 1 (BUY), 2 (SELL), 3 (BUY_MINUS), 4 (SELL_PLUS), \
+             5 (SELL_SHORT), 6 (SELL_SHORT_EXEMPT), 7 (UNDISCLOSED), 8 (CROSS), \
+             9 (CROSS_SHORT), A (CROSS_SHORT_EXEMPT), B (AS_DEFINED), C (OPPOSITE), \
+             D (SUBSCRIBE), E (REDEEM), F (LEND), G (BORROW)."
+        );
+        message.add_str(35, "ZZ");
+        assert_eq!(
+            ValidationError::InvalidMsgType.explain(&dict, &message),
+            "MsgType <35> is \"ZZ\", which is not defined in FIX.4.4."
+        );
+    }
+
+    #[test]
+    fn fix44_only_field_is_flagged_for_fix42() {
+        let fix42 = Dictionary::from_version(Version::Fix42);
+        let fix44 = Dictionary::from_version(Version::Fix44);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(11, "ORD1");
+        // `BenchmarkPrice <662>` was introduced in FIX 4.4.
+        message.add_str(662, "1.5");
+        assert!(fix44.validate_for_version(&message).is_empty());
+        assert_eq!(fix42.validate_for_version(&message), vec![662]);
+    }
+
+    #[test]
+    fn deprecated_fields_are_flagged_for_their_version() {
+        let fix44 = Dictionary::from_version(Version::Fix44);
+        let fix50sp1 = Dictionary::from_version(Version::Fix50SP1);
+        let mut message = slr::Message::new();
+        message.add_str(35, "AB");
+        message.add_str(11, "ORD1");
+        let mut leg = BTreeMap::new();
+        // `LegQty <687>` is deprecated since FIX.5.0 SP1.
+        leg.insert(687, slr::FixFieldValue::String("100".to_string()));
+        message.add_field(555u32, slr::FixFieldValue::Group(vec![leg]));
+        assert!(fix44.validate_for_version(&message).is_empty());
+        assert_eq!(fix50sp1.validate_for_version(&message), vec![687]);
+        assert!(fix50sp1.unknown_tags(&message).is_empty());
+    }
+
+    #[test]
+    fn unknown_tags_in_message() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "D");
+        message.add_str(9999, "custom");
+        assert_eq!(dict.unknown_tags(&message), vec![9999]);
+    }
+
+    fn md_entry(entry_type: Option<&str>, px: &str) -> slr::GroupEntry {
+        let mut entry = slr::GroupEntry::new();
+        if let Some(entry_type) = entry_type {
+            entry.insert(269, slr::FixFieldValue::String(entry_type.to_string()));
+        }
+        entry.insert(270, slr::FixFieldValue::String(px.to_string()));
+        entry
+    }
+
+    #[test]
+    fn fix44_new_order_single_references_instrument() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let message = dict.message_by_msgtype("D").unwrap();
+        let names: Vec<String> = message
+            .components()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        assert!(names.contains(&"Instrument".to_string()));
+        assert!(names.contains(&"OrderQtyData".to_string()));
+        assert!(!names.contains(&"StandardHeader".to_string()));
+    }
+
+    #[test]
+    fn fix50_parties_nest_sub_parties() {
+        let dict = Dictionary::from_version(Version::Fix50);
+        let parties = dict.component_by_name("Parties").unwrap();
+        let mut nested_components = Vec::new();
+        for item in parties.items() {
+            if let LayoutItemKind::Group(counter, items) = item.kind() {
+                assert_eq!(counter.tag(), 453);
+                for item in items {
+                    if let LayoutItemKind::Component(component) = item.kind() {
+                        nested_components.push(component.name().to_string());
+                    }
+                }
+            }
+        }
+        assert_eq!(nested_components, vec!["PtysSubGrp".to_string()]);
+        let sub_parties = dict.component_by_name("PtysSubGrp").unwrap();
+        assert!(sub_parties.items().any(|item| match item.kind() {
+            LayoutItemKind::Group(counter, _) => counter.tag() == 802,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn heartbeat_absent_optional_fields() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "0");
+        let absent: Vec<u32> = dict
+            .absent_optional_fields(&message, "0")
+            .iter()
+            .map(|field| field.tag())
+            .collect();
+        assert_eq!(absent, vec![112]);
+        message.add_str(112, "TEST");
+        assert!(dict.absent_optional_fields(&message, "0").is_empty());
+    }
+
+    #[test]
+    fn validate_message_checks_group_entries() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let mut message = slr::Message::new();
+        message.add_str(8, "FIX.4.4");
+        message.add_str(35, "W");
+        message.add_str(55, "ESU6");
+        assert_eq!(
+            dict.validate_message(&message),
+            Err(ValidationError::MissingField(268))
+        );
+        let entries = vec![md_entry(Some("0"), "1.50"), md_entry(Some("1"), "1.75")];
+        message.add_field(268, slr::FixFieldValue::Group(entries));
+        assert_eq!(dict.validate_message(&message), Ok(()));
+        let entries = vec![md_entry(Some("0"), "1.50"), md_entry(None, "1.75")];
+        message.add_field(268, slr::FixFieldValue::Group(entries));
+        assert_eq!(
+            dict.validate_message(&message),
+            Err(ValidationError::MissingGroupField {
+                group: 268,
+                index: 1,
+                tag: 269
+            })
+        );
+    }
+
+    #[test]
+    fn validator_runs_after_structural_validation() {
+        let mut dict = Dictionary::from_version(Version::Fix44);
+        // Limit orders must have a price.
+        dict.add_validator(
+            "D",
+            Box::new(|message: &slr::Message| {
+                let is_limit = message.get_field(40) == Some(&slr::FixFieldValue::from('2'));
+                if is_limit && message.get_field(44).is_none() {
+                    Err(ValidationError::Custom("Limit order without Price".to_string()))
+                } else {
+                    Ok(())
+                }
+            }),
+        );
+        let mut message = slr::Message::new();
+        message.add_str(35, "D");
+        for tag in &[11, 21, 55, 60] {
+            message.add_str(*tag, "x");
+        }
+        message.add_field(54, slr::FixFieldValue::from('1'));
+        assert_eq!(
+            dict.validate_message(&message),
+            Err(ValidationError::MissingField(40))
+        );
+        message.add_field(40, slr::FixFieldValue::from('2'));
+        assert_eq!(
+            dict.validate_message(&message),
+            Err(ValidationError::Custom("Limit order without Price".to_string()))
+        );
+        message.add_str(44, "1.5");
+        assert_eq!(dict.validate_message(&message), Ok(()));
+    }
+
+    #[test]
+    fn diff_from_fix42_to_fix44() {
+        let fix42 = Dictionary::from_version(Version::Fix42);
+        let fix44 = Dictionary::from_version(Version::Fix44);
+        let diff = fix42.diff(&fix44);
+        // `NoPartyIDs <453>` and `TradeCaptureReport <AE>` were introduced in
+        // FIX 4.3.
+        assert!(diff.fields_added.contains(&453));
+        assert!(diff.messages_added.contains(&"AE".to_string()));
+        assert!(!diff.fields_added.contains(&35));
+        assert!(!diff.fields_removed.contains(&35));
+        assert!(diff.messages_changed.contains(&"D".to_string()));
+        assert_eq!(fix44.diff(&fix44), DictDiff::default());
+    }
+
+    #[test]
+    fn fix44_utc_timestamp_fields() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let tags: Vec<u32> = dict
+            .fields_of_type(dt::DataType::UtcTimestamp)
+            .iter()
+            .map(|field| field.tag())
+            .collect();
+        // `SendingTime <52>` and `TransactTime <60>`.
+        assert!(tags.contains(&52));
+        assert!(tags.contains(&60));
+        assert!(!tags.contains(&55));
+        let multiple_values = dict.fields_of_type(dt::DataType::MultipleStringValue);
+        assert!(multiple_values.iter().any(|field| field.tag() == 18));
+    }
+
+    #[test]
+    fn heartbeat_json_schema_requires_standard_header() {
+        let dict = Dictionary::from_version(Version::Fix44);
+        let schema = dict.json_schema("0").unwrap();
+        let header = &schema["properties"]["Header"];
+        let required = header["required"].as_array().unwrap();
+        let standard_header = ["BeginString", "MsgType", "SenderCompID", "TargetCompID"];
+        for name in standard_header.iter() {
+            assert!(required.contains(&json!(name)), "{} is not required", name);
+        }
+        assert!(required.contains(&json!("MsgSeqNum")));
+        assert!(!required.contains(&json!("BodyLength")));
+        assert_eq!(header["properties"]["MsgType"], json!({ "const": "0" }));
+        assert!(schema["properties"]["Body"]["properties"]["TestReqID"].is_object());
+        assert!(dict.json_schema("ZZZ").is_none());
+    }
+
     #[test]
     fn msg_type_conversion() {
         fn prop(val: u16) -> bool {
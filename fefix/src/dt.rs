@@ -1,3 +1,5 @@
+use crate::codec::fast::Decimal;
+use chrono::{NaiveDateTime, Timelike};
 use std::convert::{From, TryInto};
 use std::fmt;
 use std::fmt::Write;
@@ -138,6 +140,10 @@ pub enum DataType {
     /// string field representing a country using ISO 3166 Country code (2
     /// character) values (see Appendix 6-B).
     Country,
+    /// string field representing a time interval, as a unit followed by an
+    /// amount. Valid units: D = Day, M = Month, W = Week, Y = Year (e.g. `D5`,
+    /// `M3`, `Y1`).
+    Tenor,
 }
 
 impl DataType {
@@ -189,9 +195,54 @@ impl DataType {
             DataType::Country => "Country",
             DataType::MultipleCharValue => "MultipleCharValue",
             DataType::XmlData => "XMLData",
+            DataType::Tenor => "Tenor",
         }
     }
 
+    /// Returns the maximum length in bytes of values of `self`, if fixed by
+    /// the FIX specification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fefix::dt::DataType;
+    ///
+    /// assert_eq!(DataType::Char.max_len(), Some(1));
+    /// assert_eq!(DataType::Currency.max_len(), Some(3));
+    /// assert_eq!(DataType::String.max_len(), None);
+    /// ```
+    pub fn max_len(&self) -> Option<usize> {
+        match self {
+            DataType::Char | DataType::Boolean => Some(1),
+            // ISO 3166 and ISO 4217 codes, respectively.
+            DataType::Country => Some(2),
+            DataType::Currency => Some(3),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `self` is an `int`-based datatype whose values must be
+    /// positive, and thus can't have a sign character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fefix::dt::DataType;
+    ///
+    /// assert!(DataType::SeqNum.is_unsigned());
+    /// assert!(!DataType::Int.is_unsigned());
+    /// ```
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            DataType::DayOfMonth
+                | DataType::Length
+                | DataType::NumInGroup
+                | DataType::SeqNum
+                | DataType::TagNum
+        )
+    }
+
     pub fn decode(&self, data: &[u8]) -> Option<DataTypeValue> {
         match self {
             DataType::Int => {
@@ -237,6 +288,8 @@ impl DataType {
             DataType::XmlData => Some(DataTypeValue::XmlData(XmlData(
                 std::str::from_utf8(data).unwrap().to_string(),
             ))),
+            DataType::Tenor => Tenor::parse(data).map(DataTypeValue::Tenor),
+            DataType::UtcTimestamp => UtcTimestamp::parse(data).map(DataTypeValue::UtcTimestamp),
             _ => unimplemented!(),
         }
     }
@@ -271,6 +324,7 @@ pub enum DataTypeValue {
     UtcTimestamp(UtcTimestamp),
     XmlData(XmlData),
     Country(Country),
+    Tenor(Tenor),
 }
 
 impl DataTypeValue {
@@ -308,7 +362,11 @@ impl fmt::Display for DataTypeValue {
             Self::Char(Char(c)) => c.fmt(f),
             Self::String(String(s)) => s.fmt(f),
             Self::Int(Int(i)) => i.fmt(f),
+            Self::Price(Price(price)) => price.fmt(f),
+            Self::Qty(Qty(qty)) => qty.fmt(f),
             Self::Length(Length(l)) => l.fmt(f),
+            Self::Tenor(tenor) => tenor.fmt(f),
+            Self::UtcTimestamp(timestamp) => timestamp.fmt(f),
             _ => Ok(())
         }
     }
@@ -448,8 +506,115 @@ impl DerivedDataType for UtcTimeOnly {
     type Primitive = String;
 }
 
+/// How many fractional second digits a [`UtcTimestamp`] has.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimestampPrecision {
+    /// `HH:MM:SS`.
+    Seconds,
+    /// `HH:MM:SS.sss`.
+    Millis,
+    /// `HH:MM:SS.ssssss`.
+    Micros,
+    /// `HH:MM:SS.sssssssss`.
+    Nanos,
+}
+
+impl TimestampPrecision {
+    fn from_digits(digits: usize) -> Option<Self> {
+        match digits {
+            0 => Some(TimestampPrecision::Seconds),
+            3 => Some(TimestampPrecision::Millis),
+            6 => Some(TimestampPrecision::Micros),
+            9 => Some(TimestampPrecision::Nanos),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of fractional second digits of `self`.
+    pub fn digits(&self) -> usize {
+        match self {
+            TimestampPrecision::Seconds => 0,
+            TimestampPrecision::Millis => 3,
+            TimestampPrecision::Micros => 6,
+            TimestampPrecision::Nanos => 9,
+        }
+    }
+}
+
+/// String field representing Time/date combination represented in UTC
+/// (Universal Time Coordinated, also known as "GMT") in either
+/// `YYYYMMDD-HH:MM:SS` (whole seconds) or `YYYYMMDD-HH:MM:SS.sss*` format,
+/// with 3, 6 or 9 fractional second digits. The precision of the original
+/// value is remembered, so that it's formatted back exactly as it was.
 #[derive(Debug, Clone, PartialEq)]
-pub struct UtcTimestamp(i64);
+pub struct UtcTimestamp {
+    timestamp: NaiveDateTime,
+    precision: TimestampPrecision,
+}
+
+impl UtcTimestamp {
+    /// Creates a new [`UtcTimestamp`], which will be formatted with
+    /// `precision`. Any finer detail of `timestamp` is truncated.
+    pub fn new(timestamp: NaiveDateTime, precision: TimestampPrecision) -> Self {
+        Self {
+            timestamp,
+            precision,
+        }
+    }
+
+    /// Parses `data` as a [`UtcTimestamp`]. Returns `None` if `data` is
+    /// malformed or has neither 0, 3, 6 nor 9 fractional second digits.
+    ///
+    /// ```
+    /// use fefix::dt::{TimestampPrecision, UtcTimestamp};
+    ///
+    /// let timestamp = UtcTimestamp::parse(b"20100304-07:59:30.123456").unwrap();
+    /// assert_eq!(timestamp.precision(), TimestampPrecision::Micros);
+    /// assert_eq!(timestamp.to_string(), "20100304-07:59:30.123456");
+    /// assert_eq!(UtcTimestamp::parse(b"20100304-07:59:30.12"), None);
+    /// ```
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        const LEN_WITHOUT_FRACTION: usize = "YYYYMMDD-HH:MM:SS".len();
+        let s = std::str::from_utf8(data).ok()?;
+        let digits = match s.get(LEN_WITHOUT_FRACTION..)? {
+            "" => 0,
+            fraction => {
+                let digits = fraction.strip_prefix('.')?;
+                if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return None;
+                }
+                digits.len()
+            }
+        };
+        let precision = TimestampPrecision::from_digits(digits)?;
+        let timestamp = NaiveDateTime::parse_from_str(s, "%Y%m%d-%H:%M:%S%.f").ok()?;
+        Some(Self::new(timestamp, precision))
+    }
+
+    /// Returns the date and time of `self`.
+    pub fn timestamp(&self) -> NaiveDateTime {
+        self.timestamp
+    }
+
+    /// Returns how many fractional second digits `self` is formatted with.
+    pub fn precision(&self) -> TimestampPrecision {
+        self.precision
+    }
+}
+
+impl fmt::Display for UtcTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.timestamp.format("%Y%m%d-%H:%M:%S").fmt(f)?;
+        let digits = self.precision.digits();
+        if digits > 0 {
+            // Leap seconds are represented by nanoseconds past one billion.
+            let nanos = self.timestamp.nanosecond() % 1_000_000_000;
+            let fraction = nanos / 10u32.pow(9 - digits as u32);
+            write!(f, ".{:0width$}", fraction, width = digits)?;
+        }
+        Ok(())
+    }
+}
 
 impl DerivedDataType for UtcTimestamp {
     type Primitive = String;
@@ -458,6 +623,90 @@ impl DerivedDataType for UtcTimestamp {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Country([u8; 2]);
 
+/// The time unit of a [`Tenor`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TenorUnit {
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+impl TenorUnit {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            b'D' => Some(TenorUnit::Days),
+            b'W' => Some(TenorUnit::Weeks),
+            b'M' => Some(TenorUnit::Months),
+            b'Y' => Some(TenorUnit::Years),
+            _ => None,
+        }
+    }
+
+    fn as_char(&self) -> char {
+        match self {
+            TenorUnit::Days => 'D',
+            TenorUnit::Weeks => 'W',
+            TenorUnit::Months => 'M',
+            TenorUnit::Years => 'Y',
+        }
+    }
+}
+
+/// String field (see definition of "String" above) representing a time
+/// interval, e.g. `M3` for three months.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tenor {
+    unit: TenorUnit,
+    amount: u32,
+}
+
+impl Tenor {
+    /// Creates a new [`Tenor`] of `amount` times `unit`.
+    pub fn new(unit: TenorUnit, amount: u32) -> Self {
+        Self { unit, amount }
+    }
+
+    /// Parses `data` as a [`Tenor`], e.g. `b"M3"`. Returns `None` if `data` is
+    /// malformed.
+    ///
+    /// ```
+    /// use fefix::dt::{Tenor, TenorUnit};
+    ///
+    /// assert_eq!(Tenor::parse(b"M3"), Some(Tenor::new(TenorUnit::Months, 3)));
+    /// assert_eq!(Tenor::parse(b"X9"), None);
+    /// ```
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (unit, digits) = data.split_first()?;
+        let unit = TenorUnit::from_byte(*unit)?;
+        if digits.is_empty() || !digits.iter().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        let amount = std::str::from_utf8(digits).ok()?.parse().ok()?;
+        Some(Self { unit, amount })
+    }
+
+    /// Returns the time unit of `self`.
+    pub fn unit(&self) -> TenorUnit {
+        self.unit
+    }
+
+    /// Returns how many time units `self` spans.
+    pub fn amount(&self) -> u32 {
+        self.amount
+    }
+}
+
+impl fmt::Display for Tenor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.unit.as_char(), self.amount)
+    }
+}
+
+impl DerivedDataType for Tenor {
+    type Primitive = String;
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Language([u8; 3]);
 
@@ -501,16 +750,20 @@ impl PrimitiveDataType for Float {}
 /// whole number (no decimal places) of "shares" (securities denominated in whole
 /// units) or a decimal value containing decimal places for non-share quantity
 /// asset classes (securities denominated in fractional units).
+///
+/// Quantities are fixed-point numbers, so that they never lose precision.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Qty(Float);
+pub struct Qty(pub Decimal);
 
 /// Float field (see definition of "float" above) representing a price. Note the
 /// number of decimal places may vary. For certain asset classes prices may be
 /// negative values. For example, options strategies can be negative under
 /// certain market conditions. Refer to Volume 7: FIX Usage by Product (460) for
 /// asset classes that support negative price values.
+///
+/// Prices are fixed-point numbers, so that they never lose precision.
 #[derive(Debug, Clone, PartialEq)]
-pub struct Price(Float);
+pub struct Price(pub Decimal);
 
 /// Float field (see definition of "float" above) representing a price offset,
 /// which can be mathematically added to a "Price". Note the number of decimal
@@ -610,3 +863,54 @@ impl From<&[u8]> for TagNum {
         Self(value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(timestamp: &str, precision: TimestampPrecision) {
+        let value = DataType::UtcTimestamp.decode(timestamp.as_bytes()).unwrap();
+        match &value {
+            DataTypeValue::UtcTimestamp(t) => assert_eq!(t.precision(), precision),
+            _ => panic!("Not a UtcTimestamp: {:?}", value),
+        }
+        assert_eq!(value.to_string(), timestamp);
+    }
+
+    #[test]
+    fn utc_timestamp_with_seconds() {
+        round_trip("20100304-07:59:30", TimestampPrecision::Seconds);
+    }
+
+    #[test]
+    fn utc_timestamp_with_millis() {
+        round_trip("20100304-07:59:30.120", TimestampPrecision::Millis);
+    }
+
+    #[test]
+    fn utc_timestamp_with_micros() {
+        round_trip("20100304-07:59:30.123450", TimestampPrecision::Micros);
+    }
+
+    #[test]
+    fn utc_timestamp_with_nanos() {
+        round_trip("20100304-07:59:30.000000001", TimestampPrecision::Nanos);
+    }
+
+    #[test]
+    fn utc_timestamp_with_other_precisions_is_invalid() {
+        assert_eq!(UtcTimestamp::parse(b"20100304-07:59:30."), None);
+        assert_eq!(UtcTimestamp::parse(b"20100304-07:59:30.1234"), None);
+        assert_eq!(UtcTimestamp::parse(b"20100304-07:59:30.+12"), None);
+        assert_eq!(UtcTimestamp::parse(b"20100304-07:59"), None);
+    }
+
+    #[test]
+    fn utc_timestamp_truncates_to_precision() {
+        let timestamp =
+            NaiveDateTime::parse_from_str("20100304-07:59:30.123456789", "%Y%m%d-%H:%M:%S%.f")
+                .unwrap();
+        let timestamp = UtcTimestamp::new(timestamp, TimestampPrecision::Millis);
+        assert_eq!(timestamp.to_string(), "20100304-07:59:30.123");
+    }
+}
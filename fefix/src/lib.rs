@@ -35,7 +35,10 @@ mod stream_iterator;
 pub mod transport;
 pub mod utils;
 
-pub use dictionary::{Dictionary, MsgType};
+pub use dictionary::{
+    AuditField, AuditRecord, DictConsistencyWarning, DictDiff, Dictionary, MsgType,
+    ValidationError, Validator,
+};
 pub use fefix_derive::*;
 pub use fix_codegen::codegen;
 pub use stream_iterator::StreamIterator;
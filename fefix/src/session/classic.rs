@@ -13,9 +13,12 @@
 //! point, namely [`Initiator::feed`] and [`Acceptor::feed`].
 
 use crate::app::slr;
+use crate::dt::{self, TimestampPrecision, UtcTimestamp};
 use boolinator::Boolinator;
 use futures_lite::prelude::*;
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::num::NonZeroU64;
 use std::ops::RangeInclusive;
 use std::time::Duration;
@@ -142,7 +145,7 @@ impl Default for SeqNumbers {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SeqNumberError {
     Recover,
     TooLow,
@@ -151,6 +154,178 @@ pub enum SeqNumberError {
 
 type CompID = String;
 
+/// The outcome of [`SessionStore::check_seq_num`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeqNumOutcome {
+    /// `MsgSeqNum <34>` is exactly as expected.
+    Ok,
+    /// `MsgSeqNum <34>` is higher than expected, i.e. some messages were lost
+    /// and must be resent.
+    Gap { expected: u64, received: u64 },
+    /// `MsgSeqNum <34>` is lower than expected, i.e. the message was already
+    /// received.
+    Duplicate,
+    /// `MsgSeqNum <34>` is missing or not a valid number.
+    Missing,
+}
+
+/// An in-memory store of [`SeqNumbers`] for many FIX sessions at once, each
+/// identified by its `SenderCompID <49>` and `TargetCompID <56>` as they appear
+/// on outbound messages. Sessions that were never seen before start from 1.
+#[derive(Debug, Clone, Default)]
+pub struct SessionStore {
+    sessions: HashMap<(CompID, CompID), SeqNumbers>,
+}
+
+impl SessionStore {
+    /// Creates an empty [`SessionStore`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the [`SeqNumbers`] of the session `sender`-`target`.
+    pub fn seq_numbers(&self, sender: &str, target: &str) -> SeqNumbers {
+        self.sessions
+            .get(&(sender.to_string(), target.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the expected seq. number of the next inbound message of the
+    /// session `sender`-`target`.
+    pub fn next_inbound(&self, sender: &str, target: &str) -> u64 {
+        self.seq_numbers(sender, target).next_inbound()
+    }
+
+    /// Returns the seq. number of the next outbound message of the session
+    /// `sender`-`target`.
+    pub fn next_outbound(&self, sender: &str, target: &str) -> u64 {
+        self.seq_numbers(sender, target).next_outbound()
+    }
+
+    pub fn incr_inbound(&mut self, sender: &str, target: &str) {
+        self.entry(sender, target).incr_inbound();
+    }
+
+    pub fn incr_outbound(&mut self, sender: &str, target: &str) {
+        self.entry(sender, target).incr_outbound();
+    }
+
+    /// Resets both seq. numbers of the session `sender`-`target` to 1, e.g.
+    /// after a Logon with `ResetSeqNumFlag <141>` set to "Y".
+    pub fn reset(&mut self, sender: &str, target: &str) {
+        self.sessions
+            .remove(&(sender.to_string(), target.to_string()));
+    }
+
+    /// Checks `MsgSeqNum <34>` of the inbound `message` against the expected
+    /// seq. number of its session. As `message` was sent by the counterparty,
+    /// its `TargetCompID <56>` is the sender of the session and its
+    /// `SenderCompID <49>` the target.
+    pub fn check_seq_num(&self, message: &slr::Message) -> SeqNumOutcome {
+        let comp_id = |tag: u32| match message.get_field(tag) {
+            Some(slr::FixFieldValue::String(s)) => s.as_str(),
+            _ => "",
+        };
+        let expected = self.next_inbound(comp_id(56), comp_id(49));
+        match seq_num_field(message, 34) {
+            Some(received) => match received.cmp(&expected) {
+                Ordering::Equal => SeqNumOutcome::Ok,
+                Ordering::Less => SeqNumOutcome::Duplicate,
+                Ordering::Greater => SeqNumOutcome::Gap { expected, received },
+            },
+            None => SeqNumOutcome::Missing,
+        }
+    }
+
+    /// Applies the inbound `SequenceReset <4>` `message` to the expected seq.
+    /// number of its session, which is identified just like in
+    /// [`SessionStore::check_seq_num`]. With `GapFillFlag <123>` set to "Y",
+    /// `NewSeqNo <36>` can only move the expected seq. number forward, and
+    /// [`SeqNumberError::TooLow`] is returned otherwise. Without it (i.e. in
+    /// Reset mode), `NewSeqNo <36>` is taken as is. Missing or invalid
+    /// `NewSeqNo <36>` values result in [`SeqNumberError::NoSeqNum`].
+    ///
+    /// Please refer to specs. §4.8 for more information.
+    pub fn apply_sequence_reset(&mut self, message: &slr::Message) -> Result<(), SeqNumberError> {
+        let comp_id = |tag: u32| match message.get_field(tag) {
+            Some(slr::FixFieldValue::String(s)) => s.as_str(),
+            _ => "",
+        };
+        let new_seq_num = seq_num_field(message, 36)
+            .filter(|n| *n > 0)
+            .ok_or(SeqNumberError::NoSeqNum)?;
+        let is_gap_fill = match message.get_field(123) {
+            Some(slr::FixFieldValue::String(s)) => s == "Y",
+            Some(value) => *value == slr::FixFieldValue::from('Y'),
+            None => false,
+        };
+        let seq_numbers = self.entry(comp_id(56), comp_id(49));
+        if is_gap_fill && new_seq_num < seq_numbers.next_inbound {
+            return Err(SeqNumberError::TooLow);
+        }
+        seq_numbers.next_inbound = new_seq_num;
+        Ok(())
+    }
+
+    fn entry(&mut self, sender: &str, target: &str) -> &mut SeqNumbers {
+        self.sessions
+            .entry((sender.to_string(), target.to_string()))
+            .or_default()
+    }
+}
+
+/// Returns the value of the seq. number field `tag` of `message`, either
+/// decoded as an integer or still as a string (e.g. from the JSON decoder).
+fn seq_num_field(message: &slr::Message, tag: u32) -> Option<u64> {
+    match message.get_field(tag) {
+        Some(slr::FixFieldValue::Value(dt::DataTypeValue::Int(dt::Int(n)))) => {
+            u64::try_from(*n).ok()
+        }
+        Some(slr::FixFieldValue::String(s)) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// An in-memory log of encoded outbound messages by `MsgSeqNum <34>`, which
+/// can be replayed to serve `ResendRequest <2>` messages.
+#[derive(Debug, Clone, Default)]
+pub struct MessageLog {
+    messages: BTreeMap<u64, Vec<u8>>,
+}
+
+impl MessageLog {
+    /// Creates an empty [`MessageLog`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the encoded message `data` with seq. number `seq_num`,
+    /// replacing any other message with the same seq. number.
+    pub fn insert(&mut self, seq_num: u64, data: Vec<u8>) {
+        self.messages.insert(seq_num, data);
+    }
+
+    /// Returns all stored messages from `begin` to `end` (inclusive), in
+    /// ascending seq. number order. Just like `EndSeqNo <16>`, an `end` of 0
+    /// means "infinity".
+    pub fn get_range(&self, begin: u64, end: u64) -> Vec<&[u8]> {
+        let end = if end == 0 { u64::MAX } else { end };
+        if begin > end {
+            return Vec::new();
+        }
+        self.messages
+            .range(begin..=end)
+            .map(|(_, data)| &data[..])
+            .collect()
+    }
+
+    /// Removes all messages from `self`, e.g. after a seq. number reset.
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
+
 mod acceptor {
     use super::*;
 
@@ -161,6 +336,8 @@ mod acceptor {
         delivery_threshold: Duration,
         company_id: String,
         environment: Environment,
+        clock: fn() -> chrono::NaiveDateTime,
+        sending_time_precision: TimestampPrecision,
     }
 
     impl Configuration {
@@ -170,6 +347,8 @@ mod acceptor {
                 delivery_threshold: Duration::from_secs(60),
                 company_id,
                 environment: Environment::ProductionDisallowTest,
+                clock: || chrono::Utc::now().naive_utc(),
+                sending_time_precision: TimestampPrecision::Millis,
             }
         }
 
@@ -191,6 +370,21 @@ mod acceptor {
             self
         }
 
+        /// Sets the clock which `SendingTime <52>` of outbound messages is read
+        /// from. The system clock in UTC is used by default.
+        pub fn with_clock(&mut self, clock: fn() -> chrono::NaiveDateTime) -> &mut Self {
+            self.clock = clock;
+            self
+        }
+
+        /// Sets the number of fractional digits of `SendingTime <52>` on
+        /// outbound messages, as counterparties differ in what they accept.
+        /// Milliseconds are used by default.
+        pub fn with_sending_time_precision(&mut self, precision: TimestampPrecision) -> &mut Self {
+            self.sending_time_precision = precision;
+            self
+        }
+
         pub fn acceptor(self) -> Acceptor {
             Acceptor::new(self)
         }
@@ -299,7 +493,7 @@ mod acceptor {
                     msg.add_int(7, self.seq_numbers().next_inbound() as i64);
                     msg.add_int(16, message.seq_num().unwrap() as i64);
                     msg.add_str(58, errs::production_env());
-                    to.push(EventOutbound::Message(add_time_to_msg(msg)));
+                    to.push(EventOutbound::Message(self.add_time_to_msg(msg)));
                     return;
                 }
                 _ => (),
@@ -322,7 +516,7 @@ mod acceptor {
                     msg.add_int(7, self.seq_numbers().next_inbound() as i64);
                     msg.add_int(16, message.seq_num().unwrap() as i64);
                     msg.add_str(58, errs::missing_field("MsgSeqNum", 34));
-                    to.push(EventOutbound::Message(add_time_to_msg(msg)));
+                    to.push(EventOutbound::Message(self.add_time_to_msg(msg)));
                     return;
                 }
                 // Refer to specs. §4.8 for more information.
@@ -335,12 +529,12 @@ mod acceptor {
                     response.add_int(16, message.seq_num().unwrap() as i64);
                     self.seq_numbers.incr_outbound();
                     // TODO: add other details to response message.
-                    to.push(EventOutbound::Message(add_time_to_msg(response)));
+                    to.push(EventOutbound::Message(self.add_time_to_msg(response)));
                     return;
                 }
                 Err(SeqNumberError::TooLow) => {
                     let msg = self.generate_error_seqnum_too_low();
-                    to.push(EventOutbound::Message(add_time_to_msg(msg)));
+                    to.push(EventOutbound::Message(self.add_time_to_msg(msg)));
                 }
             };
             if self.state == State::Disconnected && msg_type != Some("A") {
@@ -360,7 +554,7 @@ mod acceptor {
                     slr::FixFieldValue::String(self.config.company_id.clone()),
                 );
                 self.seq_numbers.incr_outbound();
-                to.push(EventOutbound::Message(add_time_to_msg(response)));
+                to.push(EventOutbound::Message(self.add_time_to_msg(response)));
                 self.state = State::Active;
             }
         }
@@ -372,7 +566,7 @@ mod acceptor {
             response.add_str(49, self.config.company_id.as_str());
             response.add_int(7, self.seq_numbers().next_outbound() as i64);
             response.add_str(58, error_message);
-            add_time_to_msg(response)
+            self.add_time_to_msg(response)
         }
 
         fn generate_heartbeat_message(&mut self) -> slr::Message {
@@ -380,16 +574,16 @@ mod acceptor {
             heartbeat.add_str(35, "0");
             heartbeat.add_str(49, self.config.company_id.as_str());
             heartbeat.add_int(7, self.seq_numbers().next_outbound() as i64);
-            add_time_to_msg(heartbeat)
+            self.add_time_to_msg(heartbeat)
         }
-    }
 
-    fn add_time_to_msg(mut msg: slr::Message) -> slr::Message {
-        // https://www.onixs.biz/fix-dictionary/4.4/index.html#UTCTimestamp.
-        let time = chrono::Utc::now();
-        let timestamp = time.format("%Y%m%d-%H:%M:%S.%.3f");
-        msg.add_str(52, timestamp.to_string());
-        msg
+        fn add_time_to_msg(&self, mut msg: slr::Message) -> slr::Message {
+            // https://www.onixs.biz/fix-dictionary/4.4/index.html#UTCTimestamp.
+            let time = (self.config.clock)();
+            let timestamp = UtcTimestamp::new(time, self.config.sending_time_precision);
+            msg.add_str(52, timestamp.to_string());
+            msg
+        }
     }
 
     #[derive(Clone, Debug, PartialEq, Eq)]
@@ -674,6 +868,33 @@ impl From<u32> for SessionRejectReason {
     }
 }
 
+impl From<SessionRejectReason> for u32 {
+    fn from(reason: SessionRejectReason) -> Self {
+        match reason {
+            SessionRejectReason::InvalidTagNumber => 0,
+            SessionRejectReason::RequiredTagMissing => 1,
+            SessionRejectReason::TagNotDefinedForThisMessageType => 2,
+            SessionRejectReason::UndefinedTag => 3,
+            SessionRejectReason::TagSpecifiedWithoutAValue => 4,
+            SessionRejectReason::ValueIsIncorrect => 5,
+            SessionRejectReason::IncorrectDataFormatForValue => 6,
+            SessionRejectReason::DecryptionProblem => 7,
+            SessionRejectReason::SignatureProblem => 8,
+            SessionRejectReason::CompIDProblem => 9,
+            SessionRejectReason::SendingTimeAccuracyProblem => 10,
+            SessionRejectReason::InvalidMsgType => 11,
+            SessionRejectReason::XMLValidationError => 12,
+            SessionRejectReason::TagAppearsMoreThanOnce => 13,
+            SessionRejectReason::TagSpecifiedOutOfRequiredOrder => 14,
+            SessionRejectReason::RepeatingGroupFieldsOutOfOrder => 15,
+            SessionRejectReason::IncorrectNumInGroupCountForRepeatingGroup => 16,
+            SessionRejectReason::FieldDelimiterInFieldValue => 17,
+            SessionRejectReason::InvalidUnsupportedAppVersion => 18,
+            SessionRejectReason::Other => 99,
+        }
+    }
+}
+
 /// Error messages generation.
 pub mod errs {
     pub fn heartbeat_exact(secs: u64) -> String {
@@ -866,4 +1087,180 @@ mod test {
         // The second one is ignored.
         assert!(events.next().is_none());
     }
+
+    #[test]
+    fn sending_time_precision() {
+        fn fixed_clock() -> chrono::NaiveDateTime {
+            chrono::NaiveDate::from_ymd(2021, 3, 4).and_hms_nano(7, 59, 30, 123_456_789)
+        }
+        let expected = [
+            (TimestampPrecision::Seconds, "20210304-07:59:30"),
+            (TimestampPrecision::Millis, "20210304-07:59:30.123"),
+            (TimestampPrecision::Micros, "20210304-07:59:30.123456"),
+            (TimestampPrecision::Nanos, "20210304-07:59:30.123456789"),
+        ];
+        for (precision, sending_time) in expected.iter() {
+            let mut config = Configuration::new(COMPANY_ID.to_string());
+            config.with_clock(fixed_clock);
+            config.with_sending_time_precision(*precision);
+            let mut acceptor = config.acceptor();
+            let mut events = acceptor.notify(EventInbound::HeartbeatIsDue);
+            match events.next().unwrap() {
+                EventOutbound::Message(heartbeat) => assert_eq!(
+                    *heartbeat.get_field(52).unwrap(),
+                    slr::FixFieldValue::String(sending_time.to_string())
+                ),
+                EventOutbound::Terminate => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn session_store_increments_seq_numbers_per_session() {
+        let mut store = SessionStore::new();
+        assert_eq!(store.next_inbound("A", "B"), 1);
+        assert_eq!(store.next_outbound("A", "B"), 1);
+        store.incr_inbound("A", "B");
+        store.incr_outbound("A", "B");
+        store.incr_outbound("A", "B");
+        assert_eq!(store.next_inbound("A", "B"), 2);
+        assert_eq!(store.next_outbound("A", "B"), 3);
+        // Sessions are independent from each other, even if the comp IDs are
+        // just swapped.
+        assert_eq!(store.next_outbound("B", "A"), 1);
+        store.incr_inbound("B", "A");
+        assert_eq!(store.next_inbound("A", "B"), 2);
+    }
+
+    #[test]
+    fn session_store_reset() {
+        let mut store = SessionStore::new();
+        store.incr_inbound("A", "B");
+        store.incr_outbound("A", "B");
+        store.incr_outbound("A", "C");
+        store.reset("A", "B");
+        assert_eq!(store.next_inbound("A", "B"), 1);
+        assert_eq!(store.next_outbound("A", "B"), 1);
+        assert_eq!(store.next_outbound("A", "C"), 2);
+    }
+
+    fn inbound_message(seq_num: i64) -> slr::Message {
+        let mut message = slr::Message::new();
+        message.add_str(35, "0");
+        message.add_str(49, "THEM");
+        message.add_str(56, "US");
+        message.add_int(34, seq_num);
+        message
+    }
+
+    #[test]
+    fn check_seq_num_outcomes() {
+        let mut store = SessionStore::new();
+        store.incr_inbound("US", "THEM");
+        assert_eq!(store.check_seq_num(&inbound_message(2)), SeqNumOutcome::Ok);
+        assert_eq!(
+            store.check_seq_num(&inbound_message(1)),
+            SeqNumOutcome::Duplicate
+        );
+        assert_eq!(
+            store.check_seq_num(&inbound_message(5)),
+            SeqNumOutcome::Gap {
+                expected: 2,
+                received: 5
+            }
+        );
+        let mut message = inbound_message(2);
+        message.fields.remove(&34);
+        assert_eq!(store.check_seq_num(&message), SeqNumOutcome::Missing);
+        message.add_str(34, "x");
+        assert_eq!(store.check_seq_num(&message), SeqNumOutcome::Missing);
+    }
+
+    #[test]
+    fn check_seq_num_of_string_field() {
+        let mut store = SessionStore::new();
+        store.incr_inbound("US", "THEM");
+        let mut message = inbound_message(2);
+        message.add_str(34, "2");
+        assert_eq!(store.check_seq_num(&message), SeqNumOutcome::Ok);
+        message.add_str(34, "7");
+        assert_eq!(
+            store.check_seq_num(&message),
+            SeqNumOutcome::Gap {
+                expected: 2,
+                received: 7
+            }
+        );
+    }
+
+    fn sequence_reset(new_seq_num: i64, gap_fill: Option<char>) -> slr::Message {
+        let mut message = inbound_message(2);
+        message.add_str(35, "4");
+        message.add_int(36, new_seq_num);
+        if let Some(flag) = gap_fill {
+            message.add_field(123, slr::FixFieldValue::from(flag));
+        }
+        message
+    }
+
+    #[test]
+    fn sequence_reset_gap_fill_advances() {
+        let mut store = SessionStore::new();
+        store.incr_inbound("US", "THEM");
+        assert_eq!(
+            store.apply_sequence_reset(&sequence_reset(10, Some('Y'))),
+            Ok(())
+        );
+        assert_eq!(store.next_inbound("US", "THEM"), 10);
+        assert_eq!(store.next_outbound("US", "THEM"), 1);
+    }
+
+    #[test]
+    fn sequence_reset_gap_fill_cant_lower() {
+        let mut store = SessionStore::new();
+        for _ in 0..5 {
+            store.incr_inbound("US", "THEM");
+        }
+        assert_eq!(
+            store.apply_sequence_reset(&sequence_reset(3, Some('Y'))),
+            Err(SeqNumberError::TooLow)
+        );
+        assert_eq!(store.next_inbound("US", "THEM"), 6);
+        let mut message = sequence_reset(3, Some('Y'));
+        message.fields.remove(&36);
+        assert_eq!(
+            store.apply_sequence_reset(&message),
+            Err(SeqNumberError::NoSeqNum)
+        );
+    }
+
+    #[test]
+    fn sequence_reset_hard_reset() {
+        let mut store = SessionStore::new();
+        for _ in 0..5 {
+            store.incr_inbound("US", "THEM");
+        }
+        assert_eq!(
+            store.apply_sequence_reset(&sequence_reset(3, Some('N'))),
+            Ok(())
+        );
+        assert_eq!(store.next_inbound("US", "THEM"), 3);
+        assert_eq!(
+            store.apply_sequence_reset(&sequence_reset(20, None)),
+            Ok(())
+        );
+        assert_eq!(store.next_inbound("US", "THEM"), 20);
+    }
+
+    #[test]
+    fn message_log_sub_range() {
+        let mut log = MessageLog::new();
+        for seq_num in 1..=5 {
+            log.insert(seq_num, format!("34={}|", seq_num).into_bytes());
+        }
+        let messages = log.get_range(2, 4);
+        assert_eq!(messages, vec![&b"34=2|"[..], &b"34=3|"[..], &b"34=4|"[..]]);
+        assert_eq!(log.get_range(4, 0).len(), 2);
+        assert!(log.get_range(6, 10).is_empty());
+    }
 }
@@ -21,6 +21,58 @@ pub trait Buffer {
     fn extend_from_slice(&mut self, extend: &[u8]);
 }
 
+/// A source of reusable byte buffers.
+///
+/// High-throughput encoders can draw buffers from a [`BufferPool`] (see
+/// [`Encoder::encode_with_pool`](crate::codec::Encoder::encode_with_pool))
+/// instead of allocating a fresh [`Vec`] for every message.
+pub trait BufferPool {
+    /// Returns an empty buffer, possibly recycled from a previous
+    /// [`BufferPool::release`].
+    fn acquire(&mut self) -> Vec<u8>;
+
+    /// Gives `buffer` back to `self`, so that it can be reused by later calls
+    /// to [`BufferPool::acquire`].
+    fn release(&mut self, buffer: Vec<u8>);
+}
+
+/// A simple [`BufferPool`] that keeps released buffers in a free list.
+#[derive(Debug, Default)]
+pub struct VecPool {
+    buffers: Vec<Vec<u8>>,
+    allocations: usize,
+}
+
+impl VecPool {
+    /// Creates a new [`VecPool`] without any buffers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of buffers that `self` had to allocate because its
+    /// free list was empty.
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+}
+
+impl BufferPool for VecPool {
+    fn acquire(&mut self) -> Vec<u8> {
+        match self.buffers.pop() {
+            Some(buffer) => buffer,
+            None => {
+                self.allocations += 1;
+                Vec::new()
+            }
+        }
+    }
+
+    fn release(&mut self, mut buffer: Vec<u8>) {
+        buffer.clear();
+        self.buffers.push(buffer);
+    }
+}
+
 /// A [`Vec`]-backed [`Buffer`] implementation.
 #[derive(Debug)]
 pub struct GrowableBuffer<'a> {